@@ -1,13 +1,33 @@
-use crate::services::{CryptoService, SshConfigService, SshKeyService};
+use crate::data_dir;
+use crate::error::AppError;
+use crate::profiles::VaultProfiles;
+use crate::services::agent::{default_socket_path, ConfirmNotifier, AGENT_CONFIRM_EVENT};
+use crate::services::rate_limit::FailureOutcome;
+use crate::services::session::{DEFAULT_IDLE_TIMEOUT_MINUTES, VAULT_LOCKED_EVENT};
+use crate::services::{
+    AgentIdentity, AgentService, CryptoService, EncryptedData, KeyFormatService, RateLimiter,
+    SessionService, SshConfigService, SshKeyService, SystemAgentClient,
+};
 use crate::storage::StorageService;
-use crate::types::{KeyGenerationParams, SshKeyPair};
+use crate::types::{
+    AgentKeyOptions, AgentSignRecord, AgentStatus, AuthGuardState, DataDirLocation, FailedAttempt,
+    IdentityImportFailure, IdentityImportResult, KdfParams, KeyGenerationParams, KeySlot,
+    LockoutAction, LockoutPolicy, SshKeyPair, SystemAgentKey, Vault, VaultBackupList, VaultHeader,
+    VaultProfileInfo, VaultSettings,
+};
+use crate::vault::{parse_vault, VaultRepository};
 use base64::{engine::general_purpose, Engine as _};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Mutex;
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
+use zeroize::Zeroizing;
 
 type CryptoState<'a> = State<'a, Mutex<CryptoService>>;
 type StorageState<'a> = State<'a, Mutex<StorageService>>;
+type SessionState<'a> = State<'a, Mutex<SessionService>>;
+type ProfilesState<'a> = State<'a, Mutex<VaultProfiles>>;
+type AgentState<'a> = State<'a, Mutex<AgentService>>;
 
 // 检查是否已初始化
 #[tauri::command]
@@ -16,83 +36,894 @@ pub async fn is_initialized(storage: StorageState<'_>) -> Result<bool, String> {
     Ok(storage.is_initialized())
 }
 
+// 存储文件损坏时检查是否可以从上一版本（.prev）恢复
+#[tauri::command]
+pub async fn can_recover_storage(storage: StorageState<'_>) -> Result<bool, String> {
+    let storage = storage.lock().map_err(|e| e.to_string())?;
+    Ok(storage.can_recover_from_prev())
+}
+
+// 用上一版本覆盖已损坏的存储文件，之后需重新解锁
+#[tauri::command]
+pub async fn recover_storage(storage: StorageState<'_>) -> Result<bool, String> {
+    let mut storage = storage.lock().map_err(|e| e.to_string())?;
+    storage.recover_from_prev().map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
 // 初始化应用
 #[tauri::command]
 pub async fn initialize_app(
     master_key: String,
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
+    session_state: SessionState<'_>,
 ) -> Result<bool, String> {
     let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
     let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
 
-    // 设置主密钥（这会生成盐值和派生密钥）
+    initialize_vault(&mut crypto, &mut storage, &master_key)?;
+    if let Err(e) = start_session(&session_state, &crypto, &mut storage) {
+        crypto.clear_master_key();
+        return Err(e);
+    }
+    Ok(true)
+}
+
+// 以新的主密码创建空存储
+fn initialize_vault(
+    crypto: &mut CryptoService,
+    storage: &mut StorageService,
+    master_key: &str,
+) -> Result<(), String> {
+    // 设置主密钥（这会生成数据密钥和密码槽）
     crypto
-        .set_master_key(&master_key)
+        .set_master_key(master_key)
         .map_err(|e| e.to_string())?;
 
     // 验证密码设置成功
-    if !crypto.verify_password(&master_key) {
+    if !crypto.verify_password(master_key) {
         return Err("密码验证失败".to_string());
     }
 
-    // 获取盐值和哈希
-    let salt = crypto.get_salt().unwrap();
-    let master_key_hash = crypto.get_master_key_hash().unwrap();
+    // 创建初始存储并加密保存（密钥槽作为元数据存储，不在加密数据中）
+    let mut vault = Vault::default();
+    vault.metadata.created_at = Some(chrono::Utc::now());
+    VaultRepository::new(crypto, storage).save(&mut vault)?;
+    Ok(())
+}
+
+// 用户认证
+#[tauri::command]
+pub async fn authenticate(
+    master_key: String,
+    keyfile_path: Option<String>,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+    session_state: SessionState<'_>,
+) -> Result<bool, String> {
+    let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    // 加载存储文件
+    let (encrypted_data, header) = storage.load_encrypted_data().map_err(|e| e.to_string())?;
+    let mut guard = check_rate_limit(&storage, false)?;
+
+    // 设置头部并尝试用主密码（及密钥文件）解锁数据密钥
+    crypto.set_header(header);
+    crypto.set_keyfile(read_keyfile(keyfile_path.as_deref())?);
+    let is_valid = crypto.try_unlock(&master_key).map_err(|e| e.to_string())?;
+
+    if !is_valid {
+        record_failed_attempt(&mut crypto, &mut storage, &mut guard, "authenticate")?;
+        return Ok(false);
+    }
+    record_successful_attempt(&mut storage, &mut guard)?;
+
+    // 密码正确但无法解密，说明头部（版本或密钥槽）被篡改
+    let Ok(plaintext) = crypto.decrypt(&encrypted_data).map(Zeroizing::new) else {
+        crypto.clear_master_key();
+        return Err(AppError::HeaderTampered.to_string());
+    };
 
-    // 创建初始存储（注意：不将master_key_hash和salt存储在加密数据中）
-    let initial_data = serde_json::json!({
-        "keys": [],
-        "config": {
-            "theme": "light",
-            "auto_backup": true,
-            "backup_retention": 10,
-            "default_key_type": "Ed25519",
-            "default_key_size": 256
+    if crypto.needs_upgrade() {
+        if crypto.is_legacy() {
+            // 1.x 存储在解锁后透明升级为数据密钥加密，并重新加密全部数据
+            rekey_vault(&mut crypto, &mut storage, &plaintext, &master_key)?;
+        } else {
+            // 较弱 KDF 参数的密码槽只需重新包装数据密钥
+            rewrap_password_slot(&mut crypto, &mut storage, &encrypted_data, &master_key)?;
         }
-    });
+    } else if encrypted_data.associated_data.is_none() {
+        // 2.0 存储的头部未参与认证，按当前格式重新加密
+        let key_slots = crypto.key_slots().to_vec();
+        update_key_slots(&mut crypto, &mut storage, &encrypted_data, key_slots)?;
+    }
 
-    // 加密并保存
-    let encrypted = crypto
-        .encrypt(initial_data.to_string().as_bytes())
+    // 数据无法读取（如由更新版本写入）时不保持解锁状态
+    if let Err(e) = start_session(&session_state, &crypto, &mut storage) {
+        crypto.clear_master_key();
+        return Err(e);
+    }
+    Ok(true)
+}
+
+// 修改主密码（验证旧密码后用新密码重新包装数据密钥）
+#[tauri::command]
+pub async fn change_master_password(
+    old_master_key: String,
+    new_master_key: String,
+    keyfile_path: Option<String>,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<bool, String> {
+    if new_master_key.is_empty() {
+        return Err("新密码不能为空".to_string());
+    }
+
+    let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    // 只在当前会话中修改，密钥槽直接更新到会话上，ssh-agent 等后台服务不受影响
+    if !crypto.is_authenticated() {
+        return Err("请先解锁".to_string());
+    }
+
+    let (encrypted_data, header) = storage.load_encrypted_data().map_err(|e| e.to_string())?;
+    let mut guard = check_rate_limit(&storage, false)?;
+
+    // 使用独立的实例验证旧密码，失败时不影响当前会话
+    // 未指定密钥文件时沿用当前会话的密钥文件
+    let keyfile = match read_keyfile(keyfile_path.as_deref())? {
+        Some(keyfile) => Some(keyfile),
+        None => crypto.keyfile_hash().copied(),
+    };
+
+    let mut current = CryptoService::new();
+    current.set_header(header);
+    current.set_keyfile(keyfile);
+    if !current
+        .try_unlock(&old_master_key)
+        .map_err(|e| e.to_string())?
+    {
+        record_failed_attempt(
+            &mut crypto,
+            &mut storage,
+            &mut guard,
+            "change_master_password",
+        )?;
+        return Ok(false);
+    }
+    record_successful_attempt(&mut storage, &mut guard)?;
+
+    if current.is_legacy() {
+        let plaintext = Zeroizing::new(
+            current
+                .decrypt(&encrypted_data)
+                .map_err(|e| e.to_string())?,
+        );
+        rekey_vault(&mut crypto, &mut storage, &plaintext, &new_master_key)?;
+    } else {
+        set_password_keyfile(&mut crypto, &mut storage, &new_master_key, keyfile)?;
+    }
+    Ok(true)
+}
+
+// 生成（或重新生成）恢复密钥，旧的恢复密钥随即失效；明文只在此返回一次
+#[tauri::command]
+pub async fn generate_recovery_key(
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<String, String> {
+    let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    if !crypto.is_authenticated() {
+        return Err("请先解锁".to_string());
+    }
+
+    let (encrypted_data, _header) = storage.load_encrypted_data().map_err(|e| e.to_string())?;
+    let recovery_key = CryptoService::generate_recovery_key();
+    let key_slots = crypto
+        .rewrap_recovery_key_slot(&recovery_key)
+        .map_err(|e| e.to_string())?;
+    update_key_slots(&mut crypto, &mut storage, &encrypted_data, key_slots)?;
+
+    Ok(recovery_key.to_string())
+}
+
+// 撤销恢复密钥
+#[tauri::command]
+pub async fn revoke_recovery_key(
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<bool, String> {
+    let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    if !crypto.is_authenticated() {
+        return Err("请先解锁".to_string());
+    }
+    if !crypto.has_recovery_key() {
+        return Ok(false);
+    }
+
+    let (encrypted_data, _header) = storage.load_encrypted_data().map_err(|e| e.to_string())?;
+    let key_slots = crypto.without_recovery_key_slot();
+    update_key_slots(&mut crypto, &mut storage, &encrypted_data, key_slots)?;
+    Ok(true)
+}
+
+// 检查是否已设置恢复密钥
+#[tauri::command]
+pub async fn has_recovery_key(storage_state: StorageState<'_>) -> Result<bool, String> {
+    let storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    let (_encrypted_data, header) = storage.load_encrypted_data().map_err(|e| e.to_string())?;
+    let mut crypto = CryptoService::new();
+    crypto.set_header(header);
+    Ok(crypto.has_recovery_key())
+}
+
+// 使用恢复密钥解锁并设置新的主密码
+#[tauri::command]
+pub async fn recover_with_recovery_key(
+    recovery_key: String,
+    new_master_key: String,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+    session_state: SessionState<'_>,
+) -> Result<bool, String> {
+    if new_master_key.is_empty() {
+        return Err("新密码不能为空".to_string());
+    }
+
+    let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    let (encrypted_data, header) = storage.load_encrypted_data().map_err(|e| e.to_string())?;
+
+    // 密码解锁被策略禁用后，恢复密钥仍然可用（但同样受退避限制）
+    let mut guard = check_rate_limit(&storage, true)?;
+
+    let mut current = CryptoService::new();
+    current.set_header(header);
+    if !current.unlock_with_recovery_key(&recovery_key) {
+        record_failed_attempt(
+            &mut crypto,
+            &mut storage,
+            &mut guard,
+            "recover_with_recovery_key",
+        )?;
+        return Ok(false);
+    }
+    record_successful_attempt(&mut storage, &mut guard)?;
+
+    // 恢复密钥槽保持不变，只替换密码槽
+    rewrap_password_slot(&mut current, &mut storage, &encrypted_data, &new_master_key)?;
+    start_session(&session_state, &current, &mut storage)?;
+    *crypto = current;
+    Ok(true)
+}
+
+// 立即锁定：清除内存中的数据密钥
+#[tauri::command]
+pub async fn lock_vault(app: AppHandle, crypto_state: CryptoState<'_>) -> Result<bool, String> {
+    let was_unlocked = {
+        let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+        let was_unlocked = crypto.is_authenticated();
+        crypto.clear_master_key();
+        was_unlocked
+    };
+
+    if was_unlocked {
+        app.emit(VAULT_LOCKED_EVENT, "manual")
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(was_unlocked)
+}
+
+// 记录用户活动，重置空闲计时
+#[tauri::command]
+pub async fn touch_session(session_state: SessionState<'_>) -> Result<(), String> {
+    let mut session = session_state.lock().map_err(|e| e.to_string())?;
+    session.touch();
+    Ok(())
+}
+
+// 获取自动锁定时间（分钟）
+#[tauri::command]
+pub async fn get_auto_lock_minutes(session_state: SessionState<'_>) -> Result<u32, String> {
+    let session = session_state.lock().map_err(|e| e.to_string())?;
+    Ok(session.idle_timeout_minutes())
+}
+
+// 设置自动锁定时间（分钟），并保存到存储配置中
+#[tauri::command]
+pub async fn set_auto_lock_minutes(
+    minutes: u32,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+    session_state: SessionState<'_>,
+) -> Result<bool, String> {
+    session_state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .set_idle_timeout_minutes(minutes)
         .map_err(|e| e.to_string())?;
 
-    // 保存到本地文件（master_key_hash和salt作为元数据存储）
+    with_vault(&crypto_state, &storage_state, |repo| {
+        repo.update_settings(|settings| settings.auto_lock_minutes = Some(minutes))?;
+        Ok(true)
+    })
+}
+
+// 后台线程定期调用：会话空闲超时后清除数据密钥并通知前端返回解锁页面
+pub fn lock_if_idle(app: &AppHandle) {
+    let is_idle = match app.state::<Mutex<SessionService>>().lock() {
+        Ok(session) => session.is_idle(),
+        Err(_) => return,
+    };
+    if !is_idle {
+        return;
+    }
+
+    let locked = match app.state::<Mutex<CryptoService>>().lock() {
+        Ok(mut crypto) if crypto.is_authenticated() => {
+            crypto.clear_master_key();
+            true
+        }
+        _ => false,
+    };
+    if locked {
+        let _ = app.emit(VAULT_LOCKED_EVENT, "idle");
+    }
+}
+
+// 解锁成功后重置空闲计时，并应用存储配置中的自动锁定时间与自动备份策略
+fn start_session(
+    session_state: &SessionState<'_>,
+    crypto: &CryptoService,
+    storage: &mut StorageService,
+) -> Result<(), String> {
+    let settings = load_session_settings(crypto, storage).map_err(|e| e.to_string())?;
+    storage.set_backup_policy(settings.auto_backup, settings.backup_retention);
+
+    let minutes = settings
+        .auto_lock_minutes
+        .unwrap_or(DEFAULT_IDLE_TIMEOUT_MINUTES);
+
+    let mut session = session_state.lock().map_err(|e| e.to_string())?;
+    if session.set_idle_timeout_minutes(minutes).is_err() {
+        session
+            .set_idle_timeout_minutes(DEFAULT_IDLE_TIMEOUT_MINUTES)
+            .map_err(|e| e.to_string())?;
+    }
+    session.touch();
+    Ok(())
+}
+
+// 解锁后执行结构迁移并读取配置；只有其他进程正占用存储时才先按默认配置解锁，
+// 其余错误（更新版本写入、数据损坏、读取失败等）都交给调用方清除密钥
+fn load_session_settings(
+    crypto: &CryptoService,
+    storage: &mut StorageService,
+) -> Result<VaultSettings, AppError> {
+    match VaultRepository::new(crypto, storage).migrate() {
+        Ok(vault) => Ok(vault.settings),
+        Err(AppError::StorageLocked) => Ok(VaultSettings::default()),
+        Err(e) => Err(e),
+    }
+}
+
+// 列出存储文件的加密快照，并附带最近一次创建快照失败的原因
+#[tauri::command]
+pub async fn list_vault_backups(
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<VaultBackupList, String> {
+    let crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    if !crypto.is_authenticated() {
+        return Err("请先解锁".to_string());
+    }
+    Ok(VaultBackupList {
+        backups: storage.list_backups().map_err(|e| e.to_string())?,
+        last_error: storage.last_backup_error().map(str::to_string),
+    })
+}
+
+// 用快照当时的主密码解锁快照，预览其中的密钥列表（不返回私钥）
+#[tauri::command]
+pub async fn preview_vault_backup(
+    backup_name: String,
+    master_key: String,
+    keyfile_path: Option<String>,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<Vec<SshKeyPair>, String> {
+    let crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    let (snapshot, encrypted_data) = unlock_backup(
+        &crypto,
+        &storage,
+        &backup_name,
+        &master_key,
+        keyfile_path.as_deref(),
+    )?
+    .ok_or("快照的主密码不正确")?;
+
+    let plaintext = Zeroizing::new(
+        snapshot
+            .decrypt(&encrypted_data)
+            .map_err(|e| e.to_string())?,
+    );
+    let mut keys = parse_vault(&plaintext)?.keys;
+    for key in &mut keys {
+        key.private_key.clear();
+    }
+    Ok(keys)
+}
+
+// 从快照恢复：当前内容先保留为快照，会话切换到快照的密钥槽
+#[tauri::command]
+pub async fn restore_vault_backup(
+    backup_name: String,
+    master_key: String,
+    keyfile_path: Option<String>,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+    session_state: SessionState<'_>,
+) -> Result<bool, String> {
+    let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    let Some((snapshot, _encrypted_data)) = unlock_backup(
+        &crypto,
+        &storage,
+        &backup_name,
+        &master_key,
+        keyfile_path.as_deref(),
+    )?
+    else {
+        return Ok(false);
+    };
+
     storage
-        .save_encrypted_data(&encrypted, &salt, &master_key_hash)
+        .restore_backup(&backup_name)
         .map_err(|e| e.to_string())?;
+    start_session(&session_state, &snapshot, &mut storage)?;
+    *crypto = snapshot;
+    Ok(true)
+}
+
+// 列出所有命名存储
+#[tauri::command]
+pub async fn list_vaults(
+    crypto_state: CryptoState<'_>,
+    profiles_state: ProfilesState<'_>,
+) -> Result<Vec<VaultProfileInfo>, String> {
+    let crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let profiles = profiles_state.lock().map_err(|e| e.to_string())?;
+
+    let mut vaults = profiles.list().map_err(|e| e.to_string())?;
+    for vault in &mut vaults {
+        vault.is_unlocked = vault.is_active && crypto.is_authenticated();
+    }
+    Ok(vaults)
+}
+
+// 当前打开的存储名称
+#[tauri::command]
+pub async fn get_active_vault(profiles_state: ProfilesState<'_>) -> Result<String, String> {
+    let profiles = profiles_state.lock().map_err(|e| e.to_string())?;
+    Ok(profiles.active().to_string())
+}
+
+// 创建命名存储并设置其主密码，不影响当前打开的存储
+#[tauri::command]
+pub async fn create_vault(
+    name: String,
+    master_key: String,
+    profiles_state: ProfilesState<'_>,
+) -> Result<bool, String> {
+    if master_key.is_empty() {
+        return Err("主密码不能为空".to_string());
+    }
 
+    let mut profiles = profiles_state.lock().map_err(|e| e.to_string())?;
+    let path = profiles.create(&name).map_err(|e| e.to_string())?;
+
+    let mut crypto = CryptoService::new();
+    let result = StorageService::with_path(path)
+        .map_err(|e| e.to_string())
+        .and_then(|mut storage| initialize_vault(&mut crypto, &mut storage, &master_key));
+    if let Err(e) = result {
+        // 初始化失败时不留下无法解锁的空存储
+        let _ = profiles.delete(&name);
+        return Err(e);
+    }
     Ok(true)
 }
 
-// 用户认证
+// 切换当前存储：锁定正在使用的存储，切换后需重新解锁
 #[tauri::command]
-pub async fn authenticate(
+pub async fn switch_vault(
+    name: String,
+    app: AppHandle,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+    profiles_state: ProfilesState<'_>,
+) -> Result<bool, String> {
+    let was_unlocked = switch_active_vault(&crypto_state, &storage_state, &profiles_state, &name)?;
+    if was_unlocked {
+        app.emit(VAULT_LOCKED_EVENT, "switch")
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(true)
+}
+
+// 切换到指定存储并用其主密码解锁
+#[tauri::command]
+pub async fn open_vault(
+    name: String,
     master_key: String,
+    keyfile_path: Option<String>,
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
+    session_state: SessionState<'_>,
+    profiles_state: ProfilesState<'_>,
+) -> Result<bool, String> {
+    // 由前端主动发起，不再发送锁定事件
+    switch_active_vault(&crypto_state, &storage_state, &profiles_state, &name)?;
+    authenticate(
+        master_key,
+        keyfile_path,
+        crypto_state,
+        storage_state,
+        session_state,
+    )
+    .await
+}
+
+// 重命名命名存储；默认存储不能重命名
+#[tauri::command]
+pub async fn rename_vault(
+    name: String,
+    new_name: String,
+    storage_state: StorageState<'_>,
+    profiles_state: ProfilesState<'_>,
+) -> Result<bool, String> {
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+    let mut profiles = profiles_state.lock().map_err(|e| e.to_string())?;
+
+    // 重命名期间持有该存储的文件锁，避免其他实例向正在移动的目录写入
+    let _lock = if profiles.active() == name {
+        Some(storage.lock())
+    } else if profiles.exists(&name) {
+        let path = profiles.storage_path(&name).map_err(|e| e.to_string())?;
+        Some(StorageService::with_path(path).and_then(|renamed| renamed.lock()))
+    } else {
+        None
+    }
+    .transpose()
+    .map_err(|e| e.to_string())?;
+
+    let new_path = profiles
+        .rename(&name, &new_name)
+        .map_err(|e| e.to_string())?;
+    if profiles.active() == new_name {
+        storage.set_storage_path(new_path);
+    }
+    Ok(true)
+}
+
+// 删除命名存储；已初始化的存储需验证其主密码
+#[tauri::command]
+pub async fn delete_vault(
+    name: String,
+    master_key: String,
+    keyfile_path: Option<String>,
+    profiles_state: ProfilesState<'_>,
+) -> Result<bool, String> {
+    let mut profiles = profiles_state.lock().map_err(|e| e.to_string())?;
+    if profiles.active() == name {
+        return Err("不能删除当前打开的存储，请先切换到其他存储".to_string());
+    }
+
+    let path = profiles.storage_path(&name).map_err(|e| e.to_string())?;
+    let mut storage = StorageService::with_path(path).map_err(|e| e.to_string())?;
+    if storage.is_initialized() {
+        let is_valid = check_master_password(
+            &mut CryptoService::new(),
+            &mut storage,
+            &master_key,
+            keyfile_path.as_deref(),
+            "delete_vault",
+        )?;
+        if !is_valid {
+            return Ok(false);
+        }
+    }
+
+    profiles.delete(&name).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+// 锁定当前存储并把存储服务切换到指定存储，返回切换前是否已解锁
+fn switch_active_vault(
+    crypto_state: &CryptoState<'_>,
+    storage_state: &StorageState<'_>,
+    profiles_state: &ProfilesState<'_>,
+    name: &str,
 ) -> Result<bool, String> {
     let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+    let mut profiles = profiles_state.lock().map_err(|e| e.to_string())?;
+
+    profiles.set_active(name).map_err(|e| e.to_string())?;
+    *storage = profiles.open_storage().map_err(|e| e.to_string())?;
+
+    let was_unlocked = crypto.is_authenticated();
+    crypto.clear_master_key();
+    Ok(was_unlocked)
+}
+
+// 当前数据目录及其来源（命令行、环境变量、便携模式或默认位置）
+#[tauri::command]
+pub async fn get_data_dir(profiles_state: ProfilesState<'_>) -> Result<DataDirLocation, String> {
+    let profiles = profiles_state.lock().map_err(|e| e.to_string())?;
+    let source = data_dir::resolve().map_err(|e| e.to_string())?.source;
+    Ok(DataDirLocation {
+        path: profiles.app_dir().to_path_buf(),
+        source,
+    })
+}
+
+// 把当前存储（含上一版本与快照）移动到新目录：先复制并校验副本可以解密，
+// 切换到新位置后才删除原文件
+#[tauri::command]
+pub async fn move_vault(
+    target_dir: String,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+    profiles_state: ProfilesState<'_>,
+) -> Result<String, String> {
+    if target_dir.trim().is_empty() {
+        return Err("目标目录为空".to_string());
+    }
+
+    let crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+    let mut profiles = profiles_state.lock().map_err(|e| e.to_string())?;
+    if !crypto.is_authenticated() {
+        return Err("请先解锁存储".to_string());
+    }
+
+    let target_dir = std::path::absolute(target_dir.trim()).map_err(|e| e.to_string())?;
+    // 复制、切换与删除原文件的整个过程持有原存储的文件锁，避免其他实例写入随后被删除的原文件
+    let _lock = storage.lock().map_err(|e| e.to_string())?;
+    let new_path = copy_vault_to(&crypto, &storage, &target_dir)?;
+
+    let name = profiles.active().to_string();
+    if let Err(e) = profiles.relocate(&name, target_dir) {
+        let _ = StorageService::with_path(new_path).and_then(|copy| copy.remove_files());
+        return Err(e.to_string());
+    }
+
+    let old_path = storage.storage_path().clone();
+    storage.set_storage_path(new_path.clone());
+
+    // 新位置已生效，原文件删除失败不影响使用；命名存储的空目录一并删除
+    if let Ok(old) = StorageService::with_path(old_path.clone()) {
+        let _ = old.remove_files();
+    }
+    if let Some(old_dir) = old_path.parent() {
+        let _ = std::fs::remove_dir(old_dir);
+    }
+    Ok(new_path.to_string_lossy().into_owned())
+}
+
+// 复制存储并用当前会话解密副本，校验失败时删除副本
+fn copy_vault_to(
+    crypto: &CryptoService,
+    storage: &StorageService,
+    target_dir: &Path,
+) -> Result<PathBuf, String> {
+    let new_path = storage.copy_to(target_dir).map_err(|e| e.to_string())?;
+    let copy = StorageService::with_path(new_path.clone()).map_err(|e| e.to_string())?;
+
+    let verified = copy
+        .load_encrypted_data()
+        .and_then(|(encrypted_data, _header)| crypto.decrypt(&encrypted_data));
+    if let Err(e) = verified {
+        let _ = copy.remove_files();
+        return Err(format!("校验副本失败: {}", e));
+    }
+    Ok(new_path)
+}
+
+// 需要当前会话已解锁；快照可能使用旧的主密码，因此用独立实例按快照自身的密钥槽解锁。
+// 未指定密钥文件时沿用会话中的密钥文件
+fn unlock_backup(
+    crypto: &CryptoService,
+    storage: &StorageService,
+    backup_name: &str,
+    master_key: &str,
+    keyfile_path: Option<&str>,
+) -> Result<Option<(CryptoService, EncryptedData)>, String> {
+    if !crypto.is_authenticated() {
+        return Err("请先解锁".to_string());
+    }
+
+    let (encrypted_data, header) = storage
+        .load_backup(backup_name)
+        .map_err(|e| e.to_string())?;
+    let keyfile = match read_keyfile(keyfile_path)? {
+        Some(keyfile) => Some(keyfile),
+        None => crypto.keyfile_hash().copied(),
+    };
+
+    let mut snapshot = CryptoService::new();
+    snapshot.set_header(header);
+    snapshot.set_keyfile(keyfile);
+    if !snapshot.try_unlock(master_key).map_err(|e| e.to_string())? {
+        return Ok(None);
+    }
+    Ok(Some((snapshot, encrypted_data)))
+}
+
+// 获取解锁失败记录、退避状态与锁定策略（未解锁时也可查询）
+#[tauri::command]
+pub async fn get_auth_guard_state(
+    storage_state: StorageState<'_>,
+) -> Result<AuthGuardState, String> {
     let storage = storage_state.lock().map_err(|e| e.to_string())?;
+    storage.load_auth_guard().map_err(|e| e.to_string())
+}
 
-    // 加载存储文件
-    let (_encrypted_data, _salt_vec, stored_hash, salt) =
-        storage.load_encrypted_data().map_err(|e| e.to_string())?;
+// 清空解锁失败记录，返回被清除的记录
+#[tauri::command]
+pub async fn clear_failed_attempts(
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<Vec<FailedAttempt>, String> {
+    let crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
 
-    // 设置盐值和存储的主密码哈希
-    crypto.set_salt(salt);
-    crypto.set_master_key_hash(stored_hash.clone());
+    if !crypto.is_authenticated() {
+        return Err("请先解锁".to_string());
+    }
 
-    // 验证密码
-    let is_valid = crypto.verify_password(&master_key);
+    let mut guard = storage.load_auth_guard().map_err(|e| e.to_string())?;
+    let cleared = std::mem::take(&mut guard.failed_attempts);
+    storage.save_auth_guard(&guard).map_err(|e| e.to_string())?;
+    Ok(cleared)
+}
 
-    // 如果密码验证成功，设置主密钥（使用已有的盐值派生密钥，而不是生成新的盐值）
-    if is_valid {
-        let derived_key = CryptoService::derive_key(&master_key, &salt);
-        crypto.set_derived_master_key(derived_key);
+// 设置连续失败后的锁定或清除策略（None 表示只做退避）
+#[tauri::command]
+pub async fn set_lockout_policy(
+    policy: Option<LockoutPolicy>,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<bool, String> {
+    let crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    if !crypto.is_authenticated() {
+        return Err("请先解锁".to_string());
+    }
+    if let Some(policy) = &policy {
+        RateLimiter::validate_policy(policy).map_err(|e| e.to_string())?;
+        // 锁定后密码解锁被禁用，只能依靠恢复密钥
+        if policy.action == LockoutAction::Lock && !crypto.has_recovery_key() {
+            return Err("启用锁定策略前请先生成恢复密钥".to_string());
+        }
     }
 
-    Ok(is_valid)
+    let mut guard = storage.load_auth_guard().map_err(|e| e.to_string())?;
+    guard.lockout_policy = policy;
+    storage.save_auth_guard(&guard).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+// 检查解锁是否需要密钥文件
+#[tauri::command]
+pub async fn is_keyfile_required(storage_state: StorageState<'_>) -> Result<bool, String> {
+    let storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    let (_encrypted_data, header) = storage.load_encrypted_data().map_err(|e| e.to_string())?;
+    let mut crypto = CryptoService::new();
+    crypto.set_header(header);
+    Ok(crypto.requires_keyfile())
+}
+
+// 启用密钥文件：此后解锁需要同时提供主密码与该文件
+#[tauri::command]
+pub async fn enable_keyfile(
+    master_key: String,
+    keyfile_path: String,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<bool, String> {
+    let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    if !crypto.is_authenticated() {
+        return Err("请先解锁".to_string());
+    }
+    if crypto.requires_keyfile() {
+        return Err("已启用密钥文件".to_string());
+    }
+    if !crypto
+        .check_password(&master_key)
+        .map_err(|e| e.to_string())?
+    {
+        return Ok(false);
+    }
+
+    let keyfile = read_keyfile(Some(&keyfile_path))?.ok_or("请选择密钥文件")?;
+    set_password_keyfile(&mut crypto, &mut storage, &master_key, Some(keyfile))?;
+    Ok(true)
+}
+
+// 更换密钥文件，旧文件随即失效
+#[tauri::command]
+pub async fn rotate_keyfile(
+    master_key: String,
+    new_keyfile_path: String,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<bool, String> {
+    let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    if !crypto.is_authenticated() {
+        return Err("请先解锁".to_string());
+    }
+    if !crypto.requires_keyfile() {
+        return Err("尚未启用密钥文件".to_string());
+    }
+    if !crypto
+        .check_password(&master_key)
+        .map_err(|e| e.to_string())?
+    {
+        return Ok(false);
+    }
+
+    let keyfile = read_keyfile(Some(&new_keyfile_path))?.ok_or("请选择密钥文件")?;
+    set_password_keyfile(&mut crypto, &mut storage, &master_key, Some(keyfile))?;
+    Ok(true)
+}
+
+// 停用密钥文件，此后仅凭主密码即可解锁
+#[tauri::command]
+pub async fn disable_keyfile(
+    master_key: String,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<bool, String> {
+    let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+
+    if !crypto.is_authenticated() {
+        return Err("请先解锁".to_string());
+    }
+    if !crypto.requires_keyfile() {
+        return Err("尚未启用密钥文件".to_string());
+    }
+    if !crypto
+        .check_password(&master_key)
+        .map_err(|e| e.to_string())?
+    {
+        return Ok(false);
+    }
+
+    set_password_keyfile(&mut crypto, &mut storage, &master_key, None)?;
+    Ok(true)
 }
 
 // 生成SSH密钥
@@ -105,19 +936,11 @@ pub async fn generate_ssh_key(
     // 生成密钥对
     let key_pair = SshKeyService::generate_key_pair(params).map_err(|e| e.to_string())?;
 
-    // 加载现有数据
-    let mut data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
-    // 添加新密钥
-    data["keys"]
-        .as_array_mut()
-        .ok_or("无效的数据格式")?
-        .push(serde_json::to_value(&key_pair).map_err(|e| e.to_string())?);
-
-    // 保存数据
-    save_encrypted_data(data, &crypto_state, &storage_state).await?;
-
-    Ok(key_pair)
+    // 添加新密钥并保存
+    with_vault(&crypto_state, &storage_state, |repo| {
+        repo.insert(key_pair.clone())?;
+        Ok(key_pair)
+    })
 }
 
 // 获取所有密钥
@@ -126,17 +949,7 @@ pub async fn get_all_keys(
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
 ) -> Result<Vec<SshKeyPair>, String> {
-    let data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
-    let keys: Vec<SshKeyPair> = data["keys"]
-        .as_array()
-        .ok_or("无效的数据格式")?
-        .iter()
-        .map(|v| serde_json::from_value(v.clone()))
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
-
-    Ok(keys)
+    with_vault(&crypto_state, &storage_state, |repo| Ok(repo.load()?.keys))
 }
 
 // 删除密钥
@@ -146,20 +959,10 @@ pub async fn delete_key(
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
 ) -> Result<bool, String> {
-    let mut data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
-    let keys = data["keys"].as_array_mut().ok_or("无效的数据格式")?;
-
     // 查找并删除密钥
-    let initial_len = keys.len();
-    keys.retain(|key| key["id"].as_str().unwrap_or("") != key_id);
-
-    if keys.len() < initial_len {
-        save_encrypted_data(data, &crypto_state, &storage_state).await?;
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+    with_vault(&crypto_state, &storage_state, |repo| {
+        Ok(repo.delete(&key_id)?)
+    })
 }
 
 // 更新密钥信息
@@ -171,21 +974,43 @@ pub async fn update_key_info(
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
 ) -> Result<bool, String> {
-    let mut data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
-    let keys = data["keys"].as_array_mut().ok_or("无效的数据格式")?;
-
     // 查找并更新密钥
-    for key in keys.iter_mut() {
-        if key["id"].as_str().unwrap_or("") == key_id {
-            key["name"] = serde_json::Value::String(name);
-            key["comment"] = serde_json::Value::String(comment);
-            save_encrypted_data(data, &crypto_state, &storage_state).await?;
-            return Ok(true);
-        }
-    }
+    with_vault(&crypto_state, &storage_state, |repo| {
+        Ok(repo.update(&key_id, |key| {
+            key.name = name;
+            key.comment = comment;
+            Ok(())
+        })?)
+    })
+}
 
-    Ok(false)
+// 修改、添加或移除已存储私钥的密码
+#[tauri::command]
+pub async fn change_key_passphrase(
+    key_id: String,
+    old_passphrase: Option<String>,
+    new_passphrase: Option<String>,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<bool, String> {
+    // 查找密钥并重新加密私钥
+    let updated = with_vault(&crypto_state, &storage_state, |repo| {
+        Ok(repo.update(&key_id, |key| {
+            let (private_key, passphrase_protected) = SshKeyService::change_passphrase(
+                &key.private_key,
+                old_passphrase.as_deref(),
+                new_passphrase.as_deref(),
+            )?;
+            key.private_key = private_key;
+            key.passphrase_protected = passphrase_protected;
+            Ok(())
+        })?)
+    })?;
+
+    if !updated {
+        return Err("密钥不存在".to_string());
+    }
+    Ok(true)
 }
 
 // 导出密钥（实现版本）
@@ -196,33 +1021,18 @@ pub async fn export_key(
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
 ) -> Result<bool, String> {
-    let data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
-    // 查找密钥
-    let keys: Vec<serde_json::Value> = data["keys"]
-        .as_array()
-        .ok_or("无效的数据格式")?
-        .iter()
-        .filter(|k| k["id"].as_str().unwrap_or("") == key_id)
-        .cloned()
-        .collect();
-
-    if keys.is_empty() {
-        return Err("密钥不存在".to_string());
-    }
-
-    let key = &keys[0];
+    // 查找密钥
+    let key = with_vault(&crypto_state, &storage_state, |repo| Ok(repo.get(&key_id)?))?
+        .ok_or("密钥不存在")?;
 
     // 导出公钥文件
-    let public_key_content = key["public_key"].as_str().unwrap_or("");
     let public_key_path = format!("{}.pub", export_path);
 
-    std::fs::write(&public_key_path, public_key_content)
+    std::fs::write(&public_key_path, &key.public_key)
         .map_err(|e| format!("写入公钥文件失败: {}", e))?;
 
-    // 导出私钥文件 (注意：实际应用中需要解密)
-    let private_key_content = key["private_key"].as_str().unwrap_or("");
-    std::fs::write(&export_path, private_key_content)
+    // 导出私钥文件，仅当前用户可读写
+    write_private_key_file(&export_path, key.private_key.as_bytes())
         .map_err(|e| format!("写入私钥文件失败: {}", e))?;
 
     Ok(true)
@@ -239,39 +1049,107 @@ pub async fn import_keys(
     let imported_keys: Vec<SshKeyPair> =
         serde_json::from_str(&keys_data).map_err(|e| format!("解析密钥数据失败: {}", e))?;
 
-    // 加载现有数据
-    let mut data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
     // 添加新密钥（跳过已存在的 ID，并去重导入集合内部的重复）
-    let keys_array = data["keys"].as_array_mut().ok_or("无效的数据格式")?;
+    with_vault(&crypto_state, &storage_state, |repo| {
+        let mut vault = repo.load()?;
+        let mut seen_ids: std::collections::HashSet<String> =
+            vault.keys.iter().map(|k| k.id.clone()).collect();
 
-    let mut existing_ids = std::collections::HashSet::new();
-    for k in keys_array.iter() {
-        if let Some(id) = k["id"].as_str() {
-            existing_ids.insert(id.to_string());
-        }
-    }
+        let to_add: Vec<SshKeyPair> = imported_keys
+            .into_iter()
+            .filter(|key| seen_ids.insert(key.id.clone()))
+            .collect();
 
-    let mut seen_import_ids = std::collections::HashSet::new();
-    let mut to_add: Vec<SshKeyPair> = Vec::new();
-    for key in imported_keys.into_iter() {
-        if existing_ids.contains(&key.id) {
-            continue;
+        vault.keys.extend(to_add.iter().cloned());
+        repo.save(&mut vault)?;
+        Ok(to_add)
+    })
+}
+
+// 导入单个私钥（OpenSSH / PKCS#1 / SEC1 / PKCS#8），指纹已存在时拒绝导入
+#[tauri::command]
+pub async fn import_private_key(
+    name: String,
+    private_key: String,
+    public_key: Option<String>,
+    passphrase: Option<String>,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<SshKeyPair, String> {
+    let key = SshKeyService::import_private_key(
+        &name,
+        &private_key,
+        public_key.as_deref(),
+        passphrase.as_deref(),
+    )
+    .map_err(|e| e.to_string())?;
+
+    with_vault(&crypto_state, &storage_state, |repo| {
+        let mut vault = repo.load()?;
+        if vault.keys.iter().any(|k| k.fingerprint == key.fingerprint) {
+            return Err("密钥已存在".to_string());
         }
-        if !seen_import_ids.insert(key.id.clone()) {
-            continue;
+
+        vault.keys.push(key.clone());
+        repo.save(&mut vault)?;
+        Ok(key)
+    })
+}
+
+// 从 ~/.ssh 导入选中的私钥文件（跳过指纹已存在的密钥）
+#[tauri::command]
+pub async fn import_identity_files(
+    file_names: Vec<String>,
+    dir_path: Option<String>,
+    passphrases: Option<std::collections::HashMap<String, String>>,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<IdentityImportResult, String> {
+    let passphrases = passphrases.unwrap_or_default();
+
+    // 先在存储锁外解析私钥文件（加密私钥的 KDF 较慢），再在锁内读取、合并并保存
+    let mut result = IdentityImportResult::default();
+    let mut parsed = Vec::new();
+    for file_name in file_names {
+        let imported = SshConfigService::read_identity_file(dir_path.as_deref(), &file_name)
+            .and_then(|(private_key, public_key)| {
+                SshKeyService::import_private_key(
+                    &file_name,
+                    &private_key,
+                    public_key.as_deref(),
+                    passphrases.get(&file_name).map(String::as_str),
+                )
+            });
+
+        match imported {
+            Ok(key) => parsed.push((file_name, key)),
+            Err(AppError::PassphraseRequired) => result.passphrase_required.push(file_name),
+            Err(e) => result.failed.push(IdentityImportFailure {
+                file_name,
+                error: e.to_string(),
+            }),
         }
-        to_add.push(key);
     }
 
-    for key in &to_add {
-        keys_array.push(serde_json::to_value(key).map_err(|e| e.to_string())?);
-    }
+    with_vault(&crypto_state, &storage_state, |repo| {
+        let mut vault = repo.load()?;
+        let mut known_fingerprints: std::collections::HashSet<String> =
+            vault.keys.iter().map(|k| k.fingerprint.clone()).collect();
 
-    // 保存数据
-    save_encrypted_data(data, &crypto_state, &storage_state).await?;
+        for (file_name, key) in parsed {
+            if !known_fingerprints.insert(key.fingerprint.clone()) {
+                result.skipped.push(file_name);
+                continue;
+            }
+            vault.keys.push(key.clone());
+            result.imported.push(key);
+        }
 
-    Ok(to_add)
+        if !result.imported.is_empty() {
+            repo.save(&mut vault)?;
+        }
+        Ok(result)
+    })
 }
 
 // 导出密钥到指定文件（增强版本）
@@ -281,19 +1159,13 @@ pub async fn export_keys_to_file(
     file_path: String,
     export_format: String,
     include_private_keys: bool,
+    key_passphrase: Option<String>,
+    export_passphrase: Option<String>,
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
 ) -> Result<bool, String> {
-    let data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
     // 获取所有密钥
-    let all_keys: Vec<SshKeyPair> = data["keys"]
-        .as_array()
-        .ok_or("无效的数据格式")?
-        .iter()
-        .map(|v| serde_json::from_value(v.clone()))
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+    let all_keys = with_vault(&crypto_state, &storage_state, |repo| Ok(repo.load()?.keys))?;
 
     // 筛选要导出的密钥
     let keys_to_export: Vec<SshKeyPair> = if key_ids.is_empty() {
@@ -348,39 +1220,45 @@ pub async fn export_keys_to_file(
 
                 // 如果包含私钥，写入私钥文件
                 if include_private_keys {
-                    std::fs::write(&base_path, &key.private_key)
+                    write_private_key_file(&base_path, key.private_key.as_bytes())
                         .map_err(|e| format!("写入私钥文件失败: {}", e))?;
                 }
             }
         }
-        "pem" => {
-            // 导出为PEM格式
-            let mut pem_content = String::new();
-            for key in &keys_to_export {
-                pem_content.push_str(&format!("# SSH Key: {}\n", key.name));
-                pem_content.push_str(&format!("# Type: {}\n", key.key_type));
-                pem_content.push_str(&format!("# Size: {} bits\n", key.key_size));
-                pem_content.push_str(&format!("# Fingerprint: {}\n", key.fingerprint));
-                pem_content.push_str(&format!(
-                    "# Created: {}\n",
-                    key.created_at.format("%Y-%m-%d %H:%M:%S UTC")
-                ));
-                if !key.comment.is_empty() {
-                    pem_content.push_str(&format!("# Comment: {}\n", key.comment));
-                }
-                pem_content.push_str("#\n");
-                pem_content.push_str(&format!("# Public Key:\n{}\n\n", key.public_key));
+        "pem" | "pkcs8" | "pkcs1" | "ppk" => {
+            // 导出为PEM格式：pem/pkcs8 为 PKCS#8，pkcs1 仅支持 RSA；ppk 为 PuTTY PPK v3
+            // key_passphrase 用于解锁受密码保护的私钥，export_passphrase 用于加密导出的私钥文件，为空则不加密
+            for (index, key) in keys_to_export.iter().enumerate() {
+                let base_path = if keys_to_export.len() == 1 {
+                    file_path.clone()
+                } else {
+                    format!("{}_key_{}", file_path, index + 1)
+                };
+
+                // 写入公钥文件
+                let pub_path = format!("{}.pub", base_path);
+                std::fs::write(&pub_path, &key.public_key)
+                    .map_err(|e| format!("写入公钥文件失败: {}", e))?;
 
+                // 如果包含私钥，转换并写入私钥文件
                 if include_private_keys {
-                    pem_content.push_str(&format!("# Private Key:\n{}\n\n", key.private_key));
-                } else {
-                    pem_content.push_str("# Private Key: [REDACTED for security]\n\n");
-                }
+                    let private_key = SshKeyService::unlock_private_key(
+                        &key.private_key,
+                        key_passphrase.as_deref(),
+                    )
+                    .map_err(|e| format!("{}: {}", key.name, e))?;
+                    let export_passphrase = export_passphrase.as_deref().filter(|p| !p.is_empty());
+                    let encoded = match export_format.as_str() {
+                        "pkcs1" => KeyFormatService::to_pkcs1_pem(&private_key, export_passphrase),
+                        "ppk" => KeyFormatService::to_ppk(&private_key, export_passphrase),
+                        _ => KeyFormatService::to_pkcs8_pem(&private_key, export_passphrase),
+                    }
+                    .map_err(|e| format!("{}: {}", key.name, e))?;
 
-                pem_content.push_str(&format!("# {}\n\n", "-".repeat(50)));
+                    write_private_key_file(&base_path, encoded.as_bytes())
+                        .map_err(|e| format!("写入私钥文件失败: {}", e))?;
+                }
             }
-
-            std::fs::write(&file_path, pem_content).map_err(|e| format!("写入文件失败: {}", e))?;
         }
         _ => {
             return Err(format!("不支持的导出格式: {}", export_format));
@@ -390,10 +1268,29 @@ pub async fn export_keys_to_file(
     Ok(true)
 }
 
+// 私钥文件只允许当前用户读写；覆盖已有文件时也先收紧权限再写入
+fn write_private_key_file(path: &str, content: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(content)
+}
+
 // 重置所有数据
 #[tauri::command]
 pub async fn reset_all_data(
     master_key: String,
+    keyfile_path: Option<String>,
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
 ) -> Result<bool, String> {
@@ -401,16 +1298,13 @@ pub async fn reset_all_data(
     let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
 
     // 首先验证密码
-    // 加载存储文件
-    let (_encrypted_data, _salt_vec, stored_hash, salt) =
-        storage.load_encrypted_data().map_err(|e| e.to_string())?;
-
-    // 设置盐值和存储的主密码哈希
-    crypto.set_salt(salt);
-    crypto.set_master_key_hash(stored_hash.clone());
-
-    // 验证密码
-    let is_valid = crypto.verify_password(&master_key);
+    let is_valid = check_master_password(
+        &mut crypto,
+        &mut storage,
+        &master_key,
+        keyfile_path.as_deref(),
+        "reset_all_data",
+    )?;
 
     if !is_valid {
         return Ok(false);
@@ -432,15 +1326,7 @@ pub async fn export_all_keys(
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
 ) -> Result<String, String> {
-    let data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
-    let keys: Vec<SshKeyPair> = data["keys"]
-        .as_array()
-        .ok_or("无效的数据格式")?
-        .iter()
-        .map(|v| serde_json::from_value(v.clone()))
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+    let keys = with_vault(&crypto_state, &storage_state, |repo| Ok(repo.load()?.keys))?;
 
     // 创建导出数据结构
     let export_data = serde_json::json!({
@@ -456,20 +1342,20 @@ pub async fn export_all_keys(
 #[tauri::command]
 pub async fn verify_master_password(
     master_key: String,
+    keyfile_path: Option<String>,
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
 ) -> Result<bool, String> {
     let mut crypto = crypto_state.lock().map_err(|e| e.to_string())?;
-    let storage = storage_state.lock().map_err(|e| e.to_string())?;
-
-    // 加载存储文件中的盐与哈希
-    let (_encrypted_data, _salt_vec, stored_hash, salt) =
-        storage.load_encrypted_data().map_err(|e| e.to_string())?;
-
-    crypto.set_salt(salt);
-    crypto.set_master_key_hash(stored_hash.clone());
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
 
-    Ok(crypto.verify_password(&master_key))
+    check_master_password(
+        &mut crypto,
+        &mut storage,
+        &master_key,
+        keyfile_path.as_deref(),
+        "verify_master_password",
+    )
 }
 
 // 导出所有密钥（加密JSON，使用用户提供的主密码）
@@ -480,35 +1366,24 @@ pub async fn export_all_keys_encrypted(
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
 ) -> Result<String, String> {
-    let data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
-    let keys: Vec<SshKeyPair> = data["keys"]
-        .as_array()
-        .ok_or("无效的数据格式")?
-        .iter()
-        .map(|v| serde_json::from_value(v.clone()))
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+    let keys = with_vault(&crypto_state, &storage_state, |repo| Ok(repo.load()?.keys))?;
 
     let export_salt = crate::services::CryptoService::generate_salt();
+    let export_kdf = KdfParams::default();
+    let export_key = Zeroizing::new(
+        CryptoService::derive_key_with_params(&master_key, &export_salt, &export_kdf)
+            .map_err(|e| e.to_string())?,
+    );
 
     // 为每个密钥加密公钥与私钥
     let mut enc_keys: Vec<serde_json::Value> = Vec::new();
     for k in keys {
-        let pub_enc = crate::services::CryptoService::encrypt_with_password(
-            &master_key,
-            &export_salt,
-            k.public_key.as_bytes(),
-        )
-        .map_err(|e| e.to_string())?;
+        let pub_enc = CryptoService::encrypt_with_key(&export_key, k.public_key.as_bytes())
+            .map_err(|e| e.to_string())?;
 
         let priv_enc_value = if include_private_keys {
-            let priv_enc = crate::services::CryptoService::encrypt_with_password(
-                &master_key,
-                &export_salt,
-                k.private_key.as_bytes(),
-            )
-            .map_err(|e| e.to_string())?;
+            let priv_enc = CryptoService::encrypt_with_key(&export_key, k.private_key.as_bytes())
+                .map_err(|e| e.to_string())?;
             serde_json::json!({
                 "nonce": general_purpose::STANDARD.encode(&priv_enc.nonce),
                 "ciphertext": general_purpose::STANDARD.encode(&priv_enc.ciphertext),
@@ -525,6 +1400,10 @@ pub async fn export_all_keys_encrypted(
             "comment": k.comment,
             "fingerprint": k.fingerprint,
             "created_at": k.created_at,
+            "passphrase_protected": k.passphrase_protected,
+            "tags": k.tags,
+            "notes": k.notes,
+            "expires_at": k.expires_at,
             "public_key_encrypted": {
                 "nonce": general_purpose::STANDARD.encode(&pub_enc.nonce),
                 "ciphertext": general_purpose::STANDARD.encode(&pub_enc.ciphertext)
@@ -535,13 +1414,10 @@ pub async fn export_all_keys_encrypted(
     }
 
     let export_obj = serde_json::json!({
-        "version": "1.1-encrypted",
+        "version": "1.2-encrypted",
         "is_encrypted": true,
         "exported_at": chrono::Utc::now(),
-        "kdf": {
-            "type": "pbkdf2-hmac-sha256",
-            "iterations": 100_000,
-        },
+        "kdf": export_kdf,
         "salt": general_purpose::STANDARD.encode(&export_salt),
         "keys": enc_keys,
     });
@@ -558,15 +1434,7 @@ pub async fn export_selected_keys_encrypted(
     crypto_state: CryptoState<'_>,
     storage_state: StorageState<'_>,
 ) -> Result<String, String> {
-    let data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
-    let all_keys: Vec<SshKeyPair> = data["keys"]
-        .as_array()
-        .ok_or("无效的数据格式")?
-        .iter()
-        .map(|v| serde_json::from_value(v.clone()))
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+    let all_keys = with_vault(&crypto_state, &storage_state, |repo| Ok(repo.load()?.keys))?;
 
     let keys: Vec<SshKeyPair> = if key_ids.is_empty() {
         all_keys
@@ -582,22 +1450,19 @@ pub async fn export_selected_keys_encrypted(
     }
 
     let export_salt = crate::services::CryptoService::generate_salt();
+    let export_kdf = KdfParams::default();
+    let export_key = Zeroizing::new(
+        CryptoService::derive_key_with_params(&master_key, &export_salt, &export_kdf)
+            .map_err(|e| e.to_string())?,
+    );
     let mut enc_keys: Vec<serde_json::Value> = Vec::new();
     for k in keys {
-        let pub_enc = crate::services::CryptoService::encrypt_with_password(
-            &master_key,
-            &export_salt,
-            k.public_key.as_bytes(),
-        )
-        .map_err(|e| e.to_string())?;
+        let pub_enc = CryptoService::encrypt_with_key(&export_key, k.public_key.as_bytes())
+            .map_err(|e| e.to_string())?;
 
         let priv_enc_value = if include_private_keys {
-            let priv_enc = crate::services::CryptoService::encrypt_with_password(
-                &master_key,
-                &export_salt,
-                k.private_key.as_bytes(),
-            )
-            .map_err(|e| e.to_string())?;
+            let priv_enc = CryptoService::encrypt_with_key(&export_key, k.private_key.as_bytes())
+                .map_err(|e| e.to_string())?;
             serde_json::json!({
                 "nonce": general_purpose::STANDARD.encode(&priv_enc.nonce),
                 "ciphertext": general_purpose::STANDARD.encode(&priv_enc.ciphertext),
@@ -614,6 +1479,10 @@ pub async fn export_selected_keys_encrypted(
             "comment": k.comment,
             "fingerprint": k.fingerprint,
             "created_at": k.created_at,
+            "passphrase_protected": k.passphrase_protected,
+            "tags": k.tags,
+            "notes": k.notes,
+            "expires_at": k.expires_at,
             "public_key_encrypted": {
                 "nonce": general_purpose::STANDARD.encode(&pub_enc.nonce),
                 "ciphertext": general_purpose::STANDARD.encode(&pub_enc.ciphertext)
@@ -624,13 +1493,10 @@ pub async fn export_selected_keys_encrypted(
     }
 
     let export_obj = serde_json::json!({
-        "version": "1.1-encrypted",
+        "version": "1.2-encrypted",
         "is_encrypted": true,
         "exported_at": chrono::Utc::now(),
-        "kdf": {
-            "type": "pbkdf2-hmac-sha256",
-            "iterations": 100_000,
-        },
+        "kdf": export_kdf,
         "salt": general_purpose::STANDARD.encode(&export_salt),
         "keys": enc_keys,
     });
@@ -677,6 +1543,18 @@ pub async fn import_encrypted_keys(
         return Err("缺少salt或格式无效".to_string());
     };
 
+    // kdf 缺省时为 1.1 之前导出文件使用的 PBKDF2 参数
+    let kdf: KdfParams = match v.get("kdf") {
+        Some(kdf) => {
+            serde_json::from_value(kdf.clone()).map_err(|e| format!("kdf参数无效: {}", e))?
+        }
+        None => KdfParams::legacy(),
+    };
+    let import_key = Zeroizing::new(
+        CryptoService::derive_key_with_params(&master_key, &salt, &kdf)
+            .map_err(|e| e.to_string())?,
+    );
+
     let items = v
         .get("keys")
         .and_then(|k| k.as_array())
@@ -729,6 +1607,20 @@ pub async fn import_encrypted_keys(
             _ => chrono::Utc::now(),
         };
 
+        // 标签、备注与过期时间在旧版本导出文件中不存在
+        let tags = item
+            .get("tags")
+            .and_then(|x| serde_json::from_value(x.clone()).ok())
+            .unwrap_or_default();
+        let notes = item
+            .get("notes")
+            .and_then(|x| x.as_str())
+            .unwrap_or("")
+            .to_string();
+        let expires_at = item
+            .get("expires_at")
+            .and_then(|x| serde_json::from_value(x.clone()).ok());
+
         let pub_obj = item
             .get("public_key_encrypted")
             .ok_or("缺少public_key_encrypted")?;
@@ -773,10 +1665,10 @@ pub async fn import_encrypted_keys(
         let pub_enc = crate::services::EncryptedData {
             nonce: pub_nonce_bytes,
             ciphertext: pub_cipher_bytes,
+            associated_data: None,
         };
-        let public_key_bytes =
-            crate::services::CryptoService::decrypt_with_password(&master_key, &salt, &pub_enc)
-                .map_err(|_| "解密失败: 公钥".to_string())?;
+        let public_key_bytes = CryptoService::decrypt_with_key(&import_key, &pub_enc)
+            .map_err(|_| "解密失败: 公钥".to_string())?;
         let public_key =
             String::from_utf8(public_key_bytes).map_err(|_| "公钥解码失败".to_string())?;
 
@@ -826,19 +1718,18 @@ pub async fn import_encrypted_keys(
                 let priv_enc = crate::services::EncryptedData {
                     nonce: priv_nonce_bytes,
                     ciphertext: priv_cipher_bytes,
+                    associated_data: None,
                 };
-                let private_key_bytes = crate::services::CryptoService::decrypt_with_password(
-                    &master_key,
-                    &salt,
-                    &priv_enc,
-                )
-                .map_err(|_| "解密失败: 私钥".to_string())?;
+                let private_key_bytes = CryptoService::decrypt_with_key(&import_key, &priv_enc)
+                    .map_err(|_| "解密失败: 私钥".to_string())?;
                 String::from_utf8(private_key_bytes).map_err(|_| "私钥解码失败".to_string())?
             }
         } else {
             String::new()
         };
 
+        let passphrase_protected = SshKeyService::is_private_key_encrypted(&private_key);
+
         decrypted_keys.push(SshKeyPair {
             id,
             name,
@@ -850,81 +1741,359 @@ pub async fn import_encrypted_keys(
             private_key,
             created_at,
             last_used: None,
+            passphrase_protected,
+            tags,
+            notes,
+            expires_at,
         });
     }
 
-    // 加载现有数据
-    let mut data = load_and_decrypt_data(&crypto_state, &storage_state).await?;
-
     // 添加新密钥（跳过已存在的 ID，并去重导入集合内部的重复）
-    let keys_array = data["keys"].as_array_mut().ok_or("无效的数据格式")?;
+    with_vault(&crypto_state, &storage_state, |repo| {
+        let mut vault = repo.load()?;
+        let mut seen_ids: std::collections::HashSet<String> =
+            vault.keys.iter().map(|k| k.id.clone()).collect();
 
-    let mut existing_ids = std::collections::HashSet::new();
-    for k in keys_array.iter() {
-        if let Some(id) = k["id"].as_str() {
-            existing_ids.insert(id.to_string());
-        }
+        let to_add: Vec<SshKeyPair> = decrypted_keys
+            .into_iter()
+            .filter(|key| seen_ids.insert(key.id.clone()))
+            .collect();
+
+        vault.keys.extend(to_add.iter().cloned());
+        repo.save(&mut vault)?;
+        Ok(to_add)
+    })
+}
+
+// 启动内置 ssh-agent 并提供选中的密钥（受密码保护的私钥需提供密码），每个密钥可限制
+// 有效期、签名次数或要求逐次确认；将 SSH_AUTH_SOCK 设为返回的 socket_path 即可使用，
+// 存储锁定后代理自动停止
+#[tauri::command]
+pub async fn start_agent(
+    keys: Vec<AgentKeyOptions>,
+    app: AppHandle,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+    agent_state: AgentState<'_>,
+) -> Result<AgentStatus, String> {
+    if keys.is_empty() {
+        return Err("请选择要加入代理的密钥".to_string());
     }
 
-    let mut seen_import_ids = std::collections::HashSet::new();
-    let mut to_add: Vec<SshKeyPair> = Vec::new();
-    for key in decrypted_keys.into_iter() {
-        if existing_ids.contains(&key.id) {
-            continue;
-        }
-        if !seen_import_ids.insert(key.id.clone()) {
-            continue;
-        }
-        to_add.push(key);
+    let crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let unlock = crypto.unlock_token().ok_or("请先解锁")?;
+    let vault = {
+        let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+        VaultRepository::new(&crypto, &mut storage)
+            .load()
+            .map_err(|e| e.to_string())?
+    };
+
+    let identities = agent_identities(keys, &vault.keys)?;
+
+    // 需要确认的签名请求通过事件通知前端，前端调用 respond_agent_confirmation 答复
+    let notifier: ConfirmNotifier =
+        std::sync::Arc::new(move |request| app.emit(AGENT_CONFIRM_EVENT, request).is_ok());
+
+    let socket_path = default_socket_path();
+    let mut agent = agent_state.lock().map_err(|e| e.to_string())?;
+    agent
+        .start(socket_path, identities, unlock, notifier)
+        .map_err(|e| e.to_string())?;
+    Ok(agent.status())
+}
+
+// 停止内置 ssh-agent，返回停止前是否在运行
+#[tauri::command]
+pub async fn stop_agent(agent_state: AgentState<'_>) -> Result<bool, String> {
+    let mut agent = agent_state.lock().map_err(|e| e.to_string())?;
+    let was_running = agent.status().running;
+    agent.stop();
+    Ok(was_running)
+}
+
+#[tauri::command]
+pub async fn get_agent_status(agent_state: AgentState<'_>) -> Result<AgentStatus, String> {
+    let agent = agent_state.lock().map_err(|e| e.to_string())?;
+    Ok(agent.status())
+}
+
+// 将选中的密钥加入 SSH_AUTH_SOCK 指向的系统 ssh-agent，可限制有效期或要求确认；
+// 私钥只经 socket 传给代理，不会写入磁盘。返回代理中现有的密钥
+#[tauri::command]
+pub async fn add_keys_to_system_agent(
+    keys: Vec<AgentKeyOptions>,
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<Vec<SystemAgentKey>, String> {
+    if keys.is_empty() {
+        return Err("请选择要加入代理的密钥".to_string());
+    }
+
+    let vault_keys = with_vault(&crypto_state, &storage_state, |repo| Ok(repo.load()?.keys))?;
+    let identities = agent_identities(keys, &vault_keys)?;
+    let mut client = SystemAgentClient::connect_default().map_err(|e| e.to_string())?;
+    for identity in &identities {
+        client.add_identity(identity).map_err(|e| e.to_string())?;
     }
+    list_held_keys(&mut client, &vault_keys)
+}
+
+// 列出系统 ssh-agent 中的密钥，并按指纹标出存储中对应的密钥
+#[tauri::command]
+pub async fn list_system_agent_keys(
+    crypto_state: CryptoState<'_>,
+    storage_state: StorageState<'_>,
+) -> Result<Vec<SystemAgentKey>, String> {
+    let vault_keys = with_vault(&crypto_state, &storage_state, |repo| Ok(repo.load()?.keys))?;
+    let mut client = SystemAgentClient::connect_default().map_err(|e| e.to_string())?;
+    list_held_keys(&mut client, &vault_keys)
+}
 
-    for key in &to_add {
-        keys_array.push(serde_json::to_value(key).map_err(|e| e.to_string())?);
+// 按指纹从系统 ssh-agent 中删除密钥，返回删除的数量
+#[tauri::command]
+pub async fn remove_system_agent_keys(fingerprints: Vec<String>) -> Result<usize, String> {
+    let mut client = SystemAgentClient::connect_default().map_err(|e| e.to_string())?;
+    let held = client.list_identities().map_err(|e| e.to_string())?;
+    let mut removed = 0;
+    for identity in held
+        .iter()
+        .filter(|identity| fingerprints.contains(&identity.fingerprint()))
+    {
+        client
+            .remove_identity(&identity.key_blob)
+            .map_err(|e| e.to_string())?;
+        removed += 1;
     }
+    Ok(removed)
+}
+
+// 答复签名确认请求，请求已超时或不存在时返回 false
+#[tauri::command]
+pub async fn respond_agent_confirmation(
+    request_id: u64,
+    approved: bool,
+    agent_state: AgentState<'_>,
+) -> Result<bool, String> {
+    let agent = agent_state.lock().map_err(|e| e.to_string())?;
+    Ok(agent.respond_confirmation(request_id, approved))
+}
 
-    // 保存数据
-    save_encrypted_data(data, &crypto_state, &storage_state).await?;
+#[tauri::command]
+pub async fn get_agent_sign_log(
+    agent_state: AgentState<'_>,
+) -> Result<Vec<AgentSignRecord>, String> {
+    let agent = agent_state.lock().map_err(|e| e.to_string())?;
+    Ok(agent.sign_log())
+}
 
-    Ok(to_add)
+// 按选项解锁存储中的私钥，受密码保护的私钥需提供密码
+fn agent_identities(
+    keys: Vec<AgentKeyOptions>,
+    vault_keys: &[SshKeyPair],
+) -> Result<Vec<AgentIdentity>, String> {
+    keys.into_iter()
+        .map(|options| {
+            let key = vault_keys
+                .iter()
+                .find(|key| key.id == options.key_id)
+                .ok_or_else(|| format!("密钥不存在: {}", options.key_id))?;
+            AgentIdentity::from_key_pair(key, options.passphrase.as_deref())
+                .map(|identity| identity.with_constraints(options.constraints))
+                .map_err(|e| format!("{}: {}", key.name, e))
+        })
+        .collect()
 }
 
-// 辅助函数
-async fn load_and_decrypt_data(
-    crypto_state: &CryptoState<'_>,
-    storage_state: &StorageState<'_>,
-) -> Result<serde_json::Value, String> {
-    let crypto = crypto_state.lock().map_err(|e| e.to_string())?;
-    let storage = storage_state.lock().map_err(|e| e.to_string())?;
+fn list_held_keys(
+    client: &mut SystemAgentClient,
+    vault_keys: &[SshKeyPair],
+) -> Result<Vec<SystemAgentKey>, String> {
+    let held = client.list_identities().map_err(|e| e.to_string())?;
+    Ok(held
+        .iter()
+        .map(|identity| identity.describe(vault_keys))
+        .collect())
+}
+
+// 生成新的数据密钥并重新加密存储数据；原子写入成功后才替换内存中的主密钥
+fn rekey_vault(
+    crypto: &mut CryptoService,
+    storage: &mut StorageService,
+    plaintext: &[u8],
+    master_key: &str,
+) -> Result<(), String> {
+    let mut rekeyed = CryptoService::new();
+    rekeyed
+        .set_master_key(master_key)
+        .map_err(|e| e.to_string())?;
+    let encrypted = rekeyed.encrypt(plaintext).map_err(|e| e.to_string())?;
 
-    let (encrypted_data, _salt_vec, _stored_hash, _salt) =
-        storage.load_encrypted_data().map_err(|e| e.to_string())?;
+    storage
+        .save_encrypted_data(&encrypted, rekeyed.key_slots())
+        .map_err(|e| e.to_string())?;
 
-    let decrypted = crypto.decrypt(&encrypted_data).map_err(|e| e.to_string())?;
-    let data_str = String::from_utf8(decrypted).map_err(|e| e.to_string())?;
-    serde_json::from_str(&data_str).map_err(|e| e.to_string())
+    crypto.replace_keys(&mut rekeyed);
+    Ok(())
 }
 
-async fn save_encrypted_data(
-    data: serde_json::Value,
-    crypto_state: &CryptoState<'_>,
-    storage_state: &StorageState<'_>,
+// 用主密码重新包装数据密钥，加密数据原样写回
+fn rewrap_password_slot(
+    crypto: &mut CryptoService,
+    storage: &mut StorageService,
+    encrypted_data: &EncryptedData,
+    master_key: &str,
 ) -> Result<(), String> {
-    let crypto = crypto_state.lock().map_err(|e| e.to_string())?;
-    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+    let key_slots = crypto
+        .rewrap_password_slots(master_key)
+        .map_err(|e| e.to_string())?;
+    update_key_slots(crypto, storage, encrypted_data, key_slots)
+}
+
+// 以新的密钥文件设置重新包装密码槽，加密数据原样写回
+fn set_password_keyfile(
+    crypto: &mut CryptoService,
+    storage: &mut StorageService,
+    master_key: &str,
+    keyfile: Option<[u8; 32]>,
+) -> Result<(), String> {
+    let (encrypted_data, _header) = storage.load_encrypted_data().map_err(|e| e.to_string())?;
+    let key_slots = crypto
+        .rewrap_password_slots_with_keyfile(master_key, keyfile.as_ref())
+        .map_err(|e| e.to_string())?;
+    update_key_slots(crypto, storage, &encrypted_data, key_slots)?;
+    crypto.set_keyfile(keyfile);
+    Ok(())
+}
 
-    let data_str = serde_json::to_string(&data).map_err(|e| e.to_string())?;
+// 读取密钥文件并计算哈希；未指定路径时返回 None
+fn read_keyfile(keyfile_path: Option<&str>) -> Result<Option<[u8; 32]>, String> {
+    let Some(path) = keyfile_path.filter(|path| !path.is_empty()) else {
+        return Ok(None);
+    };
 
-    let encrypted = crypto
-        .encrypt(data_str.as_bytes())
+    let contents = Zeroizing::new(std::fs::read(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            format!("密钥文件不存在: {}", path)
+        } else {
+            format!("读取密钥文件失败: {}", e)
+        }
+    })?);
+    if contents.is_empty() {
+        return Err("密钥文件为空".to_string());
+    }
+    Ok(Some(CryptoService::hash_keyfile(&contents)))
+}
+
+// 使用独立的实例校验主密码，不影响当前会话；未指定密钥文件时沿用会话中的密钥文件。
+// 与解锁一样受退避限制，失败会被记录
+fn check_master_password(
+    crypto: &mut CryptoService,
+    storage: &mut StorageService,
+    master_key: &str,
+    keyfile_path: Option<&str>,
+    source: &str,
+) -> Result<bool, String> {
+    let (_encrypted_data, header) = storage.load_encrypted_data().map_err(|e| e.to_string())?;
+    let mut guard = check_rate_limit(storage, false)?;
+
+    let keyfile = match read_keyfile(keyfile_path)? {
+        Some(keyfile) => Some(keyfile),
+        None => crypto.keyfile_hash().copied(),
+    };
+
+    let mut checker = CryptoService::new();
+    checker.set_header(header);
+    checker.set_keyfile(keyfile);
+    let is_valid = checker
+        .check_password(master_key)
         .map_err(|e| e.to_string())?;
-    let salt = crypto.get_salt().ok_or("盐值未设置".to_string())?;
-    let master_key_hash = crypto
-        .get_master_key_hash()
-        .ok_or("主密钥哈希未设置".to_string())?;
 
+    if is_valid {
+        record_successful_attempt(storage, &mut guard)?;
+    } else {
+        record_failed_attempt(crypto, storage, &mut guard, source)?;
+    }
+    Ok(is_valid)
+}
+
+// 尝试前检查退避时间与锁定状态，返回当前的失败记录
+fn check_rate_limit(
+    storage: &StorageService,
+    allow_locked_out: bool,
+) -> Result<AuthGuardState, String> {
+    let guard = storage.load_auth_guard().map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now();
+    let checked = if allow_locked_out {
+        RateLimiter::check_backoff(&guard, now)
+    } else {
+        RateLimiter::check(&guard, now)
+    };
+    checked.map_err(|e| e.to_string())?;
+    Ok(guard)
+}
+
+// 记录一次失败尝试；策略要求清除时删除存储文件并清空会话
+fn record_failed_attempt(
+    crypto: &mut CryptoService,
+    storage: &mut StorageService,
+    guard: &mut AuthGuardState,
+    source: &str,
+) -> Result<(), String> {
+    match RateLimiter::record_failure(guard, source, chrono::Utc::now()) {
+        FailureOutcome::Wipe => {
+            crypto.clear_master_key();
+            storage.reset_storage().map_err(|e| e.to_string())?;
+            Err(AppError::VaultWiped.to_string())
+        }
+        FailureOutcome::Backoff | FailureOutcome::LockedOut => {
+            storage.save_auth_guard(guard).map_err(|e| e.to_string())
+        }
+    }
+}
+
+// 成功后清零连续失败计数
+fn record_successful_attempt(
+    storage: &mut StorageService,
+    guard: &mut AuthGuardState,
+) -> Result<(), String> {
+    if RateLimiter::record_success(guard) {
+        storage.save_auth_guard(guard).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 写入新的密钥槽列表；头部是加密数据的关联数据，因此数据需按新头部重新加密。
+// 写入成功后才更新内存中的密钥槽
+fn update_key_slots(
+    crypto: &mut CryptoService,
+    storage: &mut StorageService,
+    encrypted_data: &EncryptedData,
+    key_slots: Vec<KeySlot>,
+) -> Result<(), String> {
+    let plaintext = Zeroizing::new(crypto.decrypt(encrypted_data).map_err(|e| e.to_string())?);
+    let encrypted = crypto
+        .encrypt_for_slots(&plaintext, &key_slots)
+        .map_err(|e| e.to_string())?;
     storage
-        .save_encrypted_data(&encrypted, &salt, &master_key_hash)
-        .map_err(|e| e.to_string())
+        .save_encrypted_data(&encrypted, &key_slots)
+        .map_err(|e| e.to_string())?;
+
+    crypto.set_header(VaultHeader::Envelope(key_slots));
+    Ok(())
+}
+
+// 辅助函数：锁定加密与存储服务，在存储数据仓库上执行操作
+fn with_vault<T>(
+    crypto_state: &CryptoState<'_>,
+    storage_state: &StorageState<'_>,
+    f: impl FnOnce(&mut VaultRepository) -> Result<T, String>,
+) -> Result<T, String> {
+    let crypto = crypto_state.lock().map_err(|e| e.to_string())?;
+    let mut storage = storage_state.lock().map_err(|e| e.to_string())?;
+    // 整个读取-修改-保存过程持有存储文件锁
+    let _lock = storage.lock().map_err(|e| e.to_string())?;
+    f(&mut VaultRepository::new(&crypto, &mut storage))
 }
 
 // 字符串格式化测试
@@ -1010,12 +2179,31 @@ pub async fn save_ssh_config(
         .map_err(|e| e.to_string())
 }
 
+// 对系统 SSH 配置做结构化修改（增删改 Host、排序、设置选项），保留注释与格式
+#[tauri::command]
+pub async fn edit_ssh_config(
+    edits: Vec<crate::types::SshConfigEdit>,
+    file_path: Option<String>,
+    retention: Option<usize>,
+) -> Result<String, String> {
+    SshConfigService::edit_config(file_path.as_deref(), &edits, retention)
+        .map_err(|e| e.to_string())
+}
+
 // 读取并解析系统 SSH 配置
 #[tauri::command]
 pub async fn read_ssh_config(file_path: Option<String>) -> Result<crate::types::SshConfig, String> {
     SshConfigService::read_config(file_path.as_deref()).map_err(|e| e.to_string())
 }
 
+// 读取系统 SSH 配置的完整语法树（含 Match 块与展开后的 Include），语法错误时给出行号
+#[tauri::command]
+pub async fn parse_ssh_config(
+    file_path: Option<String>,
+) -> Result<crate::types::SshConfigAst, String> {
+    SshConfigService::read_config_ast(file_path.as_deref()).map_err(|e| e.to_string())
+}
+
 // 列出 ~/.ssh 目录下的私钥文件
 #[tauri::command]
 pub async fn list_identity_files(dir_path: Option<String>) -> Result<Vec<String>, String> {
@@ -1149,3 +2337,312 @@ pub async fn backup_export_files(dir_path: String) -> Result<bool, String> {
 
     Ok(true)
 }
+
+#[cfg(test)]
+mod master_password_tests {
+    use super::{
+        check_master_password, load_session_settings, read_keyfile, rekey_vault,
+        rewrap_password_slot, set_password_keyfile, unlock_backup, update_key_slots,
+    };
+    use crate::error::AppError;
+    use crate::services::CryptoService;
+    use crate::storage::StorageService;
+    use crate::types::{LockoutAction, LockoutPolicy};
+    use tempfile::tempdir;
+
+    fn unlock_from_disk(storage: &StorageService, password: &str) -> Option<Vec<u8>> {
+        let (encrypted_data, header) = storage.load_encrypted_data().unwrap();
+        let mut reloaded = CryptoService::new();
+        reloaded.set_header(header);
+        if !reloaded.unlock(password) {
+            return None;
+        }
+        Some(reloaded.decrypt(&encrypted_data).unwrap())
+    }
+
+    #[test]
+    fn test_rekey_vault_replaces_password_and_keeps_data() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("storage.enc");
+        let mut storage = StorageService::new_for_test(path.clone());
+
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("old_password").unwrap();
+        let data = br#"{"keys":[{"id":"1"}]}"#;
+        let encrypted = crypto.encrypt(data).unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto.key_slots())
+            .unwrap();
+        let old_slots = crypto.key_slots().to_vec();
+        let token = crypto.unlock_token().unwrap();
+
+        rekey_vault(&mut crypto, &mut storage, data, "new_password").unwrap();
+
+        // 内存中的会话已切换到新密钥，之前发出的解锁凭证仍然有效
+        assert!(token.is_valid());
+        assert_ne!(crypto.key_slots(), old_slots.as_slice());
+        assert!(crypto.verify_password("new_password"));
+        assert!(!crypto.verify_password("old_password"));
+
+        // 磁盘上的数据可用新密码解锁，且没有遗留临时文件
+        assert_eq!(unlock_from_disk(&storage, "new_password").unwrap(), data);
+        assert!(unlock_from_disk(&storage, "old_password").is_none());
+        assert!(!dir.path().join("storage.enc.tmp").exists());
+    }
+
+    #[test]
+    fn test_rewrap_password_slot_keeps_data_key() {
+        let dir = tempdir().expect("create temp dir");
+        let mut storage = StorageService::new_for_test(dir.path().join("storage.enc"));
+
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("old_password").unwrap();
+        let data = br#"{"keys":[]}"#;
+        let encrypted = crypto.encrypt(data).unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto.key_slots())
+            .unwrap();
+
+        rewrap_password_slot(&mut crypto, &mut storage, &encrypted, "new_password").unwrap();
+
+        // 只替换了密码槽，数据密钥不变；数据按新的头部重新加密
+        let (reloaded, _) = storage.load_encrypted_data().unwrap();
+        assert_ne!(reloaded.ciphertext, encrypted.ciphertext);
+        assert_eq!(crypto.key_slots().len(), 1);
+        assert!(crypto.verify_password("new_password"));
+        assert!(!crypto.verify_password("old_password"));
+        assert_eq!(crypto.decrypt(&encrypted).unwrap(), data);
+
+        assert_eq!(unlock_from_disk(&storage, "new_password").unwrap(), data);
+        assert!(unlock_from_disk(&storage, "old_password").is_none());
+    }
+    #[test]
+    fn test_recovery_key_resets_master_password() {
+        let dir = tempdir().expect("create temp dir");
+        let mut storage = StorageService::new_for_test(dir.path().join("storage.enc"));
+
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("forgotten_password").unwrap();
+        let data = br#"{"keys":[{"id":"1"}]}"#;
+        let encrypted = crypto.encrypt(data).unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto.key_slots())
+            .unwrap();
+
+        let recovery_key = CryptoService::generate_recovery_key();
+        let key_slots = crypto.rewrap_recovery_key_slot(&recovery_key).unwrap();
+        update_key_slots(&mut crypto, &mut storage, &encrypted, key_slots).unwrap();
+
+        // 使用恢复密钥（大小写与分隔符不敏感）解锁并设置新密码
+        let (encrypted_data, header) = storage.load_encrypted_data().unwrap();
+        let mut recovered = CryptoService::new();
+        recovered.set_header(header);
+        assert!(recovered.has_recovery_key());
+        assert!(!recovered.unlock("wrong_password"));
+        assert!(recovered.unlock_with_recovery_key(&recovery_key.to_lowercase().replace('-', " ")));
+        rewrap_password_slot(
+            &mut recovered,
+            &mut storage,
+            &encrypted_data,
+            "new_password",
+        )
+        .unwrap();
+
+        assert_eq!(unlock_from_disk(&storage, "new_password").unwrap(), data);
+        assert!(unlock_from_disk(&storage, "forgotten_password").is_none());
+
+        // 撤销后恢复密钥不再可用
+        let key_slots = recovered.without_recovery_key_slot();
+        update_key_slots(&mut recovered, &mut storage, &encrypted_data, key_slots).unwrap();
+        let (_, header) = storage.load_encrypted_data().unwrap();
+        let mut revoked = CryptoService::new();
+        revoked.set_header(header);
+        assert!(!revoked.has_recovery_key());
+        assert!(!revoked.unlock_with_recovery_key(&recovery_key));
+        assert_eq!(unlock_from_disk(&storage, "new_password").unwrap(), data);
+    }
+    #[test]
+    fn test_keyfile_enable_rotate_disable() {
+        let dir = tempdir().expect("create temp dir");
+        let mut storage = StorageService::new_for_test(dir.path().join("storage.enc"));
+        let keyfile_path = dir.path().join("vault.key");
+        let new_keyfile_path = dir.path().join("vault2.key");
+        std::fs::write(&keyfile_path, b"first keyfile").unwrap();
+        std::fs::write(&new_keyfile_path, b"second keyfile").unwrap();
+
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("master_password").unwrap();
+        let data = br#"{"keys":[]}"#;
+        let encrypted = crypto.encrypt(data).unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto.key_slots())
+            .unwrap();
+
+        let keyfile = read_keyfile(keyfile_path.to_str()).unwrap();
+        set_password_keyfile(&mut crypto, &mut storage, "master_password", keyfile).unwrap();
+        assert!(crypto.requires_keyfile());
+        assert!(unlock_from_disk(&storage, "master_password").is_none());
+
+        let unlock_with = |storage: &StorageService, keyfile: Option<[u8; 32]>| {
+            let (encrypted_data, header) = storage.load_encrypted_data().unwrap();
+            let mut reloaded = CryptoService::new();
+            reloaded.set_header(header);
+            reloaded.set_keyfile(keyfile);
+            reloaded
+                .try_unlock("master_password")
+                .map(|ok| ok.then(|| reloaded.decrypt(&encrypted_data).unwrap()))
+        };
+        assert_eq!(unlock_with(&storage, keyfile).unwrap().unwrap(), data);
+
+        let new_keyfile = read_keyfile(new_keyfile_path.to_str()).unwrap();
+        set_password_keyfile(&mut crypto, &mut storage, "master_password", new_keyfile).unwrap();
+        assert_eq!(unlock_with(&storage, keyfile).unwrap(), None);
+        assert_eq!(unlock_with(&storage, new_keyfile).unwrap().unwrap(), data);
+
+        set_password_keyfile(&mut crypto, &mut storage, "master_password", None).unwrap();
+        assert!(!crypto.requires_keyfile());
+        assert_eq!(unlock_from_disk(&storage, "master_password").unwrap(), data);
+    }
+
+    #[test]
+    fn test_change_password_keeps_unlock_session() {
+        let dir = tempdir().expect("create temp dir");
+        let mut storage = StorageService::new_for_test(dir.path().join("storage.enc"));
+
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("old_password").unwrap();
+        let data = br#"{"keys":[]}"#;
+        let encrypted = crypto.encrypt(data).unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto.key_slots())
+            .unwrap();
+        let token = crypto.unlock_token().unwrap();
+
+        // 修改密码只更新当前会话的密钥槽，ssh-agent 持有的解锁凭证继续有效
+        set_password_keyfile(&mut crypto, &mut storage, "new_password", None).unwrap();
+        assert!(token.is_valid());
+        assert!(crypto.verify_password("new_password"));
+        assert_eq!(unlock_from_disk(&storage, "new_password").unwrap(), data);
+
+        crypto.clear_master_key();
+        assert!(!token.is_valid());
+    }
+
+    #[test]
+    fn test_read_keyfile_errors() {
+        let dir = tempdir().expect("create temp dir");
+        assert_eq!(read_keyfile(None).unwrap(), None);
+
+        let missing = dir.path().join("missing.key");
+        let err = read_keyfile(missing.to_str()).unwrap_err();
+        assert!(err.contains("密钥文件不存在"));
+
+        let empty = dir.path().join("empty.key");
+        std::fs::write(&empty, b"").unwrap();
+        assert!(read_keyfile(empty.to_str()).is_err());
+    }
+
+    #[test]
+    fn test_failed_attempts_back_off_and_wipe_by_policy() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("storage.enc");
+        let mut storage = StorageService::new_for_test(path.clone());
+
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("master_password").unwrap();
+        let encrypted = crypto.encrypt(br#"{"keys":[]}"#).unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto.key_slots())
+            .unwrap();
+
+        let mut guard = storage.load_auth_guard().unwrap();
+        guard.lockout_policy = Some(LockoutPolicy {
+            max_failures: 3,
+            action: LockoutAction::Wipe,
+        });
+        storage.save_auth_guard(&guard).unwrap();
+
+        let check = |crypto: &mut CryptoService, storage: &mut StorageService, pw: &str| {
+            check_master_password(crypto, storage, pw, None, "verify_master_password")
+        };
+
+        // 前两次失败只记录，不退避；成功后清零连续失败计数
+        assert!(!check(&mut crypto, &mut storage, "wrong").unwrap());
+        assert!(check(&mut crypto, &mut storage, "master_password").unwrap());
+        assert!(!check(&mut crypto, &mut storage, "wrong").unwrap());
+        assert!(!check(&mut crypto, &mut storage, "wrong").unwrap());
+        let guard = storage.load_auth_guard().unwrap();
+        assert_eq!(guard.consecutive_failures, 2);
+        assert_eq!(guard.failed_attempts.len(), 3);
+        assert_eq!(guard.failed_attempts[0].source, "verify_master_password");
+
+        // 第三次连续失败触发清除策略
+        let err = check(&mut crypto, &mut storage, "wrong").unwrap_err();
+        assert_eq!(err, AppError::VaultWiped.to_string());
+        assert!(!crypto.is_authenticated());
+        assert!(!storage.is_initialized());
+    }
+
+    #[test]
+    fn test_unlock_backup_uses_the_snapshot_password() {
+        let dir = tempdir().expect("create temp dir");
+        let mut storage = StorageService::new_for_test(dir.path().join("storage.enc"));
+
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("old_password").unwrap();
+        let encrypted = crypto.encrypt(br#"{"keys":[]}"#).unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto.key_slots())
+            .unwrap();
+        let snapshot = storage.list_backups().unwrap()[0].name.clone();
+
+        rewrap_password_slot(&mut crypto, &mut storage, &encrypted, "new_password").unwrap();
+
+        // 快照仍使用保存时的密码槽
+        assert!(
+            unlock_backup(&crypto, &storage, &snapshot, "new_password", None)
+                .unwrap()
+                .is_none()
+        );
+        let (unlocked, encrypted_data) =
+            unlock_backup(&crypto, &storage, &snapshot, "old_password", None)
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            unlocked.decrypt(&encrypted_data).unwrap(),
+            br#"{"keys":[]}"#
+        );
+
+        // 未解锁的会话不能访问快照
+        crypto.clear_master_key();
+        assert!(unlock_backup(&crypto, &storage, &snapshot, "old_password", None).is_err());
+    }
+
+    #[test]
+    fn test_session_settings_fall_back_only_when_storage_locked() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("storage.enc");
+        let mut storage = StorageService::new_for_test(path.clone());
+
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("password").unwrap();
+        let encrypted = crypto.encrypt(br#"{"keys":[]}"#).unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto.key_slots())
+            .unwrap();
+
+        // 其他进程占用存储时按默认配置解锁
+        let other = StorageService::new_for_test(path);
+        let held = other.lock().unwrap();
+        assert!(load_session_settings(&crypto, &mut storage).is_ok());
+        drop(held);
+
+        // 其他错误原样返回，例如存储已被另一组密钥槽改写
+        let mut stale = CryptoService::new();
+        stale.set_master_key("password").unwrap();
+        assert!(matches!(
+            load_session_settings(&stale, &mut storage),
+            Err(AppError::StorageChanged)
+        ));
+    }
+}
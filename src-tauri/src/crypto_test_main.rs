@@ -8,7 +8,7 @@ fn main() {
     let password = "test123456";
     let salt = [1u8; 32];
     
-    let key = CryptoService::derive_key(password, &salt);
+    let key = CryptoService::derive_key(password, &salt).unwrap();
     println!("派生密钥: {}...", &format!("{:x?}", &key[..8]));
     
     // 测试AES-256-GCM加密/解密
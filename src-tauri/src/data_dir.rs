@@ -0,0 +1,140 @@
+use crate::error::{AppError, AppResult};
+use crate::types::{DataDirLocation, DataDirSource};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// 数据目录的优先级：命令行 --data-dir > 环境变量 > 便携模式 > <config_dir>/sshmanager
+pub const DATA_DIR_ENV: &str = "SSHMANAGER_DATA_DIR";
+
+const DATA_DIR_ARG: &str = "--data-dir";
+// 便携模式：命令行 --portable，或可执行文件旁存在 portable 文件；数据保存在可执行文件旁的 data 目录
+const PORTABLE_ARG: &str = "--portable";
+const PORTABLE_MARKER: &str = "portable";
+const PORTABLE_DATA_DIR: &str = "data";
+
+pub fn resolve() -> AppResult<DataDirLocation> {
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+
+    let mut location = resolve_from(
+        &args,
+        std::env::var_os(DATA_DIR_ENV),
+        exe_dir.as_deref(),
+        dirs::config_dir(),
+    )?;
+    // 相对路径按当前工作目录解析，避免之后切换目录时指向别处
+    location.path = std::path::absolute(&location.path)?;
+    Ok(location)
+}
+
+fn resolve_from(
+    args: &[OsString],
+    env_dir: Option<OsString>,
+    exe_dir: Option<&Path>,
+    config_dir: Option<PathBuf>,
+) -> AppResult<DataDirLocation> {
+    let mut arg_dir = None;
+    let mut portable = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let Some(text) = arg.to_str() else {
+            continue;
+        };
+        if text == DATA_DIR_ARG {
+            let dir = iter
+                .next()
+                .ok_or_else(|| AppError::ConfigError(format!("{} 需要指定目录", DATA_DIR_ARG)))?;
+            arg_dir = Some(PathBuf::from(dir));
+        } else if let Some(dir) = text.strip_prefix("--data-dir=") {
+            arg_dir = Some(PathBuf::from(dir));
+        } else if text == PORTABLE_ARG {
+            portable = true;
+        }
+    }
+
+    let non_empty = |dir: PathBuf| (!dir.as_os_str().is_empty()).then_some(dir);
+    if let Some(path) = arg_dir.and_then(non_empty) {
+        return Ok(DataDirLocation {
+            path,
+            source: DataDirSource::CommandLine,
+        });
+    }
+    if let Some(path) = env_dir.map(PathBuf::from).and_then(non_empty) {
+        return Ok(DataDirLocation {
+            path,
+            source: DataDirSource::Environment,
+        });
+    }
+
+    if let Some(exe_dir) = exe_dir {
+        if portable || exe_dir.join(PORTABLE_MARKER).is_file() {
+            return Ok(DataDirLocation {
+                path: exe_dir.join(PORTABLE_DATA_DIR),
+                source: DataDirSource::Portable,
+            });
+        }
+    }
+
+    let config_dir =
+        config_dir.ok_or_else(|| AppError::ConfigError("无法获取配置目录".to_string()))?;
+    Ok(DataDirLocation {
+        path: config_dir.join("sshmanager"),
+        source: DataDirSource::Default,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn args(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_data_dir_precedence() {
+        let exe = tempdir().expect("create temp dir");
+        let config = Some(PathBuf::from("/config"));
+        let env = || Some(OsString::from("/from-env"));
+
+        let location = resolve_from(&args(&[]), None, Some(exe.path()), config.clone()).unwrap();
+        assert_eq!(location.source, DataDirSource::Default);
+        assert_eq!(location.path, PathBuf::from("/config/sshmanager"));
+
+        let location = resolve_from(&args(&[]), env(), Some(exe.path()), config.clone()).unwrap();
+        assert_eq!(location.source, DataDirSource::Environment);
+        assert_eq!(location.path, PathBuf::from("/from-env"));
+
+        for cli in [
+            &["--data-dir", "/from-cli"][..],
+            &["--data-dir=/from-cli"][..],
+        ] {
+            let location =
+                resolve_from(&args(cli), env(), Some(exe.path()), config.clone()).unwrap();
+            assert_eq!(location.source, DataDirSource::CommandLine);
+            assert_eq!(location.path, PathBuf::from("/from-cli"));
+        }
+        assert!(resolve_from(&args(&["--data-dir"]), None, None, config.clone()).is_err());
+
+        // 便携模式：命令行参数或可执行文件旁的标记文件
+        let location = resolve_from(
+            &args(&["--portable"]),
+            None,
+            Some(exe.path()),
+            config.clone(),
+        )
+        .unwrap();
+        assert_eq!(location.source, DataDirSource::Portable);
+        assert_eq!(location.path, exe.path().join("data"));
+
+        std::fs::write(exe.path().join("portable"), "").unwrap();
+        let location = resolve_from(&args(&[]), None, Some(exe.path()), config.clone()).unwrap();
+        assert_eq!(location.source, DataDirSource::Portable);
+        // 显式指定的目录优先于便携模式
+        let location = resolve_from(&args(&[]), env(), Some(exe.path()), config).unwrap();
+        assert_eq!(location.source, DataDirSource::Environment);
+    }
+}
@@ -20,9 +20,51 @@ pub enum AppError {
     #[error("无效的密钥类型")]
     InvalidKeyType,
     
+    #[error("私钥密码错误")]
+    InvalidPassphrase,
+    
+    #[error("私钥受密码保护，需要提供密码")]
+    PassphraseRequired,
+    
+    #[error("需要密钥文件才能解锁")]
+    KeyfileRequired,
+    
+    #[error("尝试次数过多，请在 {0} 秒后重试")]
+    RateLimited(u64),
+    
+    #[error("连续失败次数过多，密码解锁已被禁用，请使用恢复密钥")]
+    LockedOut,
+    
+    #[error("连续失败次数过多，数据已按策略清除")]
+    VaultWiped,
+    
+    #[error("数据完整性验证失败")]
+    StorageCorrupted,
+    
+    #[error("存储文件头部校验失败，文件可能已被篡改")]
+    HeaderTampered,
+    
+    #[error("存储文件正被其他进程使用，请稍后重试")]
+    StorageLocked,
+    
+    #[error("存储文件已被其他进程修改，请重新加载后再试")]
+    StorageChanged,
+    
+    #[error("存储数据字段 {0} 无效: {1}")]
+    InvalidVaultField(String, String),
+    
+    #[error("数据由更新版本的应用写入（版本 {0}，当前支持 {1}），请升级后再打开")]
+    UnsupportedVersion(String, String),
+    
     #[error("配置错误: {0}")]
     ConfigError(String),
     
+    #[error("SSH 配置{0}第 {1} 行: {2}")]
+    ConfigSyntax(String, usize, String),
+    
+    #[error("ssh-agent 错误: {0}")]
+    AgentError(String),
+    
     #[error("未知错误: {0}")]
     Unknown(String),
 }
@@ -1,36 +1,93 @@
 pub mod commands;
+pub mod data_dir;
 pub mod error;
+pub mod migrations;
+pub mod profiles;
 pub mod services;
 pub mod storage;
 pub mod types;
 pub mod utils;
+pub mod vault;
 
 #[cfg(test)]
 mod tests;
 
 use commands::*;
-use services::CryptoService;
+use profiles::VaultProfiles;
+use services::session::IDLE_CHECK_INTERVAL;
+use services::{AgentService, CryptoService, SessionService};
 use std::sync::Mutex;
-use storage::StorageService;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let profiles = VaultProfiles::new().expect("存储列表初始化失败");
+    let storage = profiles.open_storage().expect("存储服务初始化失败");
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_process::init())
         .manage(Mutex::new(CryptoService::new()))
-        .manage(Mutex::new(
-            StorageService::new().expect("存储服务初始化失败"),
-        ))
+        .manage(Mutex::new(storage))
+        .manage(Mutex::new(SessionService::new()))
+        .manage(Mutex::new(profiles))
+        .manage(Mutex::new(AgentService::new()))
+        .setup(|app| {
+            // 后台定期检查会话是否空闲超时
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(IDLE_CHECK_INTERVAL);
+                lock_if_idle(&handle);
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             is_initialized,
+            can_recover_storage,
+            recover_storage,
             initialize_app,
             authenticate,
+            change_master_password,
+            generate_recovery_key,
+            revoke_recovery_key,
+            has_recovery_key,
+            recover_with_recovery_key,
+            is_keyfile_required,
+            enable_keyfile,
+            rotate_keyfile,
+            disable_keyfile,
+            lock_vault,
+            touch_session,
+            get_auto_lock_minutes,
+            set_auto_lock_minutes,
+            get_auth_guard_state,
+            clear_failed_attempts,
+            set_lockout_policy,
+            list_vault_backups,
+            preview_vault_backup,
+            restore_vault_backup,
+            list_vaults,
+            get_active_vault,
+            create_vault,
+            switch_vault,
+            open_vault,
+            rename_vault,
+            delete_vault,
+            get_data_dir,
+            move_vault,
+            start_agent,
+            stop_agent,
+            get_agent_status,
+            respond_agent_confirmation,
+            get_agent_sign_log,
+            add_keys_to_system_agent,
+            list_system_agent_keys,
+            remove_system_agent_keys,
             generate_ssh_key,
             get_all_keys,
             delete_key,
             update_key_info,
+            change_key_passphrase,
             export_key,
             export_keys_to_file,
             export_all_keys_encrypted,
@@ -40,8 +97,12 @@ pub fn run() {
             write_file_content,
             ensure_dir_exists,
             save_ssh_config,
+            edit_ssh_config,
             read_ssh_config,
+            parse_ssh_config,
             list_identity_files,
+            import_identity_files,
+            import_private_key,
             check_file_exists,
             import_keys,
             import_encrypted_keys,
@@ -0,0 +1,164 @@
+use crate::error::{AppError, AppResult};
+use serde_json::{Map, Value};
+
+// 加密数据（Vault）的结构版本，记录在解密后数据的 schema_version 字段中；
+// 没有该字段的数据视为版本 0
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+struct Migration {
+    // 迁移前的版本，执行后版本号加一
+    from: u32,
+    apply: fn(&mut Map<String, Value>) -> AppResult<()>,
+}
+
+// 按版本顺序排列，每个版本恰好一项（下标即迁移前的版本）
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        apply: add_key_annotations,
+    },
+    Migration {
+        from: 1,
+        apply: normalize_key_type_names,
+    },
+];
+
+pub fn schema_version(payload: &Value) -> AppResult<u32> {
+    match payload.get("schema_version") {
+        None | Some(Value::Null) => Ok(0),
+        Some(value) => value
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| {
+                AppError::InvalidVaultField("schema_version".to_string(), value.to_string())
+            }),
+    }
+}
+
+// 依次执行迁移并写入新版本号，返回是否有数据被迁移；
+// 由更新版本的应用写入的数据直接拒绝，避免丢失无法识别的字段
+pub fn migrate(payload: &mut Value) -> AppResult<bool> {
+    let mut version = schema_version(payload)?;
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(AppError::UnsupportedVersion(
+            version.to_string(),
+            CURRENT_SCHEMA_VERSION.to_string(),
+        ));
+    }
+    if version == CURRENT_SCHEMA_VERSION {
+        return Ok(false);
+    }
+
+    let vault = payload.as_object_mut().ok_or_else(|| {
+        AppError::InvalidVaultField(String::new(), "存储数据不是 JSON 对象".to_string())
+    })?;
+    for migration in &MIGRATIONS[version as usize..] {
+        debug_assert_eq!(migration.from, version);
+        (migration.apply)(vault)?;
+        version = migration.from + 1;
+    }
+    vault.insert("schema_version".to_string(), Value::from(version));
+    Ok(true)
+}
+
+fn keys_mut(vault: &mut Map<String, Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    vault
+        .get_mut("keys")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+// 0 -> 1：密钥增加标签、备注与过期时间
+fn add_key_annotations(vault: &mut Map<String, Value>) -> AppResult<()> {
+    for key in keys_mut(vault) {
+        key.entry("tags")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        key.entry("notes")
+            .or_insert_with(|| Value::String(String::new()));
+        key.entry("expires_at").or_insert(Value::Null);
+    }
+    Ok(())
+}
+
+// 1 -> 2：早期版本写入的 "Rsa"/"Ecdsa" 统一为 "RSA"/"ECDSA"
+fn normalize_key_type_names(vault: &mut Map<String, Value>) -> AppResult<()> {
+    fn normalize(value: Option<&mut Value>) {
+        if let Some(value) = value {
+            let canonical = match value.as_str() {
+                Some("Rsa") => "RSA",
+                Some("Ecdsa") => "ECDSA",
+                _ => return,
+            };
+            *value = Value::String(canonical.to_string());
+        }
+    }
+
+    for key in keys_mut(vault) {
+        normalize(key.get_mut("key_type"));
+    }
+    if let Some(config) = vault.get_mut("config").and_then(Value::as_object_mut) {
+        normalize(config.get_mut("default_key_type"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::parse_vault;
+
+    const V0: &str = include_str!("../tests/fixtures/vault_v0.json");
+    const V1: &str = include_str!("../tests/fixtures/vault_v1.json");
+
+    fn migrated(fixture: &str) -> Value {
+        let mut payload: Value = serde_json::from_str(fixture).unwrap();
+        assert!(migrate(&mut payload).unwrap());
+        assert_eq!(schema_version(&payload).unwrap(), CURRENT_SCHEMA_VERSION);
+        payload
+    }
+
+    #[test]
+    fn test_migrate_v0_adds_key_annotations() {
+        let payload = migrated(V0);
+        let key = &payload["keys"][0];
+        assert_eq!(key["tags"], serde_json::json!([]));
+        assert_eq!(key["notes"], "");
+        assert!(key["expires_at"].is_null());
+        // 已有的数据保持不变
+        assert_eq!(key["name"], "legacy");
+        assert_eq!(payload["config"]["backup_retention"], 5);
+
+        let vault = parse_vault(V0.as_bytes()).unwrap();
+        assert_eq!(vault.schema_version, CURRENT_SCHEMA_VERSION);
+        assert!(vault.keys[0].tags.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_v1_normalizes_key_type_names() {
+        let payload = migrated(V1);
+        assert_eq!(payload["keys"][0]["key_type"], "RSA");
+        assert_eq!(payload["keys"][1]["key_type"], "ECDSA");
+        assert_eq!(payload["config"]["default_key_type"], "RSA");
+        // 1 -> 2 不应覆盖已有的标签
+        assert_eq!(payload["keys"][0]["tags"], serde_json::json!(["work"]));
+    }
+
+    #[test]
+    fn test_current_and_newer_versions() {
+        let mut payload = migrated(V1);
+        assert!(!migrate(&mut payload).unwrap());
+
+        payload["schema_version"] = Value::from(CURRENT_SCHEMA_VERSION + 1);
+        assert!(matches!(
+            migrate(&mut payload),
+            Err(AppError::UnsupportedVersion(..))
+        ));
+        let newer = serde_json::to_vec(&payload).unwrap();
+        assert!(matches!(
+            parse_vault(&newer),
+            Err(AppError::UnsupportedVersion(..))
+        ));
+    }
+}
@@ -0,0 +1,329 @@
+use crate::data_dir;
+use crate::error::{AppError, AppResult};
+use crate::storage::StorageService;
+use crate::types::VaultProfileInfo;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+// 默认存储沿用应用数据目录下的 storage.enc，其余命名存储位于 vaults/<名称>/storage.enc；
+// 移动过的存储记录在 vaults.json 的 locations 中
+pub const DEFAULT_VAULT_NAME: &str = "default";
+
+const STORAGE_FILE_NAME: &str = "storage.enc";
+const VAULTS_DIR: &str = "vaults";
+// 记录上次打开的存储，下次启动时沿用
+const PROFILES_FILE: &str = "vaults.json";
+const CORRUPT_PROFILES_FILE: &str = "vaults.json.corrupt";
+const MAX_VAULT_NAME_LEN: usize = 64;
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct ProfilesFile {
+    active: String,
+    // 存储名称 -> 移动后的存储目录
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    locations: BTreeMap<String, PathBuf>,
+}
+
+// 管理多个命名存储（如工作、个人、客户），每个存储有独立的文件与主密码
+pub struct VaultProfiles {
+    app_dir: PathBuf,
+    active: String,
+    locations: BTreeMap<String, PathBuf>,
+}
+
+impl VaultProfiles {
+    pub fn new() -> AppResult<Self> {
+        Self::with_app_dir(data_dir::resolve()?.path)
+    }
+
+    pub fn with_app_dir(app_dir: PathBuf) -> AppResult<Self> {
+        std::fs::create_dir_all(&app_dir)?;
+
+        let path = app_dir.join(PROFILES_FILE);
+        let file = match std::fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<ProfilesFile>(&content) {
+                Ok(file) => file,
+                // 文件损坏时改名保留（其中可能记录了移动过的存储位置），再按默认设置打开
+                Err(_) => {
+                    std::fs::rename(&path, app_dir.join(CORRUPT_PROFILES_FILE))?;
+                    ProfilesFile::default()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ProfilesFile::default(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut profiles = Self {
+            app_dir,
+            active: DEFAULT_VAULT_NAME.to_string(),
+            locations: file.locations,
+        };
+
+        // 记录的存储已被删除时回到默认存储
+        if Self::validate_name(&file.active).is_ok() && profiles.exists(&file.active) {
+            profiles.active = file.active;
+        }
+        Ok(profiles)
+    }
+
+    pub fn app_dir(&self) -> &Path {
+        &self.app_dir
+    }
+
+    pub fn active(&self) -> &str {
+        &self.active
+    }
+
+    // 当前存储的存储服务
+    pub fn open_storage(&self) -> AppResult<StorageService> {
+        StorageService::with_path(self.storage_path(&self.active)?)
+    }
+
+    pub fn storage_path(&self, name: &str) -> AppResult<PathBuf> {
+        Self::validate_name(name)?;
+        let dir = match self.locations.get(name) {
+            Some(dir) => dir.clone(),
+            None => self.default_location(name),
+        };
+        Ok(dir.join(STORAGE_FILE_NAME))
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        name == DEFAULT_VAULT_NAME
+            || self.locations.contains_key(name)
+            || Self::vault_dir(&self.app_dir, name).is_some_and(|dir| dir.is_dir())
+    }
+
+    // 默认存储在前，其余按名称排序；is_unlocked 由调用方按会话状态填写
+    pub fn list(&self) -> AppResult<Vec<VaultProfileInfo>> {
+        let mut names: BTreeSet<String> = self.locations.keys().cloned().collect();
+        let vaults_dir = self.app_dir.join(VAULTS_DIR);
+        if vaults_dir.is_dir() {
+            for entry in std::fs::read_dir(&vaults_dir)? {
+                let entry = entry?;
+                let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                    continue;
+                };
+                if entry.file_type()?.is_dir() && Self::validate_name(&name).is_ok() {
+                    names.insert(name);
+                }
+            }
+        }
+        names.remove(DEFAULT_VAULT_NAME);
+
+        std::iter::once(DEFAULT_VAULT_NAME.to_string())
+            .chain(names)
+            .map(|name| {
+                let storage = StorageService::with_path(self.storage_path(&name)?)?;
+                Ok(VaultProfileInfo {
+                    is_active: name == self.active,
+                    is_initialized: storage.is_initialized(),
+                    is_unlocked: false,
+                    storage_path: storage.storage_path().clone(),
+                    name,
+                })
+            })
+            .collect()
+    }
+
+    // 创建空的存储目录，返回其中的存储文件路径
+    pub fn create(&self, name: &str) -> AppResult<PathBuf> {
+        if self.exists(name) {
+            return Err(AppError::ConfigError(format!("存储已存在: {}", name)));
+        }
+        let path = self.storage_path(name)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(path)
+    }
+
+    pub fn set_active(&mut self, name: &str) -> AppResult<()> {
+        self.ensure_exists(name)?;
+        self.active = name.to_string();
+        self.save()
+    }
+
+    // 默认存储不能重命名；重命名当前存储时同步更新记录
+    pub fn rename(&mut self, name: &str, new_name: &str) -> AppResult<PathBuf> {
+        Self::ensure_named(name)?;
+        self.ensure_exists(name)?;
+        if self.exists(new_name) {
+            return Err(AppError::ConfigError(format!("存储已存在: {}", new_name)));
+        }
+
+        Self::validate_name(new_name)?;
+
+        // 移动过的存储只需更新记录，文件保留在原目录
+        if let Some(dir) = self.locations.remove(name) {
+            self.locations.insert(new_name.to_string(), dir);
+        } else {
+            std::fs::rename(self.default_location(name), self.default_location(new_name))?;
+        }
+
+        if self.active == name {
+            self.active = new_name.to_string();
+        }
+        self.save()?;
+        self.storage_path(new_name)
+    }
+
+    // 记录存储移动后的目录；移回默认位置时删除记录
+    pub fn relocate(&mut self, name: &str, dir: PathBuf) -> AppResult<()> {
+        self.ensure_exists(name)?;
+        if dir == self.default_location(name) {
+            self.locations.remove(name);
+        } else {
+            self.locations.insert(name.to_string(), dir);
+        }
+        self.save()
+    }
+
+    // 删除存储文件（包括其中的快照）；当前打开的存储需先切换
+    pub fn delete(&mut self, name: &str) -> AppResult<()> {
+        Self::ensure_named(name)?;
+        self.ensure_exists(name)?;
+        if self.active == name {
+            return Err(AppError::ConfigError(
+                "不能删除当前打开的存储，请先切换到其他存储".to_string(),
+            ));
+        }
+
+        // 移动到其他目录的存储只删除自己的文件，目录中可能还有其他内容
+        if self.locations.contains_key(name) {
+            StorageService::with_path(self.storage_path(name)?)?.remove_files()?;
+            self.locations.remove(name);
+            self.save()?;
+        }
+        let dir = self.default_location(name);
+        if dir.is_dir() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> AppResult<()> {
+        let content = serde_json::to_string(&ProfilesFile {
+            active: self.active.clone(),
+            locations: self.locations.clone(),
+        })?;
+        std::fs::write(self.app_dir.join(PROFILES_FILE), content)?;
+        Ok(())
+    }
+
+    fn ensure_exists(&self, name: &str) -> AppResult<()> {
+        Self::validate_name(name)?;
+        if !self.exists(name) {
+            return Err(AppError::ConfigError(format!("存储不存在: {}", name)));
+        }
+        Ok(())
+    }
+
+    fn ensure_named(name: &str) -> AppResult<()> {
+        if name == DEFAULT_VAULT_NAME {
+            return Err(AppError::ConfigError(
+                "默认存储不能重命名或删除".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // 命名存储所在目录；默认存储返回 None
+    fn vault_dir(app_dir: &Path, name: &str) -> Option<PathBuf> {
+        (name != DEFAULT_VAULT_NAME).then(|| app_dir.join(VAULTS_DIR).join(name))
+    }
+
+    // 未移动时存储所在的目录
+    fn default_location(&self, name: &str) -> PathBuf {
+        Self::vault_dir(&self.app_dir, name).unwrap_or_else(|| self.app_dir.clone())
+    }
+
+    // 名称用作目录名：只允许字母、数字、- 与 _
+    fn validate_name(name: &str) -> AppResult<()> {
+        let valid = !name.is_empty()
+            && name.chars().count() <= MAX_VAULT_NAME_LEN
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(AppError::ConfigError(format!(
+                "存储名称无效: {}（只能包含字母、数字、- 与 _，最长 {} 个字符）",
+                name, MAX_VAULT_NAME_LEN
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_create_switch_rename_and_delete_vaults() {
+        let dir = tempdir().expect("create temp dir");
+        let mut profiles = VaultProfiles::with_app_dir(dir.path().to_path_buf()).unwrap();
+        assert_eq!(profiles.active(), DEFAULT_VAULT_NAME);
+        assert_eq!(
+            profiles.storage_path(DEFAULT_VAULT_NAME).unwrap(),
+            dir.path().join("storage.enc")
+        );
+
+        let work = profiles.create("work").unwrap();
+        assert_eq!(work, dir.path().join("vaults/work/storage.enc"));
+        profiles.create("personal").unwrap();
+        assert!(profiles.create("work").is_err());
+        assert!(profiles.create("../escape").is_err());
+        assert!(profiles.create("").is_err());
+
+        let names: Vec<_> = profiles
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|v| v.name)
+            .collect();
+        assert_eq!(names, ["default", "personal", "work"]);
+
+        // 当前存储会被记录，重新打开时沿用
+        profiles.set_active("work").unwrap();
+        assert!(profiles.delete("work").is_err());
+        let renamed = profiles.rename("work", "client").unwrap();
+        assert_eq!(renamed, dir.path().join("vaults/client/storage.enc"));
+        assert_eq!(profiles.active(), "client");
+        let reopened = VaultProfiles::with_app_dir(dir.path().to_path_buf()).unwrap();
+        assert_eq!(reopened.active(), "client");
+
+        assert!(profiles.rename(DEFAULT_VAULT_NAME, "main").is_err());
+        assert!(profiles.rename("client", "personal").is_err());
+        assert!(profiles.set_active("missing").is_err());
+
+        profiles.delete("personal").unwrap();
+        assert!(!profiles.exists("personal"));
+        assert!(profiles.delete(DEFAULT_VAULT_NAME).is_err());
+
+        // 记录的存储目录被删除后回到默认存储
+        std::fs::remove_dir_all(dir.path().join("vaults/client")).unwrap();
+        let reopened = VaultProfiles::with_app_dir(dir.path().to_path_buf()).unwrap();
+        assert_eq!(reopened.active(), DEFAULT_VAULT_NAME);
+    }
+
+    #[test]
+    fn test_corrupt_or_unreadable_profiles_file() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join(PROFILES_FILE);
+
+        // 内容损坏时改名保留并按默认设置打开
+        std::fs::write(&path, b"{ not json").unwrap();
+        let profiles = VaultProfiles::with_app_dir(dir.path().to_path_buf()).unwrap();
+        assert_eq!(profiles.active(), DEFAULT_VAULT_NAME);
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read(dir.path().join(CORRUPT_PROFILES_FILE)).unwrap(),
+            b"{ not json"
+        );
+
+        // 无法读取时返回错误，而不是当作没有记录
+        std::fs::create_dir(&path).unwrap();
+        assert!(VaultProfiles::with_app_dir(dir.path().to_path_buf()).is_err());
+    }
+}
@@ -0,0 +1,754 @@
+use crate::error::{AppError, AppResult};
+use crate::services::crypto::UnlockToken;
+use crate::services::key_format::rsa_private_key;
+use crate::services::SshKeyService;
+use crate::types::{
+    AgentConfirmRequest, AgentKeyConstraints, AgentPeerInfo, AgentSignOutcome, AgentSignRecord,
+    AgentStatus, SshKeyPair,
+};
+use rsa::signature::{SignatureEncoding, Signer};
+use sha2::{Sha256, Sha512};
+use ssh_key::encoding::Encode;
+use ssh_key::private::KeypairData;
+use ssh_key::{Algorithm, HashAlg, PrivateKey, Signature, SigningKey};
+use std::collections::{HashMap, VecDeque};
+#[cfg(unix)]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// ssh-agent 协议（draft-miller-ssh-agent）中用到的消息类型
+pub const SSH_AGENT_FAILURE: u8 = 5;
+pub const SSH_AGENT_SUCCESS: u8 = 6;
+pub const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+pub const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+pub const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+pub const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+pub const SSH_AGENTC_ADD_IDENTITY: u8 = 17;
+pub const SSH_AGENTC_REMOVE_IDENTITY: u8 = 18;
+pub const SSH_AGENTC_ADD_ID_CONSTRAINED: u8 = 25;
+
+// 添加密钥时附带的限制
+pub const SSH_AGENT_CONSTRAIN_LIFETIME: u8 = 1;
+pub const SSH_AGENT_CONSTRAIN_CONFIRM: u8 = 2;
+
+// 签名请求中的标志：RSA 密钥使用 SHA-2 签名
+pub const SSH_AGENT_RSA_SHA2_256: u32 = 2;
+pub const SSH_AGENT_RSA_SHA2_512: u32 = 4;
+
+// 代理 socket 文件名及其所在目录名
+pub const AGENT_SOCKET_FILE: &str = "agent.sock";
+const AGENT_SOCKET_DIR: &str = "ssh-key-manager";
+// 需要用户确认签名时发送给前端的事件
+pub const AGENT_CONFIRM_EVENT: &str = "agent-confirm-request";
+
+// 与 OpenSSH 一致，超过该长度的消息直接断开
+#[cfg(unix)]
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+// 后台线程检查停止与锁定状态的间隔
+#[cfg(unix)]
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// 等待用户确认签名的时长，超时视为拒绝
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+// 内存中保留的签名记录条数
+const MAX_SIGN_LOG: usize = 500;
+
+// 需要确认的签名请求到达时调用，负责通知用户（如向前端发送事件），返回是否已送达；
+// 用户的答复通过 AgentService::respond_confirmation 传回
+pub type ConfirmNotifier = Arc<dyn Fn(&AgentConfirmRequest) -> bool + Send + Sync>;
+
+// 由代理提供的密钥：解密后的私钥只保存在内存中，代理停止时释放
+pub struct AgentIdentity {
+    key_id: String,
+    key_name: String,
+    fingerprint: String,
+    comment: String,
+    key_blob: Vec<u8>,
+    private_key: PrivateKey,
+    constraints: AgentKeyConstraints,
+    added_at: Instant,
+    uses: u32,
+}
+
+impl AgentIdentity {
+    // 受密码保护的私钥需要提供密码
+    pub fn from_key_pair(key: &SshKeyPair, passphrase: Option<&str>) -> AppResult<Self> {
+        let private_key = SshKeyService::unlock_private_key(&key.private_key, passphrase)?;
+        let key_blob = private_key
+            .public_key()
+            .to_bytes()
+            .map_err(|e| AppError::KeyGenerationError(format!("公钥编码失败: {}", e)))?;
+        let comment = if key.comment.is_empty() {
+            key.name.clone()
+        } else {
+            key.comment.clone()
+        };
+
+        Ok(Self {
+            key_id: key.id.clone(),
+            key_name: key.name.clone(),
+            fingerprint: key.fingerprint.clone(),
+            comment,
+            key_blob,
+            private_key,
+            constraints: AgentKeyConstraints::default(),
+            added_at: Instant::now(),
+            uses: 0,
+        })
+    }
+
+    pub fn with_constraints(mut self, constraints: AgentKeyConstraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn key_name(&self) -> &str {
+        &self.key_name
+    }
+
+    // 将私钥交给其他 ssh-agent 的添加请求，私钥部分与 OpenSSH 私钥文件中的编码相同；
+    // 标准协议没有次数限制
+    pub fn add_identity_request(&self) -> AppResult<Vec<u8>> {
+        let constraints = &self.constraints;
+        if constraints.max_uses.is_some() {
+            return Err(AppError::AgentError(
+                "系统 ssh-agent 不支持限制签名次数".to_string(),
+            ));
+        }
+        let constrained = constraints.confirm || constraints.lifetime_secs.is_some();
+
+        let mut request = vec![if constrained {
+            SSH_AGENTC_ADD_ID_CONSTRAINED
+        } else {
+            SSH_AGENTC_ADD_IDENTITY
+        }];
+        self.private_key
+            .key_data()
+            .encode(&mut request)
+            .map_err(|e| AppError::AgentError(format!("私钥编码失败: {}", e)))?;
+        put_string(&mut request, self.comment.as_bytes());
+        if let Some(secs) = constraints.lifetime_secs {
+            let secs =
+                u32::try_from(secs).map_err(|_| AppError::AgentError("有效期过长".to_string()))?;
+            request.push(SSH_AGENT_CONSTRAIN_LIFETIME);
+            put_u32(&mut request, secs);
+        }
+        if constraints.confirm {
+            request.push(SSH_AGENT_CONSTRAIN_CONFIRM);
+        }
+        Ok(request)
+    }
+
+    // 超过有效期或签名次数用完后不再提供
+    fn is_usable(&self, now: Instant) -> bool {
+        let expired = self.constraints.lifetime_secs.is_some_and(|secs| {
+            now.saturating_duration_since(self.added_at) >= Duration::from_secs(secs)
+        });
+        let exhausted = self
+            .constraints
+            .max_uses
+            .is_some_and(|max_uses| self.uses >= max_uses);
+        !expired && !exhausted
+    }
+
+    fn confirm_request(
+        &self,
+        request_id: u64,
+        peer: Option<&AgentPeerInfo>,
+    ) -> AgentConfirmRequest {
+        AgentConfirmRequest {
+            request_id,
+            key_id: self.key_id.clone(),
+            key_name: self.key_name.clone(),
+            fingerprint: self.fingerprint.clone(),
+            peer: peer.cloned(),
+        }
+    }
+
+    // 返回编码后的签名（算法名与签名数据）
+    fn sign(&self, data: &[u8], flags: u32) -> AppResult<Vec<u8>> {
+        let signature = match self.private_key.key_data() {
+            KeypairData::Rsa(keypair) => {
+                let key = rsa_private_key(keypair)?;
+                // 未指定 SHA-2 时客户端要求 ssh-rsa（SHA-1），OpenSSH 已默认禁用，这里不再支持
+                let (hash, signature) = if flags & SSH_AGENT_RSA_SHA2_512 != 0 {
+                    let signing_key = rsa::pkcs1v15::SigningKey::<Sha512>::new(key);
+                    (
+                        HashAlg::Sha512,
+                        signing_key.try_sign(data).map_err(sign_error)?,
+                    )
+                } else if flags & SSH_AGENT_RSA_SHA2_256 != 0 {
+                    let signing_key = rsa::pkcs1v15::SigningKey::<Sha256>::new(key);
+                    (
+                        HashAlg::Sha256,
+                        signing_key.try_sign(data).map_err(sign_error)?,
+                    )
+                } else {
+                    return Err(AppError::Unknown("不支持 ssh-rsa（SHA-1）签名".to_string()));
+                };
+                Signature::new(Algorithm::Rsa { hash: Some(hash) }, signature.to_vec())
+                    .map_err(sign_error)?
+            }
+            _ => sign_with(&self.private_key, data)?,
+        };
+        signature.encode_vec().map_err(sign_error)
+    }
+}
+
+fn sign_with<K: SigningKey>(key: &K, data: &[u8]) -> AppResult<Signature> {
+    key.try_sign(data).map_err(sign_error)
+}
+
+fn sign_error(e: impl std::fmt::Display) -> AppError {
+    AppError::Unknown(format!("签名失败: {}", e))
+}
+
+fn identities_answer(identities: &[AgentIdentity]) -> Vec<u8> {
+    let mut response = vec![SSH_AGENT_IDENTITIES_ANSWER];
+    put_u32(&mut response, identities.len() as u32);
+    for identity in identities {
+        put_string(&mut response, &identity.key_blob);
+        put_string(&mut response, identity.comment.as_bytes());
+    }
+    response
+}
+
+// 协议中的基本类型：大端 uint32 与带长度前缀的 string
+pub(crate) struct WireReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> WireReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(head)
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub(crate) fn read_string(&mut self) -> Option<&'a [u8]> {
+        let len = self.read_u32()? as usize;
+        self.take(len)
+    }
+}
+
+pub(crate) fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+pub(crate) fn put_string(buf: &mut Vec<u8>, value: &[u8]) {
+    put_u32(buf, value.len() as u32);
+    buf.extend_from_slice(value);
+}
+
+// 等待用户答复的签名确认
+#[derive(Default)]
+pub struct PendingConfirmations {
+    next_id: AtomicU64,
+    waiting: Mutex<HashMap<u64, mpsc::Sender<bool>>>,
+}
+
+impl PendingConfirmations {
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    // 通知失败或超时视为拒绝
+    fn wait(&self, request_id: u64, notify: impl FnOnce() -> bool) -> bool {
+        let (sender, receiver) = mpsc::channel();
+        self.lock().insert(request_id, sender);
+        let approved = notify() && receiver.recv_timeout(CONFIRM_TIMEOUT).unwrap_or(false);
+        self.lock().remove(&request_id);
+        approved
+    }
+
+    // 返回请求是否仍在等待
+    fn respond(&self, request_id: u64, approved: bool) -> bool {
+        match self.lock().remove(&request_id) {
+            Some(sender) => sender.send(approved).is_ok(),
+            None => false,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<u64, mpsc::Sender<bool>>> {
+        self.waiting.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+type SignLog = Arc<Mutex<VecDeque<AgentSignRecord>>>;
+
+struct AgentShared {
+    identities: Mutex<Vec<AgentIdentity>>,
+    unlock: UnlockToken,
+    shutdown: AtomicBool,
+    notifier: ConfirmNotifier,
+    confirmations: Arc<PendingConfirmations>,
+    log: SignLog,
+}
+
+impl AgentShared {
+    // 停止或存储锁定后不再提供密钥
+    fn is_serving(&self) -> bool {
+        !self.shutdown.load(Ordering::SeqCst) && self.unlock.is_valid()
+    }
+
+    // 先移除已过期或次数用完的密钥
+    fn usable_identities(&self) -> MutexGuard<'_, Vec<AgentIdentity>> {
+        let mut identities = self
+            .identities
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        identities.retain(|identity| identity.is_usable(now));
+        identities
+    }
+
+    // 处理一条请求（不含长度前缀），返回响应消息；只支持列出密钥与签名，
+    // 添加、删除密钥与锁定代理等请求由应用管理，一律返回失败
+    fn handle_request(&self, request: &[u8], peer: Option<&AgentPeerInfo>) -> Vec<u8> {
+        let mut reader = WireReader::new(request);
+        let response = match reader.read_u8() {
+            _ if !self.is_serving() => None,
+            Some(SSH_AGENTC_REQUEST_IDENTITIES) => {
+                Some(identities_answer(&self.usable_identities()))
+            }
+            Some(SSH_AGENTC_SIGN_REQUEST) => self.sign_response(&mut reader, peer),
+            _ => None,
+        };
+        response.unwrap_or_else(|| vec![SSH_AGENT_FAILURE])
+    }
+
+    fn sign_response(
+        &self,
+        reader: &mut WireReader,
+        peer: Option<&AgentPeerInfo>,
+    ) -> Option<Vec<u8>> {
+        let key_blob = reader.read_string()?;
+        let data = reader.read_string()?;
+        let flags = reader.read_u32()?;
+
+        // 等待确认期间不持有密钥列表的锁
+        let confirm_request = {
+            let identities = self.usable_identities();
+            let identity = identities.iter().find(|i| i.key_blob == key_blob)?;
+            identity
+                .constraints
+                .confirm
+                .then(|| identity.confirm_request(self.confirmations.next_id(), peer))
+        };
+        if let Some(request) = confirm_request {
+            let approved = self
+                .confirmations
+                .wait(request.request_id, || (self.notifier)(&request));
+            if !approved {
+                self.record(AgentSignRecord {
+                    key_id: request.key_id,
+                    key_name: request.key_name,
+                    fingerprint: request.fingerprint,
+                    signed_at: chrono::Utc::now(),
+                    peer: request.peer,
+                    outcome: AgentSignOutcome::Denied,
+                });
+                return None;
+            }
+            // 确认期间存储可能已锁定
+            if !self.is_serving() {
+                return None;
+            }
+        }
+
+        // 确认期间密钥可能已过期或被其他连接用完次数
+        let mut identities = self.usable_identities();
+        let identity = identities.iter_mut().find(|i| i.key_blob == key_blob)?;
+        let signature = identity.sign(data, flags);
+        if signature.is_ok() {
+            identity.uses += 1;
+        }
+        self.record(AgentSignRecord {
+            key_id: identity.key_id.clone(),
+            key_name: identity.key_name.clone(),
+            fingerprint: identity.fingerprint.clone(),
+            signed_at: chrono::Utc::now(),
+            peer: peer.cloned(),
+            outcome: if signature.is_ok() {
+                AgentSignOutcome::Signed
+            } else {
+                AgentSignOutcome::Failed
+            },
+        });
+
+        let mut response = vec![SSH_AGENT_SIGN_RESPONSE];
+        put_string(&mut response, &signature.ok()?);
+        Some(response)
+    }
+
+    fn record(&self, record: AgentSignRecord) {
+        let mut log = self.log.lock().unwrap_or_else(PoisonError::into_inner);
+        if log.len() >= MAX_SIGN_LOG {
+            log.pop_front();
+        }
+        log.push_back(record);
+    }
+}
+
+struct RunningAgent {
+    socket_path: PathBuf,
+    shared: Arc<AgentShared>,
+    thread: JoinHandle<()>,
+}
+
+// 内置 ssh-agent：在 Unix 域 socket 上提供选中的密钥，SSH_AUTH_SOCK 指向该 socket 即可使用；
+// 存储锁定（UnlockToken 失效）后自动停止并删除 socket。签名记录在代理重启后保留
+#[derive(Default)]
+pub struct AgentService {
+    running: Option<RunningAgent>,
+    confirmations: Arc<PendingConfirmations>,
+    log: SignLog,
+}
+
+impl AgentService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 在 socket_path 上提供给定密钥；已在运行时先停止
+    #[cfg(unix)]
+    pub fn start(
+        &mut self,
+        socket_path: PathBuf,
+        identities: Vec<AgentIdentity>,
+        unlock: UnlockToken,
+        notifier: ConfirmNotifier,
+    ) -> AppResult<()> {
+        self.stop();
+
+        let listener = bind(&socket_path)?;
+        let shared = Arc::new(AgentShared {
+            identities: Mutex::new(identities),
+            unlock,
+            shutdown: AtomicBool::new(false),
+            notifier,
+            confirmations: Arc::clone(&self.confirmations),
+            log: Arc::clone(&self.log),
+        });
+        let thread = {
+            let shared = Arc::clone(&shared);
+            let socket_path = socket_path.clone();
+            std::thread::spawn(move || serve(listener, &socket_path, &shared))
+        };
+
+        self.running = Some(RunningAgent {
+            socket_path,
+            shared,
+            thread,
+        });
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn start(
+        &mut self,
+        _socket_path: PathBuf,
+        _identities: Vec<AgentIdentity>,
+        _unlock: UnlockToken,
+        _notifier: ConfirmNotifier,
+    ) -> AppResult<()> {
+        Err(AppError::ConfigError(
+            "当前平台不支持内置 ssh-agent".to_string(),
+        ))
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(agent) = self.running.take() {
+            agent.shared.shutdown.store(true, Ordering::SeqCst);
+            let _ = agent.thread.join();
+        }
+    }
+
+    pub fn status(&self) -> AgentStatus {
+        match &self.running {
+            Some(agent) if agent.shared.is_serving() => AgentStatus {
+                running: true,
+                socket_path: Some(agent.socket_path.clone()),
+                key_ids: agent
+                    .shared
+                    .usable_identities()
+                    .iter()
+                    .map(|identity| identity.key_id.clone())
+                    .collect(),
+            },
+            _ => AgentStatus::default(),
+        }
+    }
+
+    // 用户对签名确认的答复，请求已超时或不存在时返回 false
+    pub fn respond_confirmation(&self, request_id: u64, approved: bool) -> bool {
+        self.confirmations.respond(request_id, approved)
+    }
+
+    // 按时间顺序返回签名记录
+    pub fn sign_log(&self) -> Vec<AgentSignRecord> {
+        let log = self.log.lock().unwrap_or_else(PoisonError::into_inner);
+        log.iter().cloned().collect()
+    }
+}
+
+impl Drop for AgentService {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// 代理 socket 的默认路径：$XDG_RUNTIME_DIR/ssh-key-manager/agent.sock，未设置时使用临时目录下按 uid 区分的目录。
+// 不放在数据目录中：便携模式下数据目录可能位于不支持权限设置的 FAT 分区
+pub fn default_socket_path() -> PathBuf {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute());
+    let dir = match runtime_dir {
+        Some(dir) => dir.join(AGENT_SOCKET_DIR),
+        #[cfg(unix)]
+        None => std::env::temp_dir().join(format!("{}-{}", AGENT_SOCKET_DIR, current_uid())),
+        #[cfg(not(unix))]
+        None => std::env::temp_dir().join(AGENT_SOCKET_DIR),
+    };
+    dir.join(AGENT_SOCKET_FILE)
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: geteuid 没有前置条件，总是成功
+    unsafe { libc::geteuid() }
+}
+
+// socket 所在目录只允许当前用户访问：不存在时以 0700 创建，已存在时必须属于当前用户，并去掉组与其他用户的权限。
+// 这样 socket 在 bind 之后、设置权限之前也无法被其他用户连接
+#[cfg(unix)]
+fn ensure_private_dir(dir: &Path) -> AppResult<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    let metadata = std::fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != current_uid() {
+        return Err(AppError::ConfigError(format!(
+            "代理 socket 目录不属于当前用户: {}",
+            dir.display()
+        )));
+    }
+    if metadata.mode() & 0o077 != 0 {
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn bind(socket_path: &Path) -> AppResult<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let dir = socket_path
+        .parent()
+        .ok_or_else(|| AppError::ConfigError("代理 socket 路径无效".to_string()))?;
+    ensure_private_dir(dir)?;
+    // 上次未正常退出时遗留的 socket 文件；仍有代理在监听时不抢占
+    if socket_path.exists() {
+        if UnixStream::connect(socket_path).is_ok() {
+            return Err(AppError::ConfigError(format!(
+                "代理 socket 已被占用: {}",
+                socket_path.display()
+            )));
+        }
+        std::fs::remove_file(socket_path)?;
+    }
+
+    let listener = UnixListener::bind(socket_path)?;
+    // 目录已限制访问，socket 本身也只允许当前用户连接
+    std::fs::set_permissions(socket_path, std::fs::Permissions::from_mode(0o600))?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+#[cfg(unix)]
+fn serve(
+    listener: std::os::unix::net::UnixListener,
+    socket_path: &Path,
+    shared: &Arc<AgentShared>,
+) {
+    while shared.is_serving() {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let shared = Arc::clone(shared);
+                std::thread::spawn(move || handle_connection(stream, &shared));
+            }
+            Err(_) => std::thread::sleep(POLL_INTERVAL),
+        }
+    }
+
+    // 停止或锁定后立即释放私钥
+    shared
+        .identities
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clear();
+    let _ = std::fs::remove_file(socket_path);
+}
+
+#[cfg(unix)]
+fn handle_connection(mut stream: std::os::unix::net::UnixStream, shared: &AgentShared) {
+    use std::io::{Read, Write};
+
+    let _ = stream.set_nonblocking(false);
+    let peer = peer_info(&stream);
+    // 与 OpenSSH 一致，只接受当前用户的连接；无法获取对方 uid 时同样拒绝
+    if peer.as_ref().and_then(|peer| peer.uid) != Some(current_uid()) {
+        return;
+    }
+    loop {
+        let mut len = [0u8; 4];
+        if stream.read_exact(&mut len).is_err() {
+            return;
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 || len > MAX_MESSAGE_LEN {
+            return;
+        }
+        let mut request = vec![0u8; len];
+        if stream.read_exact(&mut request).is_err() {
+            return;
+        }
+
+        let response = shared.handle_request(&request, peer.as_ref());
+        let mut message = Vec::with_capacity(response.len() + 4);
+        put_string(&mut message, &response);
+        if stream.write_all(&message).is_err() {
+            return;
+        }
+    }
+}
+
+// 连接方的进程信息：Linux 通过 SO_PEERCRED 获取 pid 与 uid，并从 /proc 读取进程名与可执行文件
+#[cfg(unix)]
+fn peer_info(stream: &std::os::unix::net::UnixStream) -> Option<AgentPeerInfo> {
+    let (pid, uid) = peer_credentials(stream)?;
+    let proc_dir = pid.map(|pid| PathBuf::from(format!("/proc/{}", pid)));
+    Some(AgentPeerInfo {
+        pid,
+        uid: Some(uid),
+        process_name: proc_dir
+            .as_ref()
+            .and_then(|dir| std::fs::read_to_string(dir.join("comm")).ok())
+            .map(|name| name.trim_end().to_string()),
+        executable: proc_dir.and_then(|dir| std::fs::read_link(dir.join("exe")).ok()),
+    })
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_credentials(stream: &std::os::unix::net::UnixStream) -> Option<(Option<u32>, u32)> {
+    use std::os::fd::AsRawFd;
+
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: cred 与 len 指向有效的局部变量，长度与 SO_PEERCRED 要求的结构一致
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    (ret == 0).then_some((Some(cred.pid as u32), cred.uid))
+}
+
+// macOS 通过 getpeereid 获取 uid，LOCAL_PEERPID 获取 pid
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn peer_credentials(stream: &std::os::unix::net::UnixStream) -> Option<(Option<u32>, u32)> {
+    use std::os::fd::AsRawFd;
+
+    let fd = stream.as_raw_fd();
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: uid 与 gid 指向有效的局部变量
+    if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } != 0 {
+        return None;
+    }
+
+    let mut pid: libc::pid_t = 0;
+    let mut len = std::mem::size_of::<libc::pid_t>() as libc::socklen_t;
+    // SAFETY: pid 与 len 指向有效的局部变量，长度与 LOCAL_PEERPID 要求一致
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_LOCAL,
+            libc::LOCAL_PEERPID,
+            &mut pid as *mut libc::pid_t as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    Some(((ret == 0).then_some(pid as u32), uid))
+}
+
+// BSD 只能通过 getpeereid 获取 uid
+#[cfg(any(
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+fn peer_credentials(stream: &std::os::unix::net::UnixStream) -> Option<(Option<u32>, u32)> {
+    use std::os::fd::AsRawFd;
+
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: uid 与 gid 指向有效的局部变量
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return None;
+    }
+    Some((None, uid))
+}
+
+// 其他平台无法获取连接方身份，所有连接都会被拒绝
+#[cfg(all(
+    unix,
+    not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ))
+))]
+fn peer_credentials(_stream: &std::os::unix::net::UnixStream) -> Option<(Option<u32>, u32)> {
+    None
+}
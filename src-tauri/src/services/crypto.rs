@@ -1,47 +1,135 @@
 use crate::error::{AppError, AppResult};
+use crate::types::{KdfParams, KeySlot, KeySlotKind, VaultHeader, STORAGE_VERSION};
 use crate::utils::constant_time_eq;
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, KeyInit, Payload},
     Aes256Gcm, Nonce,
 };
 use pbkdf2::pbkdf2_hmac;
 use rand::rngs::OsRng;
 use rand::RngCore;
 use sha2::Digest;
+use std::sync::{Arc, Weak};
 use zeroize::{ZeroizeOnDrop, Zeroizing};
 
 #[derive(ZeroizeOnDrop)]
 pub struct CryptoService {
-    master_key: Option<Zeroizing<[u8; 32]>>,
-    master_key_hash: Option<String>,
-    salt: Option<[u8; 32]>,
+    // 数据加密密钥（1.x 存储中即为主密码派生的密钥）
+    data_key: Option<Zeroizing<[u8; 32]>>,
+    // 密钥文件内容的 SHA-256，与主密码一起用于密码槽
+    keyfile_hash: Option<Zeroizing<[u8; 32]>>,
+    #[zeroize(skip)]
+    header: Option<VaultHeader>,
+    // 每次锁定时替换，之前发出的 UnlockToken 随之失效
+    #[zeroize(skip)]
+    unlock_session: Arc<()>,
 }
 
+// 解锁状态的凭证：存储锁定或 CryptoService 实例被替换后失效，供后台服务（如 ssh-agent）判断是否继续工作
+#[derive(Clone)]
+pub struct UnlockToken(Weak<()>);
+
+impl UnlockToken {
+    pub fn is_valid(&self) -> bool {
+        self.0.strong_count() > 0
+    }
+}
+
+// 拒绝导入文件或存储文件中不合理的 KDF 参数，避免过度消耗内存与时间
+const MAX_ARGON2_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 10_000_000;
+
+// 恢复密钥：160 位随机数，使用 Crockford Base32 编码为 8 组、每组 4 个字符
+const RECOVERY_KEY_BYTES: usize = 20;
+const RECOVERY_KEY_GROUP: usize = 4;
+const RECOVERY_KEY_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
 // 为CryptoService添加公共方法来访问salt
 impl CryptoService {
     pub fn new() -> Self {
         Self {
-            master_key: None,
-            master_key_hash: None,
-            salt: None,
+            data_key: None,
+            keyfile_hash: None,
+            header: None,
+            unlock_session: Arc::new(()),
         }
     }
 
-    // 使用PBKDF2派生密钥
-    pub fn derive_key(password: &str, salt: &[u8]) -> [u8; 32] {
+    // 使用默认KDF（Argon2id）派生密钥
+    pub fn derive_key(password: &str, salt: &[u8]) -> AppResult<[u8; 32]> {
+        Self::derive_key_with_params(password, salt, &KdfParams::default())
+    }
+
+    // 按指定的KDF算法与参数派生密钥
+    pub fn derive_key_with_params(
+        password: &str,
+        salt: &[u8],
+        params: &KdfParams,
+    ) -> AppResult<[u8; 32]> {
+        Self::derive_key_from_secret(password.as_bytes(), salt, params)
+    }
+
+    fn derive_key_from_secret(
+        secret: &[u8],
+        salt: &[u8],
+        params: &KdfParams,
+    ) -> AppResult<[u8; 32]> {
         let mut key = [0u8; 32];
-        pbkdf2_hmac::<sha2::Sha256>(password.as_bytes(), salt, 100_000, &mut key);
-        key
+        match *params {
+            KdfParams::Pbkdf2HmacSha256 { iterations } => {
+                if iterations == 0 || iterations > MAX_KDF_ITERATIONS {
+                    return Err(AppError::ConfigError("PBKDF2 迭代次数无效".to_string()));
+                }
+                pbkdf2_hmac::<sha2::Sha256>(secret, salt, iterations, &mut key);
+            }
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                if memory_kib > MAX_ARGON2_MEMORY_KIB || iterations > MAX_KDF_ITERATIONS {
+                    return Err(AppError::ConfigError("Argon2 参数超出允许范围".to_string()));
+                }
+                let argon2_params =
+                    argon2::Params::new(memory_kib, iterations, parallelism, Some(key.len()))
+                        .map_err(|e| AppError::ConfigError(format!("Argon2 参数无效: {}", e)))?;
+                argon2::Argon2::new(
+                    argon2::Algorithm::Argon2id,
+                    argon2::Version::V0x13,
+                    argon2_params,
+                )
+                .hash_password_into(secret, salt, &mut key)
+                .map_err(|e| AppError::Unknown(format!("密钥派生失败: {}", e)))?;
+            }
+        }
+        Ok(key)
     }
 
     // 使用提供的密码与给定盐值进行一次性加密（不依赖内部认证状态）
     pub fn encrypt_with_password(
         password: &str,
         salt: &[u8],
+        params: &KdfParams,
         data: &[u8],
     ) -> AppResult<EncryptedData> {
-        let key_bytes = Self::derive_key(password, salt);
-        let cipher = Aes256Gcm::new((&key_bytes).into());
+        let key_bytes = Zeroizing::new(Self::derive_key_with_params(password, salt, params)?);
+        Self::encrypt_with_key(&key_bytes, data)
+    }
+
+    // 使用提供的密码与给定盐值进行一次性解密（不依赖内部认证状态）
+    pub fn decrypt_with_password(
+        password: &str,
+        salt: &[u8],
+        params: &KdfParams,
+        encrypted: &EncryptedData,
+    ) -> AppResult<Vec<u8>> {
+        let key_bytes = Zeroizing::new(Self::derive_key_with_params(password, salt, params)?);
+        Self::decrypt_with_key(&key_bytes, encrypted)
+    }
+
+    // 使用已派生的密钥加密（批量加密时避免重复派生）
+    pub fn encrypt_with_key(key: &[u8; 32], data: &[u8]) -> AppResult<EncryptedData> {
+        let cipher = Aes256Gcm::new(key.into());
 
         let mut nonce_bytes = [0u8; 12];
         let mut rng = OsRng;
@@ -55,21 +143,20 @@ impl CryptoService {
         Ok(EncryptedData {
             nonce: nonce_bytes.to_vec(),
             ciphertext,
+            associated_data: None,
         })
     }
 
-    // 使用提供的密码与给定盐值进行一次性解密（不依赖内部认证状态）
-    pub fn decrypt_with_password(
-        password: &str,
-        salt: &[u8],
-        encrypted: &EncryptedData,
-    ) -> AppResult<Vec<u8>> {
-        let key_bytes = Self::derive_key(password, salt);
-        let cipher = Aes256Gcm::new((&key_bytes).into());
+    // 使用已派生的密钥解密
+    pub fn decrypt_with_key(key: &[u8; 32], encrypted: &EncryptedData) -> AppResult<Vec<u8>> {
+        if encrypted.nonce.len() != 12 {
+            return Err(AppError::Unknown("解密失败".to_string()));
+        }
+        let cipher = Aes256Gcm::new(key.into());
         let nonce = Nonce::from_slice(&encrypted.nonce);
 
         let plaintext = cipher
-            .decrypt(nonce, encrypted.ciphertext.as_ref())
+            .decrypt(nonce, encrypted.payload())
             .map_err(|_| AppError::Unknown("解密失败".to_string()))?;
         Ok(plaintext)
     }
@@ -82,91 +169,295 @@ impl CryptoService {
         format!("{:x}", hasher.finalize())
     }
 
-    // 设置主密码
+    // 设置主密码：生成新的数据密钥，并用主密码派生的密钥包装为密码槽
     pub fn set_master_key(&mut self, password: &str) -> AppResult<()> {
-        let salt = Self::generate_salt();
-        let derived_key = Self::derive_key(password, &salt);
+        let mut data_key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(data_key.as_mut());
 
-        // 使用Zeroizing包装器确保密钥在作用域结束时自动清零
-        self.master_key = Some(Zeroizing::new(derived_key));
-        self.salt = Some(salt);
+        let slot = Self::wrap_password_slot(password, self.keyfile_hash(), &data_key)?;
+        self.data_key = Some(data_key);
+        self.header = Some(VaultHeader::Envelope(vec![slot]));
+        Ok(())
+    }
 
-        // 存储派生密钥的哈希用于后续验证
-        let key_hash = Self::hash_key(&derived_key, &salt);
-        self.master_key_hash = Some(key_hash);
+    // 设置存储文件头部（解锁前调用）
+    pub fn set_header(&mut self, header: VaultHeader) {
+        self.header = Some(header);
+    }
 
-        Ok(())
+    // 当前头部中的密钥槽（1.x 存储没有密钥槽）
+    pub fn key_slots(&self) -> &[KeySlot] {
+        match &self.header {
+            Some(VaultHeader::Envelope(slots)) => slots,
+            _ => &[],
+        }
     }
 
-    // 设置主密码哈希
-    pub fn set_master_key_hash(&mut self, hash: String) {
-        self.master_key_hash = Some(hash);
+    // 是否为主密码直接加密数据的 1.x 存储
+    pub fn is_legacy(&self) -> bool {
+        matches!(self.header, Some(VaultHeader::Legacy(_)))
     }
 
-    // 设置盐值
-    pub fn set_salt(&mut self, salt: [u8; 32]) {
-        self.salt = Some(salt);
+    // 1.x 存储或 KDF 参数弱于默认参数的密码槽，需要在解锁后升级
+    pub fn needs_upgrade(&self) -> bool {
+        match &self.header {
+            Some(VaultHeader::Legacy(_)) => true,
+            Some(VaultHeader::Envelope(slots)) => slots
+                .iter()
+                .any(|slot| slot.kind == KeySlotKind::Password && slot.kdf != KdfParams::default()),
+            None => false,
+        }
     }
 
-    // 直接设置派生的主密钥（用于认证后设置）
-    pub fn set_derived_master_key(&mut self, derived_key: [u8; 32]) {
-        self.master_key = Some(Zeroizing::new(derived_key));
+    // 使用主密码（及已设置的密钥文件）解锁数据密钥
+    pub fn unlock(&mut self, password: &str) -> bool {
+        self.try_unlock(password).unwrap_or(false)
     }
 
-    // 清除主密钥
-    pub fn clear_master_key(&mut self) {
-        self.master_key = None;
-        self.master_key_hash = None;
-        self.salt = None;
+    // 同 unlock，但缺少密钥文件时返回错误；主密码或密钥文件错误均视为解锁失败
+    pub fn try_unlock(&mut self, password: &str) -> AppResult<bool> {
+        match self.open_with_password(password)? {
+            Some(data_key) => {
+                self.data_key = Some(data_key);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // 设置解锁与包装密码槽时使用的密钥文件哈希
+    pub fn set_keyfile(&mut self, keyfile_hash: Option<[u8; 32]>) {
+        self.keyfile_hash = keyfile_hash.map(Zeroizing::new);
+    }
+
+    // 当前会话使用的密钥文件哈希
+    pub fn keyfile_hash(&self) -> Option<&[u8; 32]> {
+        self.keyfile_hash.as_deref()
+    }
+
+    // 计算密钥文件哈希（文件内容的 SHA-256）
+    pub fn hash_keyfile(contents: &[u8]) -> [u8; 32] {
+        sha2::Sha256::digest(contents).into()
+    }
+
+    // 密码槽是否要求密钥文件
+    pub fn requires_keyfile(&self) -> bool {
+        self.key_slots()
+            .iter()
+            .any(|slot| slot.kind == KeySlotKind::Password && slot.requires_keyfile)
+    }
+
+    // 使用恢复密钥解锁数据密钥
+    pub fn unlock_with_recovery_key(&mut self, recovery_key: &str) -> bool {
+        let Some(normalized) = Self::normalize_recovery_key(recovery_key) else {
+            return false;
+        };
+        match self.open_slot(KeySlotKind::RecoveryKey, normalized.as_bytes()) {
+            Some(data_key) => {
+                self.data_key = Some(data_key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 是否存在恢复密钥槽
+    pub fn has_recovery_key(&self) -> bool {
+        self.key_slots()
+            .iter()
+            .any(|slot| slot.kind == KeySlotKind::RecoveryKey)
+    }
+
+    // 生成可打印的恢复密钥，形如 XXXX-XXXX-XXXX-XXXX-XXXX-XXXX-XXXX-XXXX
+    pub fn generate_recovery_key() -> Zeroizing<String> {
+        let mut bytes = Zeroizing::new([0u8; RECOVERY_KEY_BYTES]);
+        OsRng.fill_bytes(bytes.as_mut());
+
+        let mut code = Zeroizing::new(String::new());
+        let mut buffer = 0u16;
+        let mut bits = 0;
+        let mut chars = 0;
+        for &byte in bytes.iter() {
+            buffer = (buffer << 8) | byte as u16;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                if chars > 0 && chars % RECOVERY_KEY_GROUP == 0 {
+                    code.push('-');
+                }
+                code.push(RECOVERY_KEY_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+                chars += 1;
+            }
+        }
+        code
+    }
+
+    // 规范化用户输入的恢复密钥：忽略大小写、分隔符与空白，并纠正易混淆的字符
+    pub fn normalize_recovery_key(input: &str) -> Option<Zeroizing<String>> {
+        let mut normalized = Zeroizing::new(String::new());
+        for c in input.chars() {
+            let c = match c.to_ascii_uppercase() {
+                '-' | ' ' | '\t' => continue,
+                'O' => '0',
+                'I' | 'L' => '1',
+                c => c,
+            };
+            if !c.is_ascii() || !RECOVERY_KEY_ALPHABET.contains(&(c as u8)) {
+                return None;
+            }
+            normalized.push(c);
+        }
+
+        (normalized.len() == RECOVERY_KEY_BYTES * 8 / 5).then_some(normalized)
+    }
+
+    // 用新主密码重新包装当前数据密钥，返回替换密码槽后的密钥槽列表；
+    // 其余类型的槽保持不变，数据无需重新加密
+    pub fn rewrap_password_slots(&self, password: &str) -> AppResult<Vec<KeySlot>> {
+        self.rewrap_password_slots_with_keyfile(password, self.keyfile_hash())
+    }
+
+    // 同 rewrap_password_slots，但使用指定的密钥文件（None 表示不再要求密钥文件）
+    pub fn rewrap_password_slots_with_keyfile(
+        &self,
+        password: &str,
+        keyfile_hash: Option<&[u8; 32]>,
+    ) -> AppResult<Vec<KeySlot>> {
+        self.replace_key_slot(KeySlotKind::Password, |data_key| {
+            Self::wrap_password_slot(password, keyfile_hash, data_key)
+        })
+    }
+
+    // 用恢复密钥包装当前数据密钥，返回替换（或新增）恢复密钥槽后的密钥槽列表
+    pub fn rewrap_recovery_key_slot(&self, recovery_key: &str) -> AppResult<Vec<KeySlot>> {
+        let normalized = Self::normalize_recovery_key(recovery_key)
+            .ok_or_else(|| AppError::ConfigError("恢复密钥格式无效".to_string()))?;
+        self.replace_key_slot(KeySlotKind::RecoveryKey, |data_key| {
+            Self::wrap_data_key(KeySlotKind::RecoveryKey, normalized.as_bytes(), data_key)
+        })
+    }
+
+    // 返回移除恢复密钥槽后的密钥槽列表
+    pub fn without_recovery_key_slot(&self) -> Vec<KeySlot> {
+        self.key_slots()
+            .iter()
+            .filter(|slot| slot.kind != KeySlotKind::RecoveryKey)
+            .cloned()
+            .collect()
+    }
+
+    fn replace_key_slot(
+        &self,
+        kind: KeySlotKind,
+        wrap: impl FnOnce(&[u8; 32]) -> AppResult<KeySlot>,
+    ) -> AppResult<Vec<KeySlot>> {
+        let data_key = self
+            .data_key
+            .as_ref()
+            .ok_or_else(|| AppError::Unknown("主密钥未设置".to_string()))?;
+        if self.is_legacy() {
+            return Err(AppError::ConfigError("旧版本存储需要重新加密".to_string()));
+        }
+
+        let mut slots: Vec<KeySlot> = self
+            .key_slots()
+            .iter()
+            .filter(|slot| slot.kind != kind)
+            .cloned()
+            .collect();
+        slots.push(wrap(data_key)?);
+        Ok(slots)
+    }
+
+    // 换用另一实例的数据密钥、密钥槽与密钥文件（如重新加密后），会话保持不变，已发出的 UnlockToken 继续有效
+    pub fn replace_keys(&mut self, other: &mut CryptoService) {
+        self.data_key = other.data_key.take();
+        self.keyfile_hash = other.keyfile_hash.take();
+        self.header = other.header.take();
     }
 
-    // 获取主密码哈希
-    pub fn get_master_key_hash(&self) -> Option<String> {
-        self.master_key_hash.clone()
+    // 清除主密钥
+    pub fn clear_master_key(&mut self) {
+        self.data_key = None;
+        self.keyfile_hash = None;
+        self.header = None;
+        self.unlock_session = Arc::new(());
     }
 
-    // 获取盐值
-    pub fn get_salt(&self) -> Option<[u8; 32]> {
-        self.salt
+    // 未解锁时返回 None
+    pub fn unlock_token(&self) -> Option<UnlockToken> {
+        self.is_authenticated()
+            .then(|| UnlockToken(Arc::downgrade(&self.unlock_session)))
     }
 
     // 验证密码
     pub fn verify_password(&self, password: &str) -> bool {
-        if let (Some(stored_hash), Some(salt)) = (&self.master_key_hash, &self.salt) {
-            let derived_key = Self::derive_key(password, salt);
-            let derived_hash = Self::hash_key(&derived_key, salt);
-            // 使用常量时间比较防止时序攻击
-            constant_time_eq(stored_hash.as_bytes(), derived_hash.as_bytes())
-        } else {
-            false
-        }
+        self.check_password(password).unwrap_or(false)
+    }
+
+    // 同 verify_password，但缺少密钥文件或密钥文件不匹配时返回错误
+    pub fn check_password(&self, password: &str) -> AppResult<bool> {
+        Ok(self.open_with_password(password)?.is_some())
     }
 
     // 检查是否已认证
     pub fn is_authenticated(&self) -> bool {
-        self.master_key.is_some()
+        self.data_key.is_some()
     }
 
-    // AES-256-GCM加密
+    // AES-256-GCM加密，存储头部（版本与当前密钥槽）作为关联数据
     pub fn encrypt(&self, data: &[u8]) -> AppResult<EncryptedData> {
+        self.encrypt_for_slots(data, self.key_slots())
+    }
+
+    // 存储文件加密数据的关联数据：版本与密钥槽。auth_guard 在未解锁时也会更新，不参与认证
+    pub fn header_associated_data(version: &str, key_slots: &[KeySlot]) -> AppResult<Vec<u8>> {
+        let header = serde_json::json!({
+            "version": version,
+            "key_slots": key_slots,
+        });
+        let mut associated_data = b"sshmanager-storage-header\0".to_vec();
+        associated_data.extend(serde_json::to_vec(&header)?);
+        Ok(associated_data)
+    }
+
+    // 以指定的密钥槽构成的头部作为关联数据加密（用于写入新的密钥槽）；
+    // 1.x 存储没有密钥槽，不绑定头部
+    pub fn encrypt_for_slots(
+        &self,
+        data: &[u8],
+        key_slots: &[KeySlot],
+    ) -> AppResult<EncryptedData> {
         if !self.is_authenticated() {
             return Err(AppError::Unknown("主密钥未设置".to_string()));
         }
 
-        let key = self.master_key.as_ref().unwrap();
+        let key = self.data_key.as_ref().unwrap();
         let cipher = Aes256Gcm::new(key.as_ref().into());
         let mut nonce_bytes = [0u8; 12]; // 96位nonce
         let mut rng = OsRng;
         rng.fill_bytes(&mut nonce_bytes);
         let nonce = Nonce::from_slice(&nonce_bytes);
 
+        let associated_data = if key_slots.is_empty() {
+            None
+        } else {
+            Some(Self::header_associated_data(STORAGE_VERSION, key_slots)?)
+        };
         let ciphertext = cipher
-            .encrypt(nonce, data)
+            .encrypt(
+                nonce,
+                Payload {
+                    msg: data,
+                    aad: associated_data.as_deref().unwrap_or_default(),
+                },
+            )
             .map_err(|_| AppError::Unknown("加密失败".to_string()))?;
 
         Ok(EncryptedData {
             nonce: nonce_bytes.to_vec(),
             ciphertext,
+            associated_data,
         })
     }
 
@@ -176,12 +467,16 @@ impl CryptoService {
             return Err(AppError::Unknown("主密钥未设置".to_string()));
         }
 
-        let key = self.master_key.as_ref().unwrap();
+        if encrypted.nonce.len() != 12 {
+            return Err(AppError::Unknown("解密失败".to_string()));
+        }
+
+        let key = self.data_key.as_ref().unwrap();
         let cipher = Aes256Gcm::new(key.as_ref().into());
         let nonce = Nonce::from_slice(&encrypted.nonce);
 
         let plaintext = cipher
-            .decrypt(nonce, encrypted.ciphertext.as_ref())
+            .decrypt(nonce, encrypted.payload())
             .map_err(|_| AppError::Unknown("解密失败".to_string()))?;
 
         Ok(plaintext)
@@ -195,6 +490,126 @@ impl CryptoService {
         salt
     }
 
+    // 依次尝试各密码槽（1.x 存储则比对主密码哈希），成功时返回数据密钥
+    fn open_with_password(&self, password: &str) -> AppResult<Option<Zeroizing<[u8; 32]>>> {
+        let Some(header) = self.header.as_ref() else {
+            return Ok(None);
+        };
+
+        match header {
+            VaultHeader::Legacy(legacy) => {
+                let derived_key =
+                    match Self::derive_key_with_params(password, &legacy.salt, &legacy.kdf) {
+                        Ok(key) => Zeroizing::new(key),
+                        Err(_) => return Ok(None),
+                    };
+                let derived_hash = Self::hash_key(derived_key.as_ref(), &legacy.salt);
+                // 使用常量时间比较防止时序攻击
+                Ok(
+                    constant_time_eq(legacy.master_key_hash.as_bytes(), derived_hash.as_bytes())
+                        .then_some(derived_key),
+                )
+            }
+            VaultHeader::Envelope(slots) => {
+                let mut result = Ok(None);
+                for slot in slots
+                    .iter()
+                    .filter(|slot| slot.kind == KeySlotKind::Password)
+                {
+                    match self.open_password_slot(slot, password) {
+                        Ok(Some(data_key)) => return Ok(Some(data_key)),
+                        Ok(None) => {}
+                        Err(e) => result = Err(e),
+                    }
+                }
+                result
+            }
+        }
+    }
+
+    // 解包密码槽。密钥文件不单独校验（头部中的校验值可被离线用来猜测密钥文件），
+    // 主密码与密钥文件只能作为整体验证
+    fn open_password_slot(
+        &self,
+        slot: &KeySlot,
+        password: &str,
+    ) -> AppResult<Option<Zeroizing<[u8; 32]>>> {
+        match (slot.requires_keyfile, self.keyfile_hash()) {
+            (false, _) => Ok(Self::unwrap_data_key(slot, password.as_bytes())),
+            (true, None) => Err(AppError::KeyfileRequired),
+            (true, Some(keyfile_hash)) => {
+                let secret = Self::composite_key(password, keyfile_hash);
+                Ok(Self::unwrap_data_key(slot, secret.as_ref()))
+            }
+        }
+    }
+
+    // 生成密码槽；指定密钥文件时 KEK 由主密码与密钥文件共同派生
+    fn wrap_password_slot(
+        password: &str,
+        keyfile_hash: Option<&[u8; 32]>,
+        data_key: &[u8; 32],
+    ) -> AppResult<KeySlot> {
+        let Some(keyfile_hash) = keyfile_hash else {
+            return Self::wrap_data_key(KeySlotKind::Password, password.as_bytes(), data_key);
+        };
+
+        let secret = Self::composite_key(password, keyfile_hash);
+        let mut slot = Self::wrap_data_key(KeySlotKind::Password, secret.as_ref(), data_key)?;
+        slot.requires_keyfile = true;
+        Ok(slot)
+    }
+
+    // 组合密钥：SHA-256(SHA-256(主密码) || 密钥文件哈希)，与 KeePass 的做法一致
+    fn composite_key(password: &str, keyfile_hash: &[u8; 32]) -> Zeroizing<[u8; 32]> {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(sha2::Sha256::digest(password.as_bytes()));
+        hasher.update(keyfile_hash);
+        Zeroizing::new(hasher.finalize().into())
+    }
+
+    // 依次尝试指定类型的密钥槽
+    fn open_slot(&self, kind: KeySlotKind, secret: &[u8]) -> Option<Zeroizing<[u8; 32]>> {
+        self.key_slots()
+            .iter()
+            .filter(|slot| slot.kind == kind)
+            .find_map(|slot| Self::unwrap_data_key(slot, secret))
+    }
+
+    // 用凭据派生的 KEK 包装数据密钥，生成新的密钥槽
+    fn wrap_data_key(kind: KeySlotKind, secret: &[u8], data_key: &[u8; 32]) -> AppResult<KeySlot> {
+        let salt = Self::generate_salt();
+        let kdf = KdfParams::default();
+        let kek = Zeroizing::new(Self::derive_key_from_secret(secret, &salt, &kdf)?);
+        let wrapped = Self::encrypt_with_key(&kek, data_key)?;
+
+        Ok(KeySlot {
+            kind,
+            salt: salt.to_vec(),
+            kdf,
+            nonce: wrapped.nonce,
+            wrapped_key: wrapped.ciphertext,
+            requires_keyfile: false,
+        })
+    }
+
+    // 解包密钥槽；凭据错误时 AES-GCM 认证失败，返回 None
+    fn unwrap_data_key(slot: &KeySlot, secret: &[u8]) -> Option<Zeroizing<[u8; 32]>> {
+        let kek = Zeroizing::new(Self::derive_key_from_secret(secret, &slot.salt, &slot.kdf).ok()?);
+        let wrapped = EncryptedData {
+            nonce: slot.nonce.clone(),
+            ciphertext: slot.wrapped_key.clone(),
+            associated_data: None,
+        };
+        let plaintext = Zeroizing::new(Self::decrypt_with_key(&kek, &wrapped).ok()?);
+        if plaintext.len() != 32 {
+            return None;
+        }
+        let mut data_key = Zeroizing::new([0u8; 32]);
+        data_key.copy_from_slice(&plaintext);
+        Some(data_key)
+    }
+
     // 哈希密钥
     fn hash_key(key: &[u8], salt: &[u8]) -> String {
         let mut hasher = sha2::Sha256::new();
@@ -208,4 +623,16 @@ impl CryptoService {
 pub struct EncryptedData {
     pub nonce: Vec<u8>,
     pub ciphertext: Vec<u8>,
+    // 加密时绑定的关联数据（存储头部），不随密文序列化
+    #[serde(skip)]
+    pub associated_data: Option<Vec<u8>>,
+}
+
+impl EncryptedData {
+    fn payload(&self) -> Payload<'_, '_> {
+        Payload {
+            msg: &self.ciphertext,
+            aad: self.associated_data.as_deref().unwrap_or_default(),
+        }
+    }
 }
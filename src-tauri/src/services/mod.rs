@@ -1,7 +1,20 @@
+pub mod agent;
 pub mod crypto;
+pub mod key_format;
+pub mod rate_limit;
+pub mod session;
 pub mod ssh_config;
+pub mod ssh_config_document;
+pub mod ssh_config_parser;
 pub mod ssh_key;
+pub mod system_agent;
 
-pub use crypto::{CryptoService, EncryptedData};
+pub use agent::{AgentIdentity, AgentService};
+pub use crypto::{CryptoService, EncryptedData, UnlockToken};
+pub use key_format::{KeyFormatService, PrivateKeyFormat};
+pub use rate_limit::RateLimiter;
+pub use session::SessionService;
 pub use ssh_config::SshConfigService;
+pub use ssh_config_document::SshConfigDocument;
 pub use ssh_key::SshKeyService;
+pub use system_agent::SystemAgentClient;
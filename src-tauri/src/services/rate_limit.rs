@@ -0,0 +1,199 @@
+use crate::error::{AppError, AppResult};
+use crate::types::{AuthGuardState, FailedAttempt, LockoutAction, LockoutPolicy};
+use chrono::{DateTime, Duration, Utc};
+
+// 前几次失败不退避，之后每次失败等待时间翻倍，最长 15 分钟
+const FREE_ATTEMPTS: u32 = 3;
+const MAX_BACKOFF_SECS: i64 = 15 * 60;
+
+// 失败记录只保留最近的若干条
+const MAX_LOGGED_ATTEMPTS: usize = 100;
+
+// 策略阈值的允许范围，避免误设过小的值导致数据被清除
+pub const MIN_LOCKOUT_FAILURES: u32 = 3;
+pub const MAX_LOCKOUT_FAILURES: u32 = 100;
+
+// 一次失败尝试之后的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureOutcome {
+    Backoff,
+    LockedOut,
+    Wipe,
+}
+
+pub struct RateLimiter;
+
+impl RateLimiter {
+    // 检查当前是否允许尝试主密码
+    pub fn check(state: &AuthGuardState, now: DateTime<Utc>) -> AppResult<()> {
+        if state.locked_out {
+            return Err(AppError::LockedOut);
+        }
+        Self::check_backoff(state, now)
+    }
+
+    // 只检查退避时间（恢复密钥在密码解锁被禁用后仍然可用）
+    pub fn check_backoff(state: &AuthGuardState, now: DateTime<Utc>) -> AppResult<()> {
+        match state.retry_after {
+            Some(retry_after) if now < retry_after => {
+                let remaining = (retry_after - now).num_milliseconds();
+                Err(AppError::RateLimited(((remaining + 999) / 1000) as u64))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // 记录一次失败尝试，更新退避时间并按策略判断是否锁定或清除
+    pub fn record_failure(
+        state: &mut AuthGuardState,
+        source: &str,
+        now: DateTime<Utc>,
+    ) -> FailureOutcome {
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        state.retry_after = Self::backoff(state.consecutive_failures).map(|delay| now + delay);
+        state.failed_attempts.push(FailedAttempt {
+            at: now,
+            source: source.to_string(),
+        });
+        if state.failed_attempts.len() > MAX_LOGGED_ATTEMPTS {
+            let excess = state.failed_attempts.len() - MAX_LOGGED_ATTEMPTS;
+            state.failed_attempts.drain(..excess);
+        }
+
+        match &state.lockout_policy {
+            Some(policy) if state.consecutive_failures >= policy.max_failures => {
+                match policy.action {
+                    LockoutAction::Lock => {
+                        state.locked_out = true;
+                        FailureOutcome::LockedOut
+                    }
+                    LockoutAction::Wipe => FailureOutcome::Wipe,
+                }
+            }
+            _ => FailureOutcome::Backoff,
+        }
+    }
+
+    // 解锁成功后清零计数（保留失败记录供用户查看），返回状态是否有变化
+    pub fn record_success(state: &mut AuthGuardState) -> bool {
+        let changed =
+            state.consecutive_failures != 0 || state.retry_after.is_some() || state.locked_out;
+        state.consecutive_failures = 0;
+        state.retry_after = None;
+        state.locked_out = false;
+        changed
+    }
+
+    pub fn validate_policy(policy: &LockoutPolicy) -> AppResult<()> {
+        if !(MIN_LOCKOUT_FAILURES..=MAX_LOCKOUT_FAILURES).contains(&policy.max_failures) {
+            return Err(AppError::ConfigError(format!(
+                "失败次数阈值必须在 {} 到 {} 之间",
+                MIN_LOCKOUT_FAILURES, MAX_LOCKOUT_FAILURES
+            )));
+        }
+        Ok(())
+    }
+
+    // 第 n 次连续失败后的等待时间
+    fn backoff(failures: u32) -> Option<Duration> {
+        if failures < FREE_ATTEMPTS {
+            return None;
+        }
+        let exponent = (failures - FREE_ATTEMPTS).min(20);
+        let secs = (1i64 << exponent).min(MAX_BACKOFF_SECS);
+        Some(Duration::seconds(secs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exponential_backoff() {
+        let mut state = AuthGuardState::default();
+        let now = Utc::now();
+
+        for _ in 0..FREE_ATTEMPTS - 1 {
+            assert_eq!(
+                RateLimiter::record_failure(&mut state, "authenticate", now),
+                FailureOutcome::Backoff
+            );
+            assert!(RateLimiter::check(&state, now).is_ok());
+        }
+
+        RateLimiter::record_failure(&mut state, "authenticate", now);
+        assert!(matches!(
+            RateLimiter::check(&state, now),
+            Err(AppError::RateLimited(1))
+        ));
+        assert!(RateLimiter::check(&state, now + Duration::seconds(1)).is_ok());
+
+        RateLimiter::record_failure(&mut state, "authenticate", now);
+        assert!(matches!(
+            RateLimiter::check(&state, now),
+            Err(AppError::RateLimited(2))
+        ));
+
+        for _ in 0..30 {
+            RateLimiter::record_failure(&mut state, "authenticate", now);
+        }
+        assert!(matches!(
+            RateLimiter::check(&state, now),
+            Err(AppError::RateLimited(secs)) if secs == MAX_BACKOFF_SECS as u64
+        ));
+
+        assert!(RateLimiter::record_success(&mut state));
+        assert!(RateLimiter::check(&state, now).is_ok());
+        assert_eq!(state.failed_attempts.len(), FREE_ATTEMPTS as usize + 31);
+    }
+
+    #[test]
+    fn test_lockout_policy() {
+        let now = Utc::now();
+        let mut state = AuthGuardState {
+            lockout_policy: Some(LockoutPolicy {
+                max_failures: 3,
+                action: LockoutAction::Lock,
+            }),
+            ..Default::default()
+        };
+
+        RateLimiter::record_failure(&mut state, "authenticate", now);
+        RateLimiter::record_failure(&mut state, "authenticate", now);
+        assert_eq!(
+            RateLimiter::record_failure(&mut state, "authenticate", now),
+            FailureOutcome::LockedOut
+        );
+        let later = now + Duration::hours(1);
+        assert!(matches!(
+            RateLimiter::check(&state, later),
+            Err(AppError::LockedOut)
+        ));
+        assert!(RateLimiter::check_backoff(&state, later).is_ok());
+
+        state.lockout_policy.as_mut().unwrap().action = LockoutAction::Wipe;
+        state.locked_out = false;
+        assert_eq!(
+            RateLimiter::record_failure(&mut state, "authenticate", later),
+            FailureOutcome::Wipe
+        );
+
+        assert!(RateLimiter::validate_policy(&LockoutPolicy {
+            max_failures: 1,
+            action: LockoutAction::Wipe,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_failed_attempt_log_is_capped() {
+        let mut state = AuthGuardState::default();
+        let now = Utc::now();
+        for _ in 0..MAX_LOGGED_ATTEMPTS + 10 {
+            RateLimiter::record_failure(&mut state, "verify_master_password", now);
+        }
+        assert_eq!(state.failed_attempts.len(), MAX_LOGGED_ATTEMPTS);
+        assert_eq!(state.failed_attempts[0].source, "verify_master_password");
+    }
+}
@@ -0,0 +1,93 @@
+use crate::error::{AppError, AppResult};
+use std::time::{Duration, Instant};
+
+// 安全策略：空闲 10 分钟后必须锁定，只允许设置更短的时长
+pub const DEFAULT_IDLE_TIMEOUT_MINUTES: u32 = 10;
+pub const MAX_IDLE_TIMEOUT_MINUTES: u32 = 10;
+
+// 后台检查空闲状态的间隔
+pub const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+// 锁定时发送给前端的事件
+pub const VAULT_LOCKED_EVENT: &str = "vault-locked";
+
+// 会话空闲计时：记录最近一次活动，超过空闲时长后由后台线程锁定
+pub struct SessionService {
+    last_activity: Instant,
+    idle_timeout: Duration,
+}
+
+impl SessionService {
+    pub fn new() -> Self {
+        Self {
+            last_activity: Instant::now(),
+            idle_timeout: Duration::from_secs(DEFAULT_IDLE_TIMEOUT_MINUTES as u64 * 60),
+        }
+    }
+
+    // 记录一次用户活动
+    pub fn touch(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    pub fn idle_timeout_minutes(&self) -> u32 {
+        (self.idle_timeout.as_secs() / 60) as u32
+    }
+
+    pub fn set_idle_timeout_minutes(&mut self, minutes: u32) -> AppResult<()> {
+        if minutes == 0 || minutes > MAX_IDLE_TIMEOUT_MINUTES {
+            return Err(AppError::ConfigError(format!(
+                "自动锁定时间必须在 1 到 {} 分钟之间",
+                MAX_IDLE_TIMEOUT_MINUTES
+            )));
+        }
+        self.idle_timeout = Duration::from_secs(minutes as u64 * 60);
+        Ok(())
+    }
+
+    // 距最近一次活动是否已超过空闲时长
+    pub fn is_idle(&self) -> bool {
+        self.is_idle_at(Instant::now())
+    }
+
+    fn is_idle_at(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.last_activity) >= self.idle_timeout
+    }
+}
+
+impl Default for SessionService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_after_timeout() {
+        let mut session = SessionService::new();
+        assert_eq!(session.idle_timeout_minutes(), DEFAULT_IDLE_TIMEOUT_MINUTES);
+
+        let start = session.last_activity;
+        assert!(!session.is_idle_at(start + Duration::from_secs(9 * 60)));
+        assert!(session.is_idle_at(start + Duration::from_secs(10 * 60)));
+
+        session.set_idle_timeout_minutes(5).unwrap();
+        assert!(session.is_idle_at(start + Duration::from_secs(5 * 60)));
+
+        session.touch();
+        assert!(!session.is_idle());
+    }
+
+    #[test]
+    fn test_idle_timeout_limits() {
+        let mut session = SessionService::new();
+        assert!(session.set_idle_timeout_minutes(0).is_err());
+        assert!(session
+            .set_idle_timeout_minutes(MAX_IDLE_TIMEOUT_MINUTES + 1)
+            .is_err());
+        assert_eq!(session.idle_timeout_minutes(), DEFAULT_IDLE_TIMEOUT_MINUTES);
+    }
+}
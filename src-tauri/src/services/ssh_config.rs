@@ -6,7 +6,11 @@ use std::path::{Path, PathBuf};
 use chrono::Local;
 
 use crate::error::{AppError, AppResult};
-use crate::types::{SshConfig, SshHostConfig};
+use crate::services::ssh_config_document::SshConfigDocument;
+use crate::services::ssh_config_parser;
+use crate::types::{
+    SshConfig, SshConfigAst, SshConfigDirective, SshConfigEdit, SshConfigSection, SshHostConfig,
+};
 
 pub struct SshConfigService;
 
@@ -18,56 +22,94 @@ impl SshConfigService {
         file_path: Option<&str>,
         retention: Option<usize>,
     ) -> AppResult<()> {
-        let retention = retention.unwrap_or(10);
-
         let target_path = match file_path {
             Some(p) if !p.trim().is_empty() => PathBuf::from(p),
             _ => default_ssh_config_path()?,
         };
+        write_with_backup(&target_path, content, retention)
+    }
 
-        // 确保目录存在
-        if let Some(parent) = target_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
+    /// 对 SSH 配置依次应用结构化修改后保存（同样带备份与保留），未涉及的行保持原样；
+    /// 任一修改失败时不写入。返回修改后的内容
+    pub fn edit_config(
+        file_path: Option<&str>,
+        edits: &[SshConfigEdit],
+        retention: Option<usize>,
+    ) -> AppResult<String> {
+        let target_path = config_path_to_read(file_path)?;
+        let content = if target_path.exists() {
+            fs::read_to_string(&target_path)?
+        } else {
+            String::new()
+        };
 
-        // 写入前做备份（如果存在）
-        if target_path.exists() {
-            create_backup(&target_path)?;
-            enforce_retention(&target_path, retention)?;
+        let mut document = SshConfigDocument::parse(&content)?;
+        for edit in edits {
+            document.apply(edit)?;
         }
-
-        // 写入新内容
-        fs::write(&target_path, content)?;
-        Ok(())
+        let updated = document.to_string();
+        if updated != content {
+            write_with_backup(&target_path, &updated, retention)?;
+        }
+        Ok(updated)
     }
 
-    /// 读取 SSH 配置并解析为结构化数据
+    /// 读取 SSH 配置并解析为结构化数据（Include 不展开，Match 块不在其中）
     pub fn read_config(file_path: Option<&str>) -> AppResult<SshConfig> {
-        let target_path = match file_path {
-            Some(p) if !p.trim().is_empty() => PathBuf::from(p),
-            _ => default_ssh_config_path()?,
-        };
-
-        // 如果默认路径不存在，尝试 config.txt 作为兼容（Windows 常见）
-        let path_to_read = if !target_path.exists() {
-            let txt = target_path.with_extension("txt");
-            if txt.exists() {
-                txt
-            } else {
-                target_path
-            }
-        } else {
-            target_path
-        };
-
+        let path_to_read = config_path_to_read(file_path)?;
         let content = if path_to_read.exists() {
             fs::read_to_string(&path_to_read)?
         } else {
             String::new()
         };
 
-        Ok(parse_openssh_config(&content))
+        Ok(summarize_config(&ssh_config_parser::parse(&content)?))
+    }
+
+    /// 读取 SSH 配置的完整语法树，展开 Include；文件不存在时返回空配置
+    pub fn read_config_ast(file_path: Option<&str>) -> AppResult<SshConfigAst> {
+        let path_to_read = config_path_to_read(file_path)?;
+        if !path_to_read.exists() {
+            return Ok(SshConfigAst::default());
+        }
+        ssh_config_parser::parse_file(&path_to_read)
+    }
+}
+
+// 写入前如果目标文件已存在则创建带时间戳的备份，并清理超出保留数量的旧备份
+fn write_with_backup(target_path: &Path, content: &str, retention: Option<usize>) -> AppResult<()> {
+    let retention = retention.unwrap_or(10);
+
+    // 确保目录存在
+    if let Some(parent) = target_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // 写入前做备份（如果存在）
+    if target_path.exists() {
+        create_backup(target_path)?;
+        enforce_retention(target_path, retention)?;
+    }
+
+    // 写入新内容
+    fs::write(target_path, content)?;
+    Ok(())
+}
+
+fn config_path_to_read(file_path: Option<&str>) -> AppResult<PathBuf> {
+    let target_path = match file_path {
+        Some(p) if !p.trim().is_empty() => PathBuf::from(p),
+        _ => default_ssh_config_path()?,
+    };
+
+    // 如果默认路径不存在，尝试 config.txt 作为兼容（Windows 常见）
+    if !target_path.exists() {
+        let txt = target_path.with_extension("txt");
+        if txt.exists() {
+            return Ok(txt);
+        }
     }
+    Ok(target_path)
 }
 
 fn default_ssh_dir() -> AppResult<PathBuf> {
@@ -127,7 +169,7 @@ impl SshConfigService {
                     let mut buf = [0u8; 512];
                     let _ = f.read(&mut buf);
                     let head = String::from_utf8_lossy(&buf);
-                    if head.contains("PRIVATE KEY") {
+                    if head.contains("PRIVATE KEY") || head.starts_with("PuTTY-User-Key-File-") {
                         include = true;
                     }
                 }
@@ -143,6 +185,42 @@ impl SshConfigService {
         result.dedup();
         Ok(result)
     }
+
+    /// 读取 ~/.ssh（或指定目录）下的私钥文件及其同名 .pub 公钥（如存在）
+    /// 仅接受纯文件名，避免通过路径穿越读取目录以外的文件
+    pub fn read_identity_file(
+        dir_path: Option<&str>,
+        file_name: &str,
+    ) -> AppResult<(String, Option<String>)> {
+        let dir = match dir_path {
+            Some(p) if !p.trim().is_empty() => PathBuf::from(p),
+            _ => default_ssh_dir()?,
+        };
+
+        let is_plain_name = Path::new(file_name)
+            .file_name()
+            .and_then(|s| s.to_str())
+            .map(|n| n == file_name)
+            .unwrap_or(false);
+        if !is_plain_name {
+            return Err(AppError::ConfigError(format!(
+                "无效的密钥文件名: {}",
+                file_name
+            )));
+        }
+
+        let private_path = dir.join(file_name);
+        let private_key = fs::read_to_string(&private_path)?;
+
+        let public_path = dir.join(format!("{}.pub", file_name));
+        let public_key = if public_path.is_file() {
+            Some(fs::read_to_string(&public_path)?)
+        } else {
+            None
+        };
+
+        Ok((private_key, public_key))
+    }
 }
 
 fn default_ssh_config_path() -> AppResult<PathBuf> {
@@ -151,93 +229,96 @@ fn default_ssh_config_path() -> AppResult<PathBuf> {
     Ok(home.join(".ssh").join("config"))
 }
 
-/// 极简 OpenSSH config 解析器，提取全局设置与 Host 块常见字段
-fn parse_openssh_config(content: &str) -> SshConfig {
-    let mut global_settings: HashMap<String, String> = HashMap::new();
-    let mut hosts: Vec<SshHostConfig> = Vec::new();
-
-    let mut current_host: Option<SshHostConfig> = None;
-
-    for raw_line in content.lines() {
-        let mut line = raw_line.trim();
-        if line.is_empty() {
-            continue;
-        }
-        if line.starts_with('#') {
-            continue;
-        }
-
-        // 去除行内注释（简单处理：按 # 切割）
-        if let Some(idx) = line.find('#') {
-            line = &line[..idx].trim();
-            if line.is_empty() {
-                continue;
-            }
-        }
+// 可以出现多次且每次都生效的选项（IdentityFile 单独列出）
+const REPEATABLE_KEYWORDS: [&str; 6] = [
+    "localforward",
+    "remoteforward",
+    "dynamicforward",
+    "sendenv",
+    "setenv",
+    "certificatefile",
+];
+
+/// 从语法树提取全局设置与 Host 块常见字段；与 ssh 一致，重复的选项以第一个值为准，
+/// 可重复的选项另外保留全部值。选项名统一为小写
+fn summarize_config(ast: &SshConfigAst) -> SshConfig {
+    let global_settings = first_values(&ast.global);
+    let global_repeated_options = repeated_values(&ast.global);
+
+    let hosts = ast
+        .sections
+        .iter()
+        .filter_map(|section| match section {
+            SshConfigSection::Host {
+                patterns,
+                directives,
+                ..
+            } => Some(summarize_host(patterns, directives)),
+            SshConfigSection::Match { .. } => None,
+        })
+        .collect();
 
-        // 按空白分割 key 与 value
-        let mut parts = line.split_whitespace();
-        let key = match parts.next() {
-            Some(k) => k,
-            None => continue,
-        };
-        let value = parts.collect::<Vec<_>>().join(" ").trim().to_string();
-        if value.is_empty() {
-            continue;
-        }
+    SshConfig {
+        hosts,
+        global_settings,
+        global_repeated_options,
+    }
+}
 
-        let key_lc = key.to_lowercase();
-        if key_lc == "host" {
-            // 推入上一个 host
-            if let Some(h) = current_host.take() {
-                hosts.push(h);
-            }
+fn summarize_host(patterns: &[String], directives: &[SshConfigDirective]) -> SshHostConfig {
+    let first = |keyword: &str| {
+        directives
+            .iter()
+            .find(|d| d.is(keyword))
+            .map(SshConfigDirective::value)
+    };
+    let identity_files: Vec<String> = directives
+        .iter()
+        .filter(|d| d.is("identityfile"))
+        .map(SshConfigDirective::value)
+        .collect();
 
-            let host_pattern = value; // 允许包含空格（多个模式），此处整体保留
-            current_host = Some(SshHostConfig {
-                host_pattern,
-                hostname: None,
-                user: None,
-                port: None,
-                identity_file: None,
-                other_options: HashMap::new(),
-            });
-            continue;
-        }
+    let others: Vec<SshConfigDirective> = directives
+        .iter()
+        .filter(|d| {
+            !["hostname", "user", "port", "identityfile"]
+                .iter()
+                .any(|k| d.is(k))
+        })
+        .cloned()
+        .collect();
 
-        // 普通键值对
-        if let Some(h) = current_host.as_mut() {
-            match key_lc.as_str() {
-                "hostname" => h.hostname = Some(value),
-                "user" => h.user = Some(value),
-                "port" => {
-                    if let Ok(p) = value.parse::<u16>() {
-                        h.port = Some(p);
-                    }
-                }
-                "identityfile" | "identity_file" => h.identity_file = Some(value),
-                _ => {
-                    if !key_lc.is_empty() {
-                        h.other_options.insert(key.to_string(), value);
-                    }
-                }
-            }
-        } else {
-            // 全局设置
-            if !key_lc.is_empty() {
-                global_settings.insert(key.to_string(), value);
-            }
-        }
+    SshHostConfig {
+        host_pattern: ssh_config_parser::join_args(patterns),
+        hostname: first("hostname"),
+        user: first("user"),
+        port: first("port").and_then(|p| p.parse::<u16>().ok()),
+        identity_file: identity_files.first().cloned(),
+        identity_files,
+        other_options: first_values(&others),
+        repeated_options: repeated_values(&others),
     }
+}
 
-    if let Some(h) = current_host.take() {
-        hosts.push(h);
+fn first_values(directives: &[SshConfigDirective]) -> HashMap<String, String> {
+    let mut options = HashMap::new();
+    for directive in directives {
+        options
+            .entry(directive.keyword.to_ascii_lowercase())
+            .or_insert_with(|| directive.value());
     }
+    options
+}
 
-    SshConfig {
-        hosts,
-        global_settings,
+fn repeated_values(directives: &[SshConfigDirective]) -> HashMap<String, Vec<String>> {
+    let mut options: HashMap<String, Vec<String>> = HashMap::new();
+    for directive in directives {
+        let keyword = directive.keyword.to_ascii_lowercase();
+        if REPEATABLE_KEYWORDS.contains(&keyword.as_str()) {
+            options.entry(keyword).or_default().push(directive.value());
+        }
     }
+    options
 }
 
 fn create_backup(target_path: &Path) -> AppResult<PathBuf> {
@@ -0,0 +1,635 @@
+use std::fmt;
+
+use crate::error::{AppError, AppResult};
+use crate::services::ssh_config_parser::{
+    self, join_args, split_args, split_args_with_comment, split_keyword,
+};
+use crate::types::{SshConfigEdit, SshDirectiveUpdate};
+
+// 新增 Host 块中选项的缩进，与编辑器生成的配置一致
+const DEFAULT_INDENT: &str = "    ";
+
+// 原始行：text 不含换行符，ending 为原来的换行符（最后一行可能没有）
+#[derive(Debug, Clone)]
+struct Line {
+    text: String,
+    ending: String,
+}
+
+impl Line {
+    fn new(text: String, ending: &str) -> Self {
+        Self {
+            text,
+            ending: ending.to_string(),
+        }
+    }
+
+    // 去掉文件开头的 BOM 后的内容
+    fn content(&self) -> &str {
+        self.text.trim_start_matches('\u{feff}')
+    }
+
+    fn directive(&self) -> Option<(&str, Vec<String>)> {
+        let (keyword, rest) = split_keyword(self.content())?;
+        Some((keyword, split_args(rest).ok()?))
+    }
+
+    fn is(&self, keyword: &str) -> bool {
+        self.directive()
+            .is_some_and(|(k, _)| k.eq_ignore_ascii_case(keyword))
+    }
+
+    fn is_section_header(&self) -> bool {
+        self.is("host") || self.is("match")
+    }
+
+    fn is_comment(&self) -> bool {
+        self.content().trim_start().starts_with('#')
+    }
+
+    fn is_blank(&self) -> bool {
+        self.content().trim().is_empty()
+    }
+
+    fn indent(&self) -> &str {
+        let content = self.content();
+        &content[..content.len() - content.trim_start().len()]
+    }
+
+    // 替换参数，保留缩进、关键字写法、分隔符与行内注释
+    fn with_value(&self, value: &str) -> Line {
+        let content = self.content();
+        let prefix = &self.text[..self.text.len() - content.len()];
+        let (_, rest) = split_keyword(content).unwrap_or((content, ""));
+        let head = &content[..content.len() - rest.len()];
+        let (_, comment) = split_args_with_comment(rest).unwrap_or_default();
+        Line::new(
+            format!("{}{}{}{}", prefix, head, value, comment),
+            &self.ending,
+        )
+    }
+}
+
+// Host 或 Match 块；紧挨在块前的注释行属于该块，随块移动与删除
+#[derive(Debug, Clone)]
+struct Block {
+    leading: Vec<Line>,
+    header: Line,
+    body: Vec<Line>,
+}
+
+impl Block {
+    fn host_patterns(&self) -> Option<Vec<String>> {
+        match self.header.directive() {
+            Some((keyword, patterns)) if keyword.eq_ignore_ascii_case("host") => Some(patterns),
+            _ => None,
+        }
+    }
+
+    fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.leading
+            .iter()
+            .chain(std::iter::once(&self.header))
+            .chain(self.body.iter())
+    }
+}
+
+/// 保留格式的 SSH 配置文档：按行保存原文，结构化修改只改动涉及的行，
+/// 其余行（含注释、空行、缩进与换行符）原样输出
+#[derive(Debug, Clone)]
+pub struct SshConfigDocument {
+    preamble: Vec<Line>, // 第一个 Host/Match 之前的全局部分
+    blocks: Vec<Block>,
+    newline: String,
+}
+
+impl SshConfigDocument {
+    /// 解析配置文本，语法错误时带行号返回
+    pub fn parse(content: &str) -> AppResult<Self> {
+        ssh_config_parser::parse(content)?;
+
+        let newline = match content.find('\n') {
+            Some(index) if content[..index].ends_with('\r') => "\r\n",
+            _ => "\n",
+        };
+        let mut preamble: Vec<Line> = Vec::new();
+        let mut blocks: Vec<Block> = Vec::new();
+
+        for piece in content.split_inclusive('\n') {
+            let (text, ending) = match piece.strip_suffix('\n') {
+                Some(text) => match text.strip_suffix('\r') {
+                    Some(text) => (text, "\r\n"),
+                    None => (text, "\n"),
+                },
+                None => (piece, ""),
+            };
+            let line = Line::new(text.to_string(), ending);
+
+            let container = match blocks.last_mut() {
+                Some(block) => &mut block.body,
+                None => &mut preamble,
+            };
+            if line.is_section_header() {
+                let comments = container
+                    .iter()
+                    .rev()
+                    .take_while(|l| l.is_comment())
+                    .count();
+                let leading = container.split_off(container.len() - comments);
+                blocks.push(Block {
+                    leading,
+                    header: line,
+                    body: Vec::new(),
+                });
+            } else {
+                container.push(line);
+            }
+        }
+
+        Ok(Self {
+            preamble,
+            blocks,
+            newline: newline.to_string(),
+        })
+    }
+
+    /// 应用一项修改；出错时文档可能已部分修改，调用方应放弃该文档
+    pub fn apply(&mut self, edit: &SshConfigEdit) -> AppResult<()> {
+        match edit {
+            SshConfigEdit::AddHost {
+                patterns,
+                directives,
+                index,
+            } => self.add_host(patterns, directives, *index),
+            SshConfigEdit::UpdateHost {
+                host,
+                patterns,
+                directives,
+            } => {
+                let index = self.find_host(host)?;
+                if let Some(patterns) = patterns {
+                    check_patterns(patterns)?;
+                    if self.position_of(patterns).is_some_and(|i| i != index) {
+                        return Err(host_exists(patterns));
+                    }
+                    let block = &mut self.blocks[index];
+                    block.header = block.header.with_value(&join_args(patterns));
+                }
+                for update in directives {
+                    self.set_directive(Some(index), update)?;
+                }
+                Ok(())
+            }
+            SshConfigEdit::RemoveHost { host } => {
+                let index = self.find_host(host)?;
+                self.blocks.remove(index);
+                Ok(())
+            }
+            SshConfigEdit::ReorderHosts { order } => self.reorder_hosts(order),
+            SshConfigEdit::SetDirective { host, directive } => {
+                let index = host.as_deref().map(|h| self.find_host(h)).transpose()?;
+                self.set_directive(index, directive)
+            }
+        }
+    }
+
+    fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.preamble
+            .iter()
+            .chain(self.blocks.iter().flat_map(Block::lines))
+    }
+
+    fn position_of(&self, patterns: &[String]) -> Option<usize> {
+        self.blocks
+            .iter()
+            .position(|block| block.host_patterns().as_deref() == Some(patterns))
+    }
+
+    fn find_host(&self, host: &str) -> AppResult<usize> {
+        let patterns =
+            split_args(host).map_err(|e| AppError::ConfigError(format!("无效的 Host: {}", e)))?;
+        self.position_of(&patterns)
+            .ok_or_else(|| AppError::ConfigError(format!("Host 不存在: {}", host)))
+    }
+
+    fn new_line(&self, text: String) -> Line {
+        Line::new(text, &self.newline)
+    }
+
+    fn add_host(
+        &mut self,
+        patterns: &[String],
+        directives: &[SshDirectiveUpdate],
+        index: Option<usize>,
+    ) -> AppResult<()> {
+        check_patterns(patterns)?;
+        if self.position_of(patterns).is_some() {
+            return Err(host_exists(patterns));
+        }
+
+        // index 按 Host 块计数
+        let position = index
+            .and_then(|index| {
+                self.blocks
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, block)| block.host_patterns().is_some())
+                    .nth(index)
+                    .map(|(position, _)| position)
+            })
+            .unwrap_or(self.blocks.len());
+
+        // 与前后内容之间各空一行
+        let previous = match position {
+            0 => self.preamble.last(),
+            _ => self.blocks[position - 1].lines().last(),
+        };
+        let mut block = Block {
+            leading: Vec::new(),
+            header: self.new_line(format!("Host {}", join_args(patterns))),
+            body: Vec::new(),
+        };
+        if previous.is_some_and(|line| !line.is_blank()) {
+            block.leading.push(self.new_line(String::new()));
+        }
+        for update in directives {
+            let values = parse_update(update)?;
+            for value in &values {
+                let line = self.new_line(format!("{}{} {}", DEFAULT_INDENT, update.keyword, value));
+                block.body.push(line);
+            }
+        }
+        if position < self.blocks.len() {
+            block.body.push(self.new_line(String::new()));
+        }
+
+        self.blocks.insert(position, block);
+        Ok(())
+    }
+
+    fn reorder_hosts(&mut self, order: &[String]) -> AppResult<()> {
+        let slots: Vec<usize> = (0..self.blocks.len())
+            .filter(|&i| self.blocks[i].host_patterns().is_some())
+            .collect();
+        let mut picked = Vec::with_capacity(order.len());
+        for host in order {
+            let index = self.find_host(host)?;
+            if picked.contains(&index) {
+                return Err(AppError::ConfigError(format!("Host 重复: {}", host)));
+            }
+            picked.push(index);
+        }
+        if picked.len() != slots.len() {
+            return Err(AppError::ConfigError("排序需要包含全部 Host".to_string()));
+        }
+
+        // 原来位于末尾的块移到中间时补一个空行与后面的块分开
+        let last = self.blocks.len().checked_sub(1);
+        let mut reordered: Vec<Block> = picked.iter().map(|&i| self.blocks[i].clone()).collect();
+        for (block, (&from, &to)) in reordered.iter_mut().zip(picked.iter().zip(&slots)) {
+            let ends_blank = block.lines().last().is_some_and(Line::is_blank);
+            if Some(from) == last && Some(to) != last && !ends_blank {
+                block.body.push(Line::new(String::new(), &self.newline));
+            }
+        }
+        for (slot, block) in slots.into_iter().zip(reordered) {
+            self.blocks[slot] = block;
+        }
+        Ok(())
+    }
+
+    // 已有的同名选项按顺序改写为新值，多余的删除，不足的追加在最后一个同名选项
+    // （或块内最后一个选项）之后；值未变的行保持原样
+    fn set_directive(&mut self, host: Option<usize>, update: &SshDirectiveUpdate) -> AppResult<()> {
+        let values = parse_update(update)?;
+        let newline = self.newline.clone();
+        let has_blocks = !self.blocks.is_empty();
+        let (lines, default_indent, is_global) = match host {
+            Some(index) => (&mut self.blocks[index].body, DEFAULT_INDENT, false),
+            None => (&mut self.preamble, "", true),
+        };
+
+        let existing: Vec<usize> = (0..lines.len())
+            .filter(|&i| lines[i].is(&update.keyword))
+            .collect();
+        for (&i, value) in existing.iter().zip(&values) {
+            let unchanged = lines[i]
+                .directive()
+                .is_some_and(|(_, args)| split_args(value).is_ok_and(|new| new == args));
+            if !unchanged {
+                lines[i] = lines[i].with_value(value);
+            }
+        }
+        for &i in existing.iter().skip(values.len()).rev() {
+            lines.remove(i);
+        }
+        if values.len() <= existing.len() {
+            return Ok(());
+        }
+
+        let last_directive = (0..lines.len())
+            .rev()
+            .find(|&i| lines[i].directive().is_some());
+        let insert_at = existing
+            .last()
+            .or(last_directive.as_ref())
+            .map(|&i| i + 1)
+            .unwrap_or(if is_global { lines.len() } else { 0 });
+        let indent = lines
+            .iter()
+            .find(|line| line.directive().is_some())
+            .map(|line| line.indent().to_string())
+            .unwrap_or_else(|| default_indent.to_string());
+
+        let mut added: Vec<Line> = values[existing.len()..]
+            .iter()
+            .map(|value| Line::new(format!("{}{} {}", indent, update.keyword, value), &newline))
+            .collect();
+        // 全局部分原本为空时与后面的 Host 块空一行
+        if is_global && insert_at == lines.len() && has_blocks {
+            let ends_blank = lines.last().is_some_and(Line::is_blank);
+            if !ends_blank {
+                added.push(Line::new(String::new(), &newline));
+            }
+        }
+        lines.splice(insert_at..insert_at, added);
+        Ok(())
+    }
+}
+
+impl fmt::Display for SshConfigDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = self.lines().peekable();
+        while let Some(line) = lines.next() {
+            f.write_str(&line.text)?;
+            // 原文最后一行没有换行符，后面追加了内容时补上
+            if line.ending.is_empty() && lines.peek().is_some() {
+                f.write_str(&self.newline)?;
+            } else {
+                f.write_str(&line.ending)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_patterns(patterns: &[String]) -> AppResult<()> {
+    if patterns.is_empty() || patterns.iter().any(|p| p.trim().is_empty()) {
+        return Err(AppError::ConfigError("Host 模式不能为空".to_string()));
+    }
+    Ok(())
+}
+
+fn host_exists(patterns: &[String]) -> AppError {
+    AppError::ConfigError(format!("Host 已存在: {}", join_args(patterns)))
+}
+
+// 校验关键字与每个值，返回去掉首尾空白的值
+fn parse_update(update: &SshDirectiveUpdate) -> AppResult<Vec<String>> {
+    let keyword = update.keyword.as_str();
+    let valid_keyword = !keyword.is_empty()
+        && keyword.chars().all(|c| c.is_ascii_alphanumeric())
+        && !keyword.eq_ignore_ascii_case("host")
+        && !keyword.eq_ignore_ascii_case("match");
+    if !valid_keyword {
+        return Err(AppError::ConfigError(format!("无效的选项名: {}", keyword)));
+    }
+
+    update
+        .values
+        .iter()
+        .map(|value| {
+            let value = value.trim();
+            match split_args_with_comment(value) {
+                Ok((args, "")) if !args.is_empty() => Ok(value.to_string()),
+                _ => Err(AppError::ConfigError(format!(
+                    "{} 的值无效: {}",
+                    keyword, value
+                ))),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+# 全局设置
+ServerAliveInterval=30\r
+\tCompression yes   # 压缩
+
+# 跳板机
+Host jump
+\tHostName 10.0.0.1 # 内网
+\tUser  admin
+
+Match host *.internal
+  ForwardAgent yes
+
+Host web \"db 1\"
+  HostName web.example.com
+  IdentityFile ~/.ssh/a
+  IdentityFile ~/.ssh/b
+# 末尾注释";
+
+    fn update(keyword: &str, values: &[&str]) -> SshDirectiveUpdate {
+        SshDirectiveUpdate {
+            keyword: keyword.to_string(),
+            values: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    fn edited(edits: &[SshConfigEdit]) -> String {
+        let mut document = SshConfigDocument::parse(CONFIG).unwrap();
+        for edit in edits {
+            document.apply(edit).unwrap();
+        }
+        document.to_string()
+    }
+
+    #[test]
+    fn test_round_trip_is_byte_identical() {
+        assert_eq!(
+            SshConfigDocument::parse(CONFIG).unwrap().to_string(),
+            CONFIG
+        );
+        for content in ["", "\n", "\u{feff}Host a\r\n  User b\r\n", "Host a\n\n\n"] {
+            assert_eq!(
+                SshConfigDocument::parse(content).unwrap().to_string(),
+                content
+            );
+        }
+        assert!(matches!(
+            SshConfigDocument::parse("Host a\n  User \"b\n"),
+            Err(AppError::ConfigSyntax(_, 2, _))
+        ));
+    }
+
+    #[test]
+    fn test_set_directive_keeps_untouched_lines() {
+        let output = edited(&[
+            SshConfigEdit::SetDirective {
+                host: Some("jump".to_string()),
+                directive: update("hostname", &["10.0.0.2"]),
+            },
+            // 值未变的行原样保留（包括多余的空白）
+            SshConfigEdit::SetDirective {
+                host: Some("jump".to_string()),
+                directive: update("User", &["admin"]),
+            },
+            SshConfigEdit::SetDirective {
+                host: Some("jump".to_string()),
+                directive: update("Port", &["2222"]),
+            },
+            SshConfigEdit::SetDirective {
+                host: Some("web \"db 1\"".to_string()),
+                directive: update("IdentityFile", &["~/.ssh/c"]),
+            },
+            SshConfigEdit::SetDirective {
+                host: None,
+                directive: update("ServerAliveInterval", &["60"]),
+            },
+            SshConfigEdit::SetDirective {
+                host: None,
+                directive: update("Compression", &[]),
+            },
+        ]);
+        assert_eq!(
+            output,
+            "\
+# 全局设置
+ServerAliveInterval=60\r
+
+# 跳板机
+Host jump
+\tHostName 10.0.0.2 # 内网
+\tUser  admin
+\tPort 2222
+
+Match host *.internal
+  ForwardAgent yes
+
+Host web \"db 1\"
+  HostName web.example.com
+  IdentityFile ~/.ssh/c
+# 末尾注释"
+        );
+    }
+
+    #[test]
+    fn test_add_update_remove_hosts() {
+        // 删除 Host 时连同块前的注释与块内的注释一起删除
+        let output = edited(&[
+            SshConfigEdit::RemoveHost {
+                host: "web \"db 1\"".to_string(),
+            },
+            SshConfigEdit::AddHost {
+                patterns: vec!["new".to_string()],
+                directives: vec![update("HostName", &["new.example.com"])],
+                index: Some(0),
+            },
+            SshConfigEdit::AddHost {
+                patterns: vec!["last".to_string()],
+                directives: vec![update(
+                    "LocalForward",
+                    &["80 localhost:8080", "443 localhost:8443"],
+                )],
+                index: None,
+            },
+            SshConfigEdit::UpdateHost {
+                host: "jump".to_string(),
+                patterns: Some(vec!["bastion".to_string(), "jump".to_string()]),
+                directives: vec![update("User", &["root"])],
+            },
+        ]);
+        assert_eq!(
+            output,
+            "\
+# 全局设置
+ServerAliveInterval=30\r
+\tCompression yes   # 压缩
+
+Host new
+    HostName new.example.com
+
+# 跳板机
+Host bastion jump
+\tHostName 10.0.0.1 # 内网
+\tUser  root
+
+Match host *.internal
+  ForwardAgent yes
+
+Host last
+    LocalForward 80 localhost:8080
+    LocalForward 443 localhost:8443
+"
+        );
+
+        // 追加到没有结尾换行的文件时补上换行并空一行
+        let mut document = SshConfigDocument::parse("Host a").unwrap();
+        document
+            .apply(&SshConfigEdit::AddHost {
+                patterns: vec!["b".to_string()],
+                directives: Vec::new(),
+                index: None,
+            })
+            .unwrap();
+        assert_eq!(document.to_string(), "Host a\n\nHost b\n");
+
+        let mut document = SshConfigDocument::parse(CONFIG).unwrap();
+        let missing = SshConfigEdit::RemoveHost {
+            host: "missing".to_string(),
+        };
+        assert!(document.apply(&missing).is_err());
+        let duplicate = SshConfigEdit::AddHost {
+            patterns: vec!["jump".to_string()],
+            directives: Vec::new(),
+            index: None,
+        };
+        assert!(document.apply(&duplicate).is_err());
+        let bad_value = SshConfigEdit::SetDirective {
+            host: None,
+            directive: update("User", &["a # b"]),
+        };
+        assert!(document.apply(&bad_value).is_err());
+    }
+
+    #[test]
+    fn test_reorder_hosts_moves_leading_comments() {
+        let output = edited(&[SshConfigEdit::ReorderHosts {
+            order: vec!["web \"db 1\"".to_string(), "jump".to_string()],
+        }]);
+        assert_eq!(
+            output,
+            "\
+# 全局设置
+ServerAliveInterval=30\r
+\tCompression yes   # 压缩
+
+Host web \"db 1\"
+  HostName web.example.com
+  IdentityFile ~/.ssh/a
+  IdentityFile ~/.ssh/b
+# 末尾注释
+
+Match host *.internal
+  ForwardAgent yes
+
+# 跳板机
+Host jump
+\tHostName 10.0.0.1 # 内网
+\tUser  admin
+
+"
+        );
+
+        let mut document = SshConfigDocument::parse(CONFIG).unwrap();
+        let partial = SshConfigEdit::ReorderHosts {
+            order: vec!["jump".to_string()],
+        };
+        assert!(document.apply(&partial).is_err());
+    }
+}
@@ -0,0 +1,540 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{AppError, AppResult};
+use crate::types::{SshConfigAst, SshConfigDirective, SshConfigSection, SshMatchCriterion};
+
+// 与 OpenSSH 的 READCONF_MAX_DEPTH 一致
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// 不带参数的 Match 条件
+const MATCH_FLAGS: [&str; 3] = ["all", "canonical", "final"];
+// 带一个参数（模式列表或命令）的 Match 条件
+const MATCH_ARG_CRITERIA: [&str; 10] = [
+    "host",
+    "originalhost",
+    "user",
+    "localuser",
+    "localnetwork",
+    "exec",
+    "tagged",
+    "command",
+    "sessiontype",
+    "version",
+];
+
+/// 解析 OpenSSH 配置文本，Include 只记录不展开
+pub fn parse(content: &str) -> AppResult<SshConfigAst> {
+    parse_content(content, None, None)
+}
+
+/// 解析配置文件并展开 Include；相对路径相对于该文件所在目录（~/.ssh/config 即 ~/.ssh），
+/// 与 ssh 一致，不存在的 Include 文件被忽略
+pub fn parse_file(path: &Path) -> AppResult<SshConfigAst> {
+    let base_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let content = fs::read_to_string(path)?;
+    parse_content(&content, Some(path), Some((&base_dir, 0)))
+}
+
+impl SshConfigDirective {
+    /// 关键字不区分大小写
+    pub fn is(&self, keyword: &str) -> bool {
+        self.keyword.eq_ignore_ascii_case(keyword)
+    }
+
+    /// 参数重新拼接为一行，必要时加引号
+    pub fn value(&self) -> String {
+        join_args(&self.args)
+    }
+}
+
+impl SshConfigSection {
+    pub fn directives(&self) -> &[SshConfigDirective] {
+        match self {
+            SshConfigSection::Host { directives, .. }
+            | SshConfigSection::Match { directives, .. } => directives,
+        }
+    }
+
+    fn directives_mut(&mut self) -> &mut Vec<SshConfigDirective> {
+        match self {
+            SshConfigSection::Host { directives, .. }
+            | SshConfigSection::Match { directives, .. } => directives,
+        }
+    }
+}
+
+/// 将参数拼接为配置中的一行；含空白、引号、# 或为空的参数加双引号
+pub fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| quote_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn quote_arg(arg: &str) -> String {
+    let needs_quotes = arg.is_empty()
+        || arg.starts_with('#')
+        || arg
+            .chars()
+            .any(|c| c.is_ascii_whitespace() || c == '"' || c == '\'' || c == '\\');
+    if !needs_quotes {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn parse_content(
+    content: &str,
+    path: Option<&Path>,
+    include: Option<(&Path, usize)>,
+) -> AppResult<SshConfigAst> {
+    let mut ast = SshConfigAst {
+        path: path.map(Path::to_path_buf),
+        ..Default::default()
+    };
+
+    for (index, raw_line) in content.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| syntax_error(path, line, message);
+        let raw_line = if index == 0 {
+            raw_line.trim_start_matches('\u{feff}')
+        } else {
+            raw_line
+        };
+
+        let Some((keyword, rest)) = split_keyword(raw_line) else {
+            continue;
+        };
+        if keyword.is_empty() {
+            return Err(error("缺少关键字".to_string()));
+        }
+        let args = split_args(rest).map_err(|message| error(message.to_string()))?;
+        if args.is_empty() {
+            return Err(error(format!("{} 缺少参数", keyword)));
+        }
+
+        if keyword.eq_ignore_ascii_case("host") {
+            ast.sections.push(SshConfigSection::Host {
+                patterns: args,
+                line,
+                directives: Vec::new(),
+            });
+            continue;
+        }
+        if keyword.eq_ignore_ascii_case("match") {
+            ast.sections.push(SshConfigSection::Match {
+                criteria: parse_match(&args).map_err(error)?,
+                line,
+                directives: Vec::new(),
+            });
+            continue;
+        }
+
+        let mut directive = SshConfigDirective {
+            keyword: keyword.to_string(),
+            args,
+            line,
+            includes: Vec::new(),
+        };
+        if let Some((base_dir, depth)) = include.filter(|_| directive.is("include")) {
+            if depth + 1 > MAX_INCLUDE_DEPTH {
+                return Err(error("Include 嵌套层数过多".to_string()));
+            }
+            for pattern in &directive.args {
+                for file in expand_include(pattern, base_dir) {
+                    let content = fs::read_to_string(&file)?;
+                    directive.includes.push(parse_content(
+                        &content,
+                        Some(&file),
+                        Some((base_dir, depth + 1)),
+                    )?);
+                }
+            }
+        }
+
+        match ast.sections.last_mut() {
+            Some(section) => section.directives_mut().push(directive),
+            None => ast.global.push(directive),
+        }
+    }
+
+    Ok(ast)
+}
+
+fn syntax_error(path: Option<&Path>, line: usize, message: String) -> AppError {
+    let file = path
+        .map(|p| format!(" {} ", p.display()))
+        .unwrap_or_default();
+    AppError::ConfigSyntax(file, line, message)
+}
+
+// 拆出关键字与其余部分：关键字与参数之间为空白或一个 =（两侧可有空白）。
+// 空行与注释行返回 None
+pub(crate) fn split_keyword(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_matches(|c: char| c.is_ascii_whitespace());
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let end = line
+        .find(|c: char| c.is_ascii_whitespace() || c == '=')
+        .unwrap_or(line.len());
+    let rest = line[end..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+    let rest = rest.strip_prefix('=').unwrap_or(rest);
+    Some((&line[..end], rest))
+}
+
+// 按 OpenSSH 的规则拆分参数：空白分隔，支持单双引号与 \" \' \\ 转义（引号外还有 "\ "），
+// 参数开头的 # 表示行内注释，参数中间的 # 是普通字符
+pub(crate) fn split_args(rest: &str) -> Result<Vec<String>, &'static str> {
+    split_args_with_comment(rest).map(|(args, _)| args)
+}
+
+// 同 split_args，另外返回最后一个参数之后的行内注释（含前面的空白），没有注释时为空
+pub(crate) fn split_args_with_comment(rest: &str) -> Result<(Vec<String>, &str), &'static str> {
+    let mut args = Vec::new();
+    let mut chars = rest.char_indices().peekable();
+    let mut args_end = 0;
+
+    loop {
+        while chars.next_if(|(_, c)| c.is_ascii_whitespace()).is_some() {}
+        match chars.peek() {
+            None => return Ok((args, "")),
+            Some((_, '#')) => return Ok((args, &rest[args_end..])),
+            Some(_) => {}
+        }
+
+        let mut arg = String::new();
+        let mut quote = None;
+        args_end = rest.len();
+        while let Some((index, c)) = chars.next() {
+            match (c, quote) {
+                ('\\', _) => match chars.peek() {
+                    Some(&(_, next @ ('"' | '\'' | '\\'))) => {
+                        arg.push(next);
+                        chars.next();
+                    }
+                    Some((_, ' ')) if quote.is_none() => {
+                        arg.push(' ');
+                        chars.next();
+                    }
+                    _ => arg.push('\\'),
+                },
+                (c, None) if c.is_ascii_whitespace() => {
+                    args_end = index;
+                    break;
+                }
+                ('"' | '\'', None) => quote = Some(c),
+                (c, Some(q)) if c == q => quote = None,
+                (c, _) => arg.push(c),
+            }
+        }
+        if quote.is_some() {
+            return Err("引号不匹配");
+        }
+        args.push(arg);
+    }
+}
+
+fn parse_match(args: &[String]) -> Result<Vec<SshMatchCriterion>, String> {
+    let mut criteria = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let (negated, name) = match arg.strip_prefix('!') {
+            Some(name) => (true, name),
+            None => (false, arg.as_str()),
+        };
+        let keyword = name.to_ascii_lowercase();
+        let arg = if MATCH_FLAGS.contains(&keyword.as_str()) {
+            None
+        } else if MATCH_ARG_CRITERIA.contains(&keyword.as_str()) {
+            Some(
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("Match {} 缺少参数", name))?,
+            )
+        } else {
+            return Err(format!("不支持的 Match 条件: {}", name));
+        };
+        criteria.push(SshMatchCriterion {
+            keyword,
+            negated,
+            arg,
+        });
+    }
+
+    // all 只能与 canonical、final 同时使用
+    let has_all = criteria.iter().any(|c| c.keyword == "all");
+    if has_all && criteria.iter().any(|c| c.arg.is_some()) {
+        return Err("Match all 不能与其他条件同时使用".to_string());
+    }
+    Ok(criteria)
+}
+
+// 展开 Include 中的路径：~ 表示主目录，相对路径相对于 base_dir，文件名中的 * ? 按通配符匹配；
+// 结果只含存在的文件，按路径排序
+fn expand_include(pattern: &str, base_dir: &Path) -> Vec<PathBuf> {
+    let path = match pattern.strip_prefix("~/") {
+        Some(rest) => match dirs::home_dir() {
+            Some(home) => home.join(rest),
+            None => return Vec::new(),
+        },
+        None => base_dir.join(pattern),
+    };
+
+    let mut matches = vec![PathBuf::new()];
+    for component in path.components() {
+        let name = component.as_os_str();
+        let wildcard = name.to_str().filter(|name| name.contains(['*', '?']));
+        matches = match wildcard {
+            None => matches.into_iter().map(|dir| dir.join(name)).collect(),
+            Some(wildcard) => matches
+                .iter()
+                .flat_map(|dir| list_matching(dir, wildcard))
+                .collect(),
+        };
+    }
+
+    let mut files: Vec<PathBuf> = matches.into_iter().filter(|p| p.is_file()).collect();
+    files.sort();
+    files
+}
+
+// 与 glob(3) 一致，模式不以 . 开头时不匹配隐藏文件
+fn list_matching(dir: &Path, wildcard: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.file_name().to_str().is_some_and(|name| {
+                (wildcard.starts_with('.') || !name.starts_with('.'))
+                    && wildcard_match(wildcard, name)
+            })
+        })
+        .map(|entry| dir.join(entry.file_name()))
+        .collect()
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // 最近一个 * 的位置及其匹配到的 name 位置，用于回溯
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn directive(keyword: &str, args: &[&str], line: usize) -> SshConfigDirective {
+        SshConfigDirective {
+            keyword: keyword.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            line,
+            includes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_full_syntax() {
+        let content = "\
+# 全局
+ServerAliveInterval=30
+Include config.d/*
+
+Host web-* !web-test db
+    HostName 10.0.0.1
+    IdentityFile ~/.ssh/id_ed25519
+    IdentityFile \"~/.ssh/my key\"
+    LocalForward 8080 localhost:80
+    LocalForward 8443 localhost:443
+    ProxyCommand ssh -W %h:%p 'jump host'  # 行内注释
+    Password pass#word
+  User = alice
+
+Match host *.example.com !user root exec \"test -f /tmp/x\"
+    ForwardAgent yes
+Match canonical all
+    Port 2222
+";
+        let ast = parse(content).unwrap();
+        assert_eq!(ast.path, None);
+        assert_eq!(
+            ast.global,
+            [
+                directive("ServerAliveInterval", &["30"], 2),
+                directive("Include", &["config.d/*"], 3),
+            ]
+        );
+        assert_eq!(ast.sections.len(), 3);
+
+        let SshConfigSection::Host {
+            patterns,
+            line,
+            directives,
+        } = &ast.sections[0]
+        else {
+            panic!("expected Host section");
+        };
+        assert_eq!(patterns, &["web-*", "!web-test", "db"]);
+        assert_eq!(*line, 5);
+        assert_eq!(
+            directives,
+            &[
+                directive("HostName", &["10.0.0.1"], 6),
+                directive("IdentityFile", &["~/.ssh/id_ed25519"], 7),
+                directive("IdentityFile", &["~/.ssh/my key"], 8),
+                directive("LocalForward", &["8080", "localhost:80"], 9),
+                directive("LocalForward", &["8443", "localhost:443"], 10),
+                directive("ProxyCommand", &["ssh", "-W", "%h:%p", "jump host"], 11),
+                directive("Password", &["pass#word"], 12),
+                directive("User", &["alice"], 13),
+            ]
+        );
+        assert_eq!(directives[5].value(), "ssh -W %h:%p \"jump host\"");
+
+        let SshConfigSection::Match { criteria, line, .. } = &ast.sections[1] else {
+            panic!("expected Match section");
+        };
+        assert_eq!(*line, 15);
+        let criteria: Vec<_> = criteria
+            .iter()
+            .map(|c| (c.keyword.as_str(), c.negated, c.arg.as_deref()))
+            .collect();
+        assert_eq!(
+            criteria,
+            [
+                ("host", false, Some("*.example.com")),
+                ("user", true, Some("root")),
+                ("exec", false, Some("test -f /tmp/x")),
+            ]
+        );
+        assert_eq!(
+            ast.sections[2].directives(),
+            &[directive("Port", &["2222"], 18)]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_report_line() {
+        let cases = [
+            ("Host a\n  HostName\n", 2),
+            ("Host a\n  ProxyCommand \"ssh -W\n", 2),
+            ("\n\nMatch host\n", 3),
+            ("Match bogus x\n", 1),
+            ("Match all host a\n", 1),
+            ("Port = # 只有注释\n", 1),
+        ];
+        for (content, line) in cases {
+            match parse(content) {
+                Err(AppError::ConfigSyntax(_, error_line, _)) => {
+                    assert_eq!(error_line, line, "{:?}", content)
+                }
+                other => panic!("{:?} -> {:?}", content, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_quote_arg_round_trip() {
+        let args: Vec<String> = [
+            "plain",
+            "with space",
+            "",
+            "#hash",
+            "say \"hi\"",
+            "back\\slash",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+        let line = format!("Key {}", join_args(&args));
+        let ast = parse(&line).unwrap();
+        assert_eq!(ast.global[0].args, args);
+    }
+
+    #[test]
+    fn test_parse_file_expands_includes() {
+        let dir = tempdir().unwrap();
+        let config_d = dir.path().join("config.d");
+        fs::create_dir(&config_d).unwrap();
+        fs::write(config_d.join("b.conf"), "Host b\n  User bob\n").unwrap();
+        fs::write(config_d.join("a.conf"), "Include nested\nHost a\n").unwrap();
+        fs::write(config_d.join(".hidden.conf"), "Host hidden\n").unwrap();
+        fs::write(dir.path().join("nested"), "User nested\n").unwrap();
+        let config = dir.path().join("config");
+        fs::write(
+            &config,
+            "Include config.d/*.conf missing\nHost main\n  Include config.d/b.conf\n",
+        )
+        .unwrap();
+
+        let ast = parse_file(&config).unwrap();
+        assert_eq!(ast.path.as_deref(), Some(config.as_path()));
+        let included: Vec<_> = ast.global[0]
+            .includes
+            .iter()
+            .map(|a| a.path.clone().unwrap())
+            .collect();
+        assert_eq!(included, [config_d.join("a.conf"), config_d.join("b.conf")]);
+        // 嵌套 Include 同样相对于主配置所在目录
+        let nested = &ast.global[0].includes[0].global[0].includes;
+        assert_eq!(nested.len(), 1);
+        assert_eq!(nested[0].global, [directive("User", &["nested"], 1)]);
+        // Host 块中的 Include 记录在该块中
+        assert_eq!(ast.sections[0].directives()[0].includes.len(), 1);
+
+        // 不从文件解析时不展开
+        let content = fs::read_to_string(&config).unwrap();
+        assert!(parse(&content).unwrap().global[0].includes.is_empty());
+
+        // 循环包含在达到层数上限时报错
+        fs::write(dir.path().join("loop"), "Include loop\n").unwrap();
+        assert!(matches!(
+            parse_file(&dir.path().join("loop")),
+            Err(AppError::ConfigSyntax(_, 1, _))
+        ));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.conf", "a.conf"));
+        assert!(wildcard_match("a?c*", "abcdef"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("*.conf", "a.conf.bak"));
+        assert!(!wildcard_match("a?c", "ac"));
+    }
+}
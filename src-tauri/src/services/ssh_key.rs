@@ -1,4 +1,5 @@
 use crate::error::{AppError, AppResult};
+use crate::services::key_format::{KeyFormatService, PrivateKeyFormat};
 use crate::types::{KeyGenerationParams, SshKeyPair, SshKeyType};
 use chrono::Utc;
 use sha2::{Digest, Sha256};
@@ -81,6 +82,15 @@ impl SshKeyService {
             }
         };
 
+        // 如果提供了密码，使用 bcrypt-pbkdf + aes256-ctr 加密私钥（OpenSSH 标准格式）
+        let passphrase = params.passphrase.as_deref().filter(|p| !p.is_empty());
+        let private_key = match passphrase {
+            Some(passphrase) => private_key
+                .encrypt(&mut rng, passphrase)
+                .map_err(|e| AppError::KeyGenerationError(format!("私钥加密失败: {}", e)))?,
+            None => private_key,
+        };
+
         // 生成私钥（OpenSSH格式）
         let private_key_pem: Zeroizing<String> = private_key
             .to_openssh(LineEnding::LF)
@@ -104,9 +114,224 @@ impl SshKeyService {
             fingerprint,
             created_at: Utc::now(),
             last_used: None,
+            passphrase_protected: private_key.is_encrypted(),
+            tags: Vec::new(),
+            notes: String::new(),
+            expires_at: None,
+        })
+    }
+
+    /// 修改 OpenSSH 格式私钥的密码：用旧密码解密后，使用新密码重新加密。
+    /// `new_passphrase` 为空时移除密码保护。返回新的私钥文本及其是否受密码保护。
+    pub fn change_passphrase(
+        private_key: &str,
+        old_passphrase: Option<&str>,
+        new_passphrase: Option<&str>,
+    ) -> AppResult<(String, bool)> {
+        let mut rng = OsCryptoRng;
+
+        let decrypted =
+            Self::unlock_private_key(private_key, old_passphrase).map_err(|e| match e {
+                AppError::PassphraseRequired => AppError::InvalidPassphrase,
+                other => other,
+            })?;
+
+        let reencrypted = match new_passphrase.filter(|p| !p.is_empty()) {
+            Some(passphrase) => decrypted
+                .encrypt(&mut rng, passphrase)
+                .map_err(|e| AppError::KeyGenerationError(format!("私钥加密失败: {}", e)))?,
+            None => decrypted,
+        };
+
+        let private_key_pem = reencrypted
+            .to_openssh(LineEnding::LF)
+            .map_err(|e| AppError::KeyGenerationError(format!("私钥格式转换失败: {}", e)))?;
+
+        Ok((private_key_pem.to_string(), reencrypted.is_encrypted()))
+    }
+
+    /// 解析 OpenSSH 格式私钥，受密码保护时用给定密码解密
+    pub fn unlock_private_key(
+        private_key: &str,
+        passphrase: Option<&str>,
+    ) -> AppResult<PrivateKey> {
+        let private_key = PrivateKey::from_openssh(private_key)
+            .map_err(|e| AppError::KeyGenerationError(format!("私钥解析失败: {}", e)))?;
+
+        if !private_key.is_encrypted() {
+            return Ok(private_key);
+        }
+
+        let passphrase = passphrase
+            .filter(|p| !p.is_empty())
+            .ok_or(AppError::PassphraseRequired)?;
+        private_key
+            .decrypt(passphrase)
+            .map_err(|_| AppError::InvalidPassphrase)
+    }
+
+    /// 导入现有私钥（可附带同名 .pub 公钥内容），解析出类型、长度、注释与指纹。
+    /// 支持 OpenSSH、PKCS#1、SEC1 与 PKCS#8 格式；受密码保护的私钥需要提供密码以校验，
+    /// 入库时统一转换为 OpenSSH 格式并保持加密状态。
+    pub fn import_private_key(
+        name: &str,
+        private_key: &str,
+        public_key: Option<&str>,
+        passphrase: Option<&str>,
+    ) -> AppResult<SshKeyPair> {
+        match KeyFormatService::detect_format(private_key) {
+            Some(PrivateKeyFormat::OpenSsh) | None => {}
+            Some(_) => {
+                return Self::import_converted_private_key(
+                    name,
+                    private_key,
+                    public_key,
+                    passphrase,
+                )
+            }
+        }
+
+        let private_key = PrivateKey::from_openssh(private_key)
+            .map_err(|e| AppError::KeyGenerationError(format!("私钥解析失败: {}", e)))?;
+
+        // 加密私钥的注释位于加密部分，需要解密后才能读取
+        let comment = if private_key.is_encrypted() {
+            let passphrase = passphrase
+                .filter(|p| !p.is_empty())
+                .ok_or(AppError::PassphraseRequired)?;
+            let decrypted = private_key
+                .decrypt(passphrase)
+                .map_err(|_| AppError::InvalidPassphrase)?;
+            decrypted.comment().as_str_lossy().to_string()
+        } else {
+            private_key.comment().as_str_lossy().to_string()
+        };
+
+        let private_key_pem = private_key
+            .to_openssh(LineEnding::LF)
+            .map_err(|e| AppError::KeyGenerationError(format!("私钥格式转换失败: {}", e)))?;
+
+        Self::build_imported_key_pair(
+            name,
+            private_key.public_key(),
+            comment,
+            public_key,
+            private_key_pem.to_string(),
+            private_key.is_encrypted(),
+        )
+    }
+
+    /// 导入 PKCS#1 / SEC1 / PKCS#8 PEM 或 PuTTY PPK 私钥，加密的私钥以相同密码重新加密为 OpenSSH 格式
+    fn import_converted_private_key(
+        name: &str,
+        private_key: &str,
+        public_key: Option<&str>,
+        passphrase: Option<&str>,
+    ) -> AppResult<SshKeyPair> {
+        let mut rng = OsCryptoRng;
+
+        let (private_key, encrypted) = match KeyFormatService::detect_format(private_key) {
+            Some(PrivateKeyFormat::Ppk) => {
+                KeyFormatService::parse_ppk_private_key(private_key, passphrase)?
+            }
+            _ => KeyFormatService::parse_pem_private_key(private_key, passphrase)?,
+        };
+
+        let stored = match passphrase.filter(|p| encrypted && !p.is_empty()) {
+            Some(passphrase) => private_key
+                .encrypt(&mut rng, passphrase)
+                .map_err(|e| AppError::KeyGenerationError(format!("私钥加密失败: {}", e)))?,
+            None => private_key.clone(),
+        };
+
+        let private_key_pem = stored
+            .to_openssh(LineEnding::LF)
+            .map_err(|e| AppError::KeyGenerationError(format!("私钥格式转换失败: {}", e)))?;
+
+        Self::build_imported_key_pair(
+            name,
+            private_key.public_key(),
+            private_key.comment().as_str_lossy().to_string(),
+            public_key,
+            private_key_pem.to_string(),
+            stored.is_encrypted(),
+        )
+    }
+
+    /// 根据公钥信息组装导入的密钥记录；私钥未携带注释时回退到 .pub 文件中的注释
+    fn build_imported_key_pair(
+        name: &str,
+        public_key: &PublicKey,
+        comment: String,
+        public_key_file: Option<&str>,
+        private_key: String,
+        passphrase_protected: bool,
+    ) -> AppResult<SshKeyPair> {
+        let mut comment = comment;
+        if let Some(content) = public_key_file.map(str::trim).filter(|c| !c.is_empty()) {
+            let file_key = PublicKey::from_openssh(content)
+                .map_err(|e| AppError::KeyGenerationError(format!("公钥解析失败: {}", e)))?;
+            if file_key.key_data() != public_key.key_data() {
+                return Err(AppError::KeyGenerationError("公钥与私钥不匹配".to_string()));
+            }
+            if comment.is_empty() {
+                comment = file_key.comment().as_str_lossy().to_string();
+            }
+        }
+
+        let (key_type, key_size) = Self::key_type_and_size(public_key)?;
+
+        let public_key_openssh = PublicKey::new(public_key.key_data().clone(), comment.as_str())
+            .to_openssh()
+            .map_err(|e| AppError::KeyGenerationError(format!("公钥格式转换失败: {}", e)))?;
+
+        let fingerprint = Self::calculate_fingerprint(&public_key_openssh)?;
+
+        Ok(SshKeyPair {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            key_type,
+            key_size,
+            comment,
+            public_key: public_key_openssh,
+            private_key,
+            fingerprint,
+            created_at: Utc::now(),
+            last_used: None,
+            passphrase_protected,
+            tags: Vec::new(),
+            notes: String::new(),
+            expires_at: None,
         })
     }
 
+    /// 从公钥推导密钥类型与长度（位）
+    fn key_type_and_size(public_key: &PublicKey) -> AppResult<(SshKeyType, u32)> {
+        let key_data = public_key.key_data();
+        if let Some(rsa) = key_data.rsa() {
+            return Ok((SshKeyType::Rsa, rsa.key_size()));
+        }
+        if key_data.is_ed25519() {
+            return Ok((SshKeyType::Ed25519, 256));
+        }
+        if let Some(ecdsa) = key_data.ecdsa() {
+            let size = match ecdsa.curve() {
+                EcdsaCurve::NistP256 => 256,
+                EcdsaCurve::NistP384 => 384,
+                EcdsaCurve::NistP521 => 521,
+            };
+            return Ok((SshKeyType::Ecdsa, size));
+        }
+        Err(AppError::InvalidKeyType)
+    }
+
+    /// 判断 OpenSSH 格式私钥是否已用密码加密（无法解析时视为未加密）
+    pub fn is_private_key_encrypted(private_key: &str) -> bool {
+        PrivateKey::from_openssh(private_key)
+            .map(|k| k.is_encrypted())
+            .unwrap_or(false)
+    }
+
     /// 计算密钥指纹（SHA256）
     fn calculate_fingerprint(public_key: &str) -> AppResult<String> {
         // 提取base64部分
@@ -119,14 +344,16 @@ impl SshKeyService {
             .decode(parts[1])
             .map_err(|_| AppError::KeyGenerationError("公钥解码失败".to_string()))?;
 
+        Ok(Self::blob_fingerprint(&key_data))
+    }
+
+    /// 按 SSH 线格式的公钥数据计算指纹，与 calculate_fingerprint 结果一致
+    pub fn blob_fingerprint(key_data: &[u8]) -> String {
         let mut hasher = Sha256::new();
-        hasher.update(&key_data);
+        hasher.update(key_data);
         let hash = hasher.finalize();
 
-        Ok(format!(
-            "SHA256:{}",
-            general_purpose::STANDARD.encode(&hash)
-        ))
+        format!("SHA256:{}", general_purpose::STANDARD.encode(&hash))
     }
 }
 
@@ -157,6 +384,7 @@ mod tests {
             .private_key
             .contains("-----END OPENSSH PRIVATE KEY-----"));
         assert!(key_pair.public_key.starts_with("ssh-ed25519 "));
+        assert!(!key_pair.passphrase_protected);
 
         // 验证私钥格式
         println!("Generated private key:\n{}", key_pair.private_key);
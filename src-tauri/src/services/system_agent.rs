@@ -0,0 +1,158 @@
+use crate::error::{AppError, AppResult};
+use crate::services::agent::{
+    put_string, AgentIdentity, WireReader, SSH_AGENTC_REMOVE_IDENTITY,
+    SSH_AGENTC_REQUEST_IDENTITIES, SSH_AGENT_IDENTITIES_ANSWER, SSH_AGENT_SUCCESS,
+};
+use crate::services::SshKeyService;
+use crate::types::{SshKeyPair, SystemAgentKey};
+use std::io::{Read, Write};
+use std::path::Path;
+#[cfg(unix)]
+use std::time::Duration;
+
+pub const SSH_AUTH_SOCK_ENV: &str = "SSH_AUTH_SOCK";
+
+// Windows 上的 OpenSSH 代理使用命名管道，未设置 SSH_AUTH_SOCK 时连接默认管道
+#[cfg(not(unix))]
+const DEFAULT_AGENT_PIPE: &str = r"\\.\pipe\openssh-ssh-agent";
+
+// 代理响应的长度上限，与 OpenSSH 一致
+const MAX_RESPONSE_LEN: usize = 256 * 1024;
+#[cfg(unix)]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[cfg(unix)]
+type AgentStream = std::os::unix::net::UnixStream;
+#[cfg(not(unix))]
+type AgentStream = std::fs::File;
+
+// 系统 ssh-agent 中的一个密钥
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeldIdentity {
+    pub key_blob: Vec<u8>,
+    pub comment: String,
+}
+
+impl HeldIdentity {
+    pub fn fingerprint(&self) -> String {
+        SshKeyService::blob_fingerprint(&self.key_blob)
+    }
+
+    // 按指纹与存储中的密钥对应
+    pub fn describe(&self, vault_keys: &[SshKeyPair]) -> SystemAgentKey {
+        let fingerprint = self.fingerprint();
+        let vault_key = vault_keys.iter().find(|key| key.fingerprint == fingerprint);
+        let algorithm = WireReader::new(&self.key_blob)
+            .read_string()
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+            .unwrap_or_default();
+
+        SystemAgentKey {
+            fingerprint,
+            algorithm,
+            comment: self.comment.clone(),
+            key_id: vault_key.map(|key| key.id.clone()),
+            key_name: vault_key.map(|key| key.name.clone()),
+        }
+    }
+}
+
+// 连接已有 ssh-agent 的客户端，用于向系统代理添加、列出与删除密钥，私钥只经 socket 传给代理
+pub struct SystemAgentClient {
+    stream: AgentStream,
+}
+
+impl SystemAgentClient {
+    // 连接 SSH_AUTH_SOCK 指向的代理
+    pub fn connect_default() -> AppResult<Self> {
+        match std::env::var_os(SSH_AUTH_SOCK_ENV).filter(|path| !path.is_empty()) {
+            Some(path) => Self::connect(Path::new(&path)),
+            #[cfg(not(unix))]
+            None => Self::connect(Path::new(DEFAULT_AGENT_PIPE)),
+            #[cfg(unix)]
+            None => Err(AppError::AgentError(
+                "未设置 SSH_AUTH_SOCK，系统 ssh-agent 可能未运行".to_string(),
+            )),
+        }
+    }
+
+    pub fn connect(path: &Path) -> AppResult<Self> {
+        let connect_error =
+            |e: std::io::Error| AppError::AgentError(format!("无法连接 {}: {}", path.display(), e));
+
+        #[cfg(unix)]
+        let stream = {
+            let stream = AgentStream::connect(path).map_err(connect_error)?;
+            stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+            stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+            stream
+        };
+        #[cfg(not(unix))]
+        let stream = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(connect_error)?;
+
+        Ok(Self { stream })
+    }
+
+    pub fn list_identities(&mut self) -> AppResult<Vec<HeldIdentity>> {
+        let response = self.request(&[SSH_AGENTC_REQUEST_IDENTITIES])?;
+        let invalid = || AppError::AgentError("代理返回的密钥列表无效".to_string());
+
+        let mut reader = WireReader::new(&response);
+        if reader.read_u8() != Some(SSH_AGENT_IDENTITIES_ANSWER) {
+            return Err(invalid());
+        }
+        let count = reader.read_u32().ok_or_else(invalid)?;
+        (0..count)
+            .map(|_| {
+                let key_blob = reader.read_string().ok_or_else(invalid)?.to_vec();
+                let comment = reader.read_string().ok_or_else(invalid)?;
+                Ok(HeldIdentity {
+                    key_blob,
+                    comment: String::from_utf8_lossy(comment).into_owned(),
+                })
+            })
+            .collect()
+    }
+
+    // 代理中已有同一密钥时，OpenSSH 会以新的注释与限制替换
+    pub fn add_identity(&mut self, identity: &AgentIdentity) -> AppResult<()> {
+        let request = zeroize::Zeroizing::new(identity.add_identity_request()?);
+        if !self.request(&request)?.starts_with(&[SSH_AGENT_SUCCESS]) {
+            return Err(AppError::AgentError(format!(
+                "代理拒绝添加密钥 {}",
+                identity.key_name()
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn remove_identity(&mut self, key_blob: &[u8]) -> AppResult<()> {
+        let mut request = vec![SSH_AGENTC_REMOVE_IDENTITY];
+        put_string(&mut request, key_blob);
+        if !self.request(&request)?.starts_with(&[SSH_AGENT_SUCCESS]) {
+            return Err(AppError::AgentError("代理拒绝删除密钥".to_string()));
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, message: &[u8]) -> AppResult<Vec<u8>> {
+        self.stream
+            .write_all(&(message.len() as u32).to_be_bytes())?;
+        self.stream.write_all(message)?;
+        self.stream.flush()?;
+
+        let mut len = [0u8; 4];
+        self.stream.read_exact(&mut len)?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len == 0 || len > MAX_RESPONSE_LEN {
+            return Err(AppError::AgentError("代理响应长度无效".to_string()));
+        }
+        let mut response = vec![0u8; len];
+        self.stream.read_exact(&mut response)?;
+        Ok(response)
+    }
+}
@@ -1,31 +1,111 @@
-use std::path::PathBuf;
-use crate::services::crypto::EncryptedData;
+use std::cell::RefCell;
+use std::fs::{File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use crate::services::crypto::{CryptoService, EncryptedData};
 use crate::error::{AppError, AppResult};
+use crate::types::{AuthGuardState, EncryptedStorage, KdfParams, KeySlot, LegacyHeader, VaultBackupInfo, VaultHeader, STORAGE_VERSION};
+
+const AUTHENTICATED_HEADER_VERSION: (u32, u32) = (2, 1);
+
+// 解析 主版本.次版本 形式的存储格式版本
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+// 与初始配置中的 backup_retention 一致
+pub const DEFAULT_BACKUP_RETENTION: usize = 10;
+
+// 快照保存在存储目录下的 backups 子目录，文件名形如 storage.enc.bak.<timestamp>
+const BACKUP_DIR: &str = "backups";
+
+// 多个进程（如两个应用实例）共用同一存储文件时，通过同目录下的 storage.enc.lock 加建议锁；
+// 等待超过 LOCK_TIMEOUT 时放弃
+const LOCK_SUFFIX: &str = ".lock";
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+// 同一进程内可重入：只有最外层的锁真正持有文件锁
+#[derive(Default)]
+struct LockState {
+    file: Option<File>,
+    depth: usize,
+}
+
+// 存储文件锁，离开作用域时释放
+pub struct StorageLock {
+    state: Arc<Mutex<LockState>>,
+}
+
+impl Drop for StorageLock {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.depth -= 1;
+        if state.depth == 0 {
+            if let Some(file) = state.file.take() {
+                let _ = file.unlock();
+            }
+        }
+    }
+}
+
+// 上次读取或写入时存储文件的状态，用于发现其他进程的修改
+#[derive(Clone)]
+struct FileStamp {
+    // 头部与加密数据的摘要，不包括未解锁时也会更新的 auth_guard
+    checksum: String,
+}
 
 pub struct StorageService {
     storage_path: PathBuf,
+    // 每次保存后保留的加密快照数量，None 表示关闭自动备份
+    backup_retention: Option<usize>,
+    lock_state: Arc<Mutex<LockState>>,
+    // None 表示尚未读取，保存时不检查
+    observed: RefCell<Option<FileStamp>>,
+    // 最近一次保存后创建快照失败的原因；数据已保存，快照失败不作为保存失败
+    last_backup_error: Option<String>,
 }
 
 impl StorageService {
 
-    //
-    pub fn new() -> AppResult<Self> {
-        let app_dir = dirs::config_dir()
-            .ok_or_else(|| AppError::ConfigError("无法获取配置目录".to_string()))?
-            .join("sshmanager");
-        
+    // 使用指定的存储文件
+    pub fn with_path(storage_path: PathBuf) -> AppResult<Self> {
         // 确保目录存在
-        std::fs::create_dir_all(&app_dir)?;
-        
-        let storage_path = app_dir.join("storage.enc");
+        if let Some(dir) = storage_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
         
-        Ok(Self { storage_path })
+        Ok(Self {
+            storage_path,
+            backup_retention: Some(DEFAULT_BACKUP_RETENTION),
+            lock_state: Arc::default(),
+            observed: RefCell::new(None),
+            last_backup_error: None,
+        })
     }
     
     // 用于测试的构造函数
     #[cfg(test)]
     pub fn new_for_test(storage_path: PathBuf) -> Self {
-        Self { storage_path }
+        Self {
+            storage_path,
+            backup_retention: Some(DEFAULT_BACKUP_RETENTION),
+            lock_state: Arc::default(),
+            observed: RefCell::new(None),
+            last_backup_error: None,
+        }
+    }
+
+    pub fn last_backup_error(&self) -> Option<&str> {
+        self.last_backup_error.as_deref()
+    }
+
+    // 按解密后的配置（auto_backup、backup_retention）设置自动备份策略
+    pub fn set_backup_policy(&mut self, auto_backup: bool, retention: usize) {
+        self.backup_retention = auto_backup.then_some(retention);
     }
     
     // 返回storage_path
@@ -33,37 +113,136 @@ impl StorageService {
         &self.storage_path
     }
 
+    // 存储文件被重命名或移动后更新路径
+    // 已持有的原路径文件锁在其 StorageLock 释放前保持有效，新路径使用新的锁状态
+    pub fn set_storage_path(&mut self, storage_path: PathBuf) {
+        self.storage_path = storage_path;
+        self.lock_state = Arc::default();
+        self.observed.replace(None);
+    }
+
+    pub fn lock_path(&self) -> PathBuf {
+        Self::sibling_path(&self.storage_path, LOCK_SUFFIX)
+    }
+
+    // 获取存储文件锁；读取-修改-保存的整个过程应持有同一把锁
+    pub fn lock(&self) -> AppResult<StorageLock> {
+        let mut state = self.lock_state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.depth == 0 {
+            if let Some(dir) = self.storage_path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(self.lock_path())?;
+
+            let deadline = Instant::now() + LOCK_TIMEOUT;
+            loop {
+                match file.try_lock() {
+                    Ok(()) => break,
+                    Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                        std::thread::sleep(LOCK_RETRY_INTERVAL);
+                    }
+                    Err(TryLockError::WouldBlock) => return Err(AppError::StorageLocked),
+                    Err(TryLockError::Error(e)) => return Err(e.into()),
+                }
+            }
+            state.file = Some(file);
+        }
+        state.depth += 1;
+        Ok(StorageLock {
+            state: Arc::clone(&self.lock_state),
+        })
+    }
+
+    fn content_checksum(&self, storage_data: &EncryptedStorage) -> String {
+        let header = serde_json::json!({
+            "version": storage_data.version,
+            "salt": storage_data.salt,
+            "master_key_hash": storage_data.master_key_hash,
+            "kdf": storage_data.kdf,
+            "key_slots": storage_data.key_slots,
+            "iv": storage_data.iv,
+            "checksum": storage_data.checksum,
+        });
+        self.calculate_checksum(header.to_string().as_bytes())
+    }
+
+    // 存储文件在上次读取或写入后被其他进程修改时拒绝覆盖。总是比较内容摘要而不是修改时间：
+    // FAT32 等文件系统的时间精度为 2 秒，同一时间片内的另一次写入不会改变修改时间
+    fn ensure_unchanged(&self) -> AppResult<()> {
+        let Some(observed) = self.observed.borrow().clone() else {
+            return Ok(());
+        };
+
+        // 只更新了解锁失败记录时内容摘要不变，继续保存；
+        // 已损坏或不存在的文件中没有可保留的数据，按原有流程覆盖
+        let current = match std::fs::read_to_string(&self.storage_path) {
+            Ok(content) => content,
+            Err(_) => return Ok(()),
+        };
+        match self.parse_storage(&current) {
+            Ok(current) if self.content_checksum(&current) != observed.checksum => {
+                return Err(AppError::StorageChanged);
+            }
+            Err(AppError::UnsupportedVersion(..)) => return Err(AppError::StorageChanged),
+            _ => {}
+        }
+        Ok(())
+    }
+
     
+    // 上一版本存储文件的路径（storage.enc.prev）
+    pub fn prev_path(&self) -> PathBuf {
+        Self::sibling_path(&self.storage_path, ".prev")
+    }
+
     pub fn is_initialized(&self) -> bool {
-        if !self.storage_path.exists() {
-            return false;
+        // 主文件损坏到无法解析时参考上一版本，避免把已有数据误判为未初始化
+        match Self::has_unlock_header(&self.storage_path) {
+            Some(initialized) => initialized,
+            None => Self::has_unlock_header(&self.prev_path()).unwrap_or(false),
         }
-        
-        // 读取文件内容
-        match std::fs::read_to_string(&self.storage_path) {
-            Ok(content) => {
-                // 解析 JSON 数据
-                match serde_json::from_str::<serde_json::Value>(&content) {
-                    Ok(data) => {
-                        // 检查顶层字段中是否存在主密钥哈希字段,且主密钥哈希字段不为空
-                        if let Some(master_key_hash) = data.get("master_key_hash") {
-                            if let Some(hash_str) = master_key_hash.as_str() {
-                                return !hash_str.is_empty();
-                            }
-                        }
-                        false
-                    },
-                    Err(_) => false
-                }
-            },
-            Err(_) => false
+    }
+
+    // 文件中是否存在解锁信息；文件不存在或无法解析时返回 None
+    fn has_unlock_header(path: &Path) -> Option<bool> {
+        // 读取并解析 JSON 数据
+        let content = std::fs::read_to_string(path).ok()?;
+        let data = serde_json::from_str::<serde_json::Value>(&content).ok()?;
+
+        // 2.0 版本：存在至少一个密钥槽
+        if let Some(key_slots) = data.get("key_slots").and_then(|v| v.as_array()) {
+            return Some(!key_slots.is_empty());
+        }
+
+        // 1.x 版本：检查顶层字段中是否存在主密钥哈希字段,且主密钥哈希字段不为空
+        if let Some(hash_str) = data.get("master_key_hash").and_then(|v| v.as_str()) {
+            return Some(!hash_str.is_empty());
         }
+        Some(false)
     }
 
-    pub fn save_encrypted_data(&mut self, encrypted_data: &EncryptedData, salt: &[u8], master_key_hash: &str) -> AppResult<()> {
+    pub fn save_encrypted_data(&mut self, encrypted_data: &EncryptedData, key_slots: &[KeySlot]) -> AppResult<()> {
+        // 没有密钥槽的存储文件将无法解锁
+        if key_slots.is_empty() {
+            return Err(AppError::ConfigError("缺少密钥槽".to_string()));
+        }
+        // 加密数据必须绑定即将写入的头部，否则下次解锁会被判定为篡改
+        if encrypted_data.associated_data.as_deref()
+            != Some(CryptoService::header_associated_data(STORAGE_VERSION, key_slots)?.as_slice())
+        {
+            return Err(AppError::ConfigError("加密数据与密钥槽不匹配".to_string()));
+        }
+
+        let _lock = self.lock()?;
+        self.ensure_unchanged()?;
+
         // 创建存储数据
         let mut data_map = serde_json::Map::new();
-        // 注意：不在data_map中存储salt和master_key_hash，因为它们已经是EncryptedStorage结构体的顶层字段
+        // 注意：不在data_map中存储密钥槽，它们已经是EncryptedStorage结构体的顶层字段
         
         // 创建空的keys数组
         data_map.insert("keys".to_string(), serde_json::Value::Array(vec![]));
@@ -77,11 +256,17 @@ impl StorageService {
         config_map.insert("default_key_size".to_string(), serde_json::Value::Number(256.into()));
         
         data_map.insert("config".to_string(), serde_json::Value::Object(config_map));
+
+        // 保留头部中的解锁失败记录与策略
+        let auth_guard = self.load_auth_guard().unwrap_or_default();
         
-        let storage_data = crate::types::EncryptedStorage {
-            version: "1.0".to_string(),
-            salt: salt.to_vec(),
-            master_key_hash: master_key_hash.to_string(),
+        let storage_data = EncryptedStorage {
+            version: STORAGE_VERSION.to_string(),
+            salt: Vec::new(),
+            master_key_hash: String::new(),
+            kdf: None,
+            key_slots: key_slots.to_vec(),
+            auth_guard,
             iv: encrypted_data.nonce.clone(),
             encrypted_data: encrypted_data.ciphertext.clone(),
             checksum: self.calculate_checksum(&encrypted_data.ciphertext),
@@ -89,47 +274,341 @@ impl StorageService {
         };
         
         let serialized = serde_json::to_string(&storage_data)?;
-        std::fs::write(&self.storage_path, serialized)?;
-        
+        self.write_atomic(serialized.as_bytes())?;
+        self.observed.replace(Some(FileStamp {
+            checksum: self.content_checksum(&storage_data),
+        }));
+
+        // 保存成功后写入加密快照；数据已经写入，快照失败（如磁盘已满）记录下来供界面提示
+        if let Some(retention) = self.backup_retention {
+            self.last_backup_error = self.create_backup(retention).err().map(|e| e.to_string());
+        }
         Ok(())
     }
-    
-    pub fn load_encrypted_data(&self) -> AppResult<(EncryptedData, Vec<u8>, String, [u8; 32])> {
-        let content = std::fs::read_to_string(&self.storage_path)?;
-        let storage_data: crate::types::EncryptedStorage = serde_json::from_str(&content)?;
-        
+
+    pub fn backup_dir(&self) -> PathBuf {
+        self.storage_path
+            .parent()
+            .map(|dir| dir.join(BACKUP_DIR))
+            .unwrap_or_else(|| PathBuf::from(BACKUP_DIR))
+    }
+
+    fn backup_prefix(&self) -> String {
+        let file_name = self
+            .storage_path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("storage.enc");
+        format!("{}.bak.", file_name)
+    }
+
+    // 把当前存储文件复制为带时间戳的快照，并清理超出保留数量的旧快照
+    fn create_backup(&self, retention: usize) -> AppResult<()> {
+        let backup_dir = self.backup_dir();
+        std::fs::create_dir_all(&backup_dir)?;
+
+        // 精确到微秒，同一时刻的多次保存追加序号，避免覆盖（文件名排序仍按时间先后）
+        let ts = chrono::Local::now().format("%Y%m%d%H%M%S%6f");
+        let mut backup_path = backup_dir.join(format!("{}{}", self.backup_prefix(), ts));
+        let mut n = 1;
+        while backup_path.exists() {
+            backup_path = backup_dir.join(format!("{}{}-{}", self.backup_prefix(), ts, n));
+            n += 1;
+        }
+
+        let content = std::fs::read(&self.storage_path)?;
+        Self::write_file_atomic(&backup_path, &content)?;
+        self.enforce_backup_retention(retention)
+    }
+
+    fn enforce_backup_retention(&self, retention: usize) -> AppResult<()> {
+        let mut backups = self.backup_paths()?;
+
+        // 按文件名（包含时间戳）排序，新的在后
+        backups.sort();
+
+        if backups.len() > retention {
+            let to_remove = backups.len() - retention;
+            for old in backups.into_iter().take(to_remove) {
+                let _ = std::fs::remove_file(old);
+            }
+        }
+        Ok(())
+    }
+
+    fn backup_paths(&self) -> AppResult<Vec<PathBuf>> {
+        let backup_dir = self.backup_dir();
+        if !backup_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let prefix = self.backup_prefix();
+        Ok(std::fs::read_dir(&backup_dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|s| s.to_str())
+                    .map(|name| name.starts_with(&prefix) && !name.ends_with(".tmp"))
+                    .unwrap_or(false)
+            })
+            .collect())
+    }
+
+    // 列出所有快照，新的在前
+    pub fn list_backups(&self) -> AppResult<Vec<VaultBackupInfo>> {
+        let mut backups = self.backup_paths()?;
+        backups.sort();
+        backups.reverse();
+
+        let mut infos = Vec::with_capacity(backups.len());
+        for path in backups {
+            let metadata = std::fs::metadata(&path)?;
+            let name = path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string();
+            infos.push(VaultBackupInfo {
+                name,
+                created_at: metadata.modified()?.into(),
+                size: metadata.len(),
+            });
+        }
+        Ok(infos)
+    }
+
+    // 按名称定位快照，只接受 list_backups 返回的文件名
+    fn backup_path(&self, name: &str) -> AppResult<PathBuf> {
+        let valid = name.starts_with(&self.backup_prefix()) && !name.contains(['/', '\\']);
+        let path = self.backup_dir().join(name);
+        if !valid || !path.is_file() {
+            return Err(AppError::ConfigError(format!("快照不存在: {}", name)));
+        }
+        Ok(path)
+    }
+
+    // 读取快照中的加密数据与解锁头部
+    pub fn load_backup(&self, name: &str) -> AppResult<(EncryptedData, VaultHeader)> {
+        let path = self.backup_path(name)?;
+        self.load_from(&path)
+    }
+
+    // 用快照替换当前存储文件；当前内容先保留为快照，解锁失败记录保持不变
+    pub fn restore_backup(&mut self, name: &str) -> AppResult<()> {
+        let path = self.backup_path(name)?;
+        let content = std::fs::read_to_string(&path)?;
+        self.parse_storage(&content)?;
+        let _lock = self.lock()?;
+
+        if let Some(retention) = self.backup_retention {
+            if self.storage_path.exists() {
+                self.create_backup(retention.max(1))?;
+            }
+        }
+
+        let mut storage_data: serde_json::Value = serde_json::from_str(&content)?;
+        if let Ok(auth_guard) = self.load_auth_guard() {
+            if let Some(header) = storage_data.as_object_mut() {
+                header.insert("auth_guard".to_string(), serde_json::to_value(auth_guard)?);
+            }
+        }
+
+        let serialized = serde_json::to_string(&storage_data)?;
+        self.write_atomic(serialized.as_bytes())?;
+        // 恢复后需要重新读取
+        self.observed.replace(None);
+        Ok(())
+    }
+
+    // 原子写入：当前文件完好时先保留为 .prev，再把新内容写入同目录下的临时文件并 fsync，
+    // 最后重命名覆盖存储文件，写入中途崩溃时原文件保持不变
+    fn write_atomic(&self, content: &[u8]) -> AppResult<()> {
+        if let Ok(current) = std::fs::read_to_string(&self.storage_path) {
+            // 已损坏的文件不覆盖 .prev，以免丢失最后一份完好的数据
+            if self.parse_storage(&current).is_ok() {
+                Self::write_file_atomic(&self.prev_path(), current.as_bytes())?;
+            }
+        }
+        Self::write_file_atomic(&self.storage_path, content)
+    }
+
+    fn write_file_atomic(path: &Path, content: &[u8]) -> AppResult<()> {
+        use std::io::Write;
+
+        let tmp_path = Self::sibling_path(path, ".tmp");
+
+        let result = (|| -> AppResult<()> {
+            let mut file = std::fs::File::create(&tmp_path)?;
+            file.write_all(content)?;
+            file.sync_all()?;
+            std::fs::rename(&tmp_path, path)?;
+            Ok(())
+        })();
+
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+            return result;
+        }
+
+        // 同步目录项，确保重命名本身已落盘
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+            if let Ok(dir) = std::fs::File::open(dir) {
+                let _ = dir.sync_all();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+        let mut sibling = path.as_os_str().to_owned();
+        sibling.push(suffix);
+        PathBuf::from(sibling)
+    }
+
+    // 解析存储文件并验证校验和，无法解析或校验失败都视为文件损坏
+    fn parse_storage(&self, content: &str) -> AppResult<EncryptedStorage> {
+        let storage_data: EncryptedStorage =
+            serde_json::from_str(content).map_err(|_| AppError::StorageCorrupted)?;
+        Self::check_storage_version(&storage_data.version)?;
+
         // 验证校验和
         let calculated_checksum = self.calculate_checksum(&storage_data.encrypted_data);
         if calculated_checksum != storage_data.checksum {
-            return Err(AppError::ConfigError("数据完整性验证失败".to_string()));
+            return Err(AppError::StorageCorrupted);
         }
-        
-        // 从顶层字段解析主密码哈希和盐值，而不是从data字段
-        let master_key_hash = storage_data.master_key_hash;
-        
-        // 直接使用顶层的salt字段
-        let salt_vec = storage_data.salt.clone();
+        Ok(storage_data)
+    }
 
-        // 确保盐值长度为32字节
-        if salt_vec.len() != 32 {
-            return Err(AppError::ConfigError("盐值长度不正确".to_string()));
+    // 拒绝由更新版本写入的存储文件（按 主版本.次版本 比较）
+    fn check_storage_version(version: &str) -> AppResult<()> {
+        match parse_version(version) {
+            Some(v) if parse_version(STORAGE_VERSION).is_some_and(|current| v <= current) => Ok(()),
+            _ => Err(AppError::UnsupportedVersion(
+                version.to_string(),
+                STORAGE_VERSION.to_string(),
+            )),
         }
-        
-        let mut salt = [0u8; 32];
-        salt.copy_from_slice(&salt_vec);
-        
-        // 返回加密数据、盐值、主密码哈希
+    }
+
+    // 主文件已损坏且 .prev 完好时可以恢复
+    pub fn can_recover_from_prev(&self) -> bool {
+        let main_corrupted = matches!(
+            std::fs::read_to_string(&self.storage_path)
+                .map_err(AppError::from)
+                .and_then(|content| self.parse_storage(&content)),
+            Err(AppError::StorageCorrupted)
+        );
+        main_corrupted
+            && std::fs::read_to_string(self.prev_path())
+                .map_err(AppError::from)
+                .and_then(|content| self.parse_storage(&content))
+                .is_ok()
+    }
+
+    // 用 .prev 覆盖已损坏的主文件；主文件中仍可读取的解锁失败记录会被保留
+    pub fn recover_from_prev(&mut self) -> AppResult<()> {
+        if !self.can_recover_from_prev() {
+            return Err(AppError::ConfigError("没有可用于恢复的上一版本".to_string()));
+        }
+        let _lock = self.lock()?;
+
+        let prev = std::fs::read_to_string(self.prev_path())?;
+        let mut storage_data: serde_json::Value = serde_json::from_str(&prev)?;
+        if let Ok(auth_guard) = self.load_auth_guard() {
+            if let Some(header) = storage_data.as_object_mut() {
+                header.insert("auth_guard".to_string(), serde_json::to_value(auth_guard)?);
+            }
+        }
+
+        let serialized = serde_json::to_string(&storage_data)?;
+        Self::write_file_atomic(&self.storage_path, serialized.as_bytes())?;
+        self.observed.replace(None);
+        Ok(())
+    }
+    
+    // 读取并记录文件内容摘要，保存前据此判断文件是否被其他进程修改
+    pub fn load_encrypted_data(&self) -> AppResult<(EncryptedData, VaultHeader)> {
+        let content = std::fs::read_to_string(&self.storage_path)?;
+        let storage_data = self.parse_storage(&content)?;
+        self.observed.replace(Some(FileStamp {
+            checksum: self.content_checksum(&storage_data),
+        }));
+        self.split_storage(storage_data)
+    }
+
+    fn load_from(&self, path: &Path) -> AppResult<(EncryptedData, VaultHeader)> {
+        let content = std::fs::read_to_string(path)?;
+        let storage_data = self.parse_storage(&content)?;
+        self.split_storage(storage_data)
+    }
+
+    fn split_storage(&self, storage_data: EncryptedStorage) -> AppResult<(EncryptedData, VaultHeader)> {
+
+        // 2.0 及更早的文件头部未参与认证，按兼容方式解密
+        let associated_data = if !storage_data.key_slots.is_empty()
+            && parse_version(&storage_data.version)
+                .is_some_and(|v| v >= AUTHENTICATED_HEADER_VERSION)
+        {
+            Some(CryptoService::header_associated_data(&storage_data.version, &storage_data.key_slots)?)
+        } else {
+            None
+        };
+
+        let header = if !storage_data.key_slots.is_empty() {
+            VaultHeader::Envelope(storage_data.key_slots)
+        } else {
+            // 1.x 版本：从顶层字段解析主密码哈希和盐值，而不是从data字段
+            let salt: [u8; 32] = storage_data
+                .salt
+                .as_slice()
+                .try_into()
+                .map_err(|_| AppError::ConfigError("盐值长度不正确".to_string()))?;
+
+            VaultHeader::Legacy(LegacyHeader {
+                salt,
+                master_key_hash: storage_data.master_key_hash,
+                kdf: storage_data.kdf.unwrap_or_else(KdfParams::legacy),
+            })
+        };
+
+        // 返回加密数据与头部解锁信息
         Ok((
             EncryptedData {
                 nonce: storage_data.iv,
                 ciphertext: storage_data.encrypted_data,
+                associated_data,
             },
-            salt_vec,
-            master_key_hash,
-            salt,
+            header,
         ))
     }
     
+    // 读取头部中的解锁失败记录（不需要解锁，也不校验加密数据）
+    pub fn load_auth_guard(&self) -> AppResult<AuthGuardState> {
+        let content = std::fs::read_to_string(&self.storage_path)?;
+        let storage_data: serde_json::Value = serde_json::from_str(&content)?;
+        match storage_data.get("auth_guard") {
+            Some(auth_guard) => Ok(serde_json::from_value(auth_guard.clone())?),
+            None => Ok(AuthGuardState::default()),
+        }
+    }
+
+    // 只更新头部中的解锁失败记录，其余内容原样保留
+    pub fn save_auth_guard(&mut self, auth_guard: &AuthGuardState) -> AppResult<()> {
+        let _lock = self.lock()?;
+        let content = std::fs::read_to_string(&self.storage_path)?;
+        let mut storage_data: serde_json::Value = serde_json::from_str(&content)?;
+        let header = storage_data
+            .as_object_mut()
+            .ok_or_else(|| AppError::ConfigError("存储文件格式无效".to_string()))?;
+        header.insert("auth_guard".to_string(), serde_json::to_value(auth_guard)?);
+
+        let serialized = serde_json::to_string(&storage_data)?;
+        self.write_atomic(serialized.as_bytes())
+    }
+
     fn calculate_checksum(&self, data: &[u8]) -> String {
         use sha2::{Digest, Sha256};
         let mut hasher = Sha256::new();
@@ -137,11 +616,67 @@ impl StorageService {
         format!("{:x}", hasher.finalize())
     }
     
-    pub fn reset_storage(&mut self) -> AppResult<()> {
-        // 如果文件存在，则删除它
+    // 把存储文件、上一版本与快照复制到新目录并校验副本，返回新的存储文件路径。
+    // 目标目录中已有存储文件时拒绝，避免覆盖其他存储
+    pub fn copy_to(&self, dir: &Path) -> AppResult<PathBuf> {
+        let file_name = self.storage_path.file_name()
+            .ok_or_else(|| AppError::ConfigError("无法获取存储文件名".to_string()))?;
+        let target = dir.join(file_name);
+        if target.exists() {
+            return Err(AppError::ConfigError(format!("目标目录中已存在存储文件: {}", target.display())));
+        }
+        std::fs::create_dir_all(dir)?;
+        // 复制期间不允许其他进程写入
+        let _lock = self.lock()?;
+
+        let backups = self.backup_paths()?;
+        if !backups.is_empty() {
+            let target_backup_dir = dir.join(BACKUP_DIR);
+            std::fs::create_dir_all(&target_backup_dir)?;
+            for backup in backups {
+                if let Some(name) = backup.file_name() {
+                    Self::write_file_atomic(&target_backup_dir.join(name), &std::fs::read(&backup)?)?;
+                }
+            }
+        }
+        let prev_path = self.prev_path();
+        if prev_path.exists() {
+            Self::write_file_atomic(&Self::sibling_path(&target, ".prev"), &std::fs::read(&prev_path)?)?;
+        }
+
+        // 主文件最后写入，复制中断时目标目录中不会出现不完整的存储
+        let content = std::fs::read(&self.storage_path)?;
+        Self::write_file_atomic(&target, &content)?;
+        let copied = std::fs::read(&target)?;
+        if copied != content {
+            return Err(AppError::StorageCorrupted);
+        }
+        self.parse_storage(&String::from_utf8_lossy(&copied))?;
+        Ok(target)
+    }
+
+    // 删除存储文件、上一版本与全部快照
+    pub fn remove_files(&self) -> AppResult<()> {
         if self.storage_path.exists() {
             std::fs::remove_file(&self.storage_path)?;
         }
+        let prev_path = self.prev_path();
+        if prev_path.exists() {
+            std::fs::remove_file(&prev_path)?;
+        }
+        for backup in self.backup_paths()? {
+            std::fs::remove_file(backup)?;
+        }
+        // 快照目录为空时一并删除
+        let _ = std::fs::remove_dir(self.backup_dir());
+        let _ = std::fs::remove_file(self.lock_path());
+        Ok(())
+    }
+
+    pub fn reset_storage(&mut self) -> AppResult<()> {
+        // 如果文件存在，则删除它（包括上一版本与快照）
+        self.remove_files()?;
+        self.observed.replace(None);
         
         // 重新初始化存储
         let app_dir = self.storage_path.parent()
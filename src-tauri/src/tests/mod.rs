@@ -69,6 +69,118 @@ mod integration_tests {
         }
     }
 
+    #[test]
+    fn test_read_identity_file_with_public_sibling() {
+        use std::fs;
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        fs::write(dir.path().join("id_test"), "PRIVATE").unwrap();
+        fs::write(dir.path().join("id_test.pub"), "PUBLIC").unwrap();
+        fs::write(dir.path().join("id_nopub"), "PRIVATE ONLY").unwrap();
+
+        let (private_key, public_key) =
+            SshConfigService::read_identity_file(dir.path().to_str(), "id_test").unwrap();
+        assert_eq!(private_key, "PRIVATE");
+        assert_eq!(public_key.as_deref(), Some("PUBLIC"));
+
+        let (_, public_key) =
+            SshConfigService::read_identity_file(dir.path().to_str(), "id_nopub").unwrap();
+        assert!(public_key.is_none());
+
+        // 拒绝包含路径的文件名
+        assert!(SshConfigService::read_identity_file(dir.path().to_str(), "../id_test").is_err());
+    }
+
+    #[test]
+    fn test_read_ssh_config_summary_and_ast() {
+        use std::fs;
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let cfg_path = dir.path().join("config");
+        fs::write(dir.path().join("extra"), "Host extra\n  User eve\n").unwrap();
+        fs::write(
+            &cfg_path,
+            "Include extra\nCompression yes\nCompression no\n\nHost web \"db 1\"\n  HostName=10.0.0.2\n  Port 2200\n  IdentityFile ~/.ssh/a\n  IdentityFile ~/.ssh/b\n  LocalForward 80 localhost:8080\n  localforward 443 localhost:8443\n\nMatch host *.internal\n  User ops\n",
+        )
+        .unwrap();
+
+        // 摘要中重复选项取第一个值，IdentityFile 与可重复的选项全部保留，选项名为小写，Match 块不计入
+        let config = SshConfigService::read_config(cfg_path.to_str()).expect("read config");
+        assert_eq!(config.global_settings.get("compression").map(String::as_str), Some("yes"));
+        assert_eq!(config.hosts.len(), 1);
+        let host = &config.hosts[0];
+        assert_eq!(host.host_pattern, "web \"db 1\"");
+        assert_eq!(host.hostname.as_deref(), Some("10.0.0.2"));
+        assert_eq!(host.port, Some(2200));
+        assert_eq!(host.identity_file.as_deref(), Some("~/.ssh/a"));
+        assert_eq!(host.identity_files, ["~/.ssh/a", "~/.ssh/b"]);
+        assert_eq!(host.other_options.get("localforward").map(String::as_str), Some("80 localhost:8080"));
+        assert_eq!(
+            host.repeated_options["localforward"],
+            ["80 localhost:8080", "443 localhost:8443"]
+        );
+
+        // 语法树包含 Match 块与展开的 Include
+        let ast = SshConfigService::read_config_ast(cfg_path.to_str()).expect("read ast");
+        assert_eq!(ast.sections.len(), 2);
+        assert_eq!(ast.global[0].includes.len(), 1);
+        assert_eq!(ast.global[0].includes[0].sections[0].directives()[0].args, ["eve"]);
+
+        // 语法错误带行号
+        fs::write(&cfg_path, "Host a\n  ProxyCommand \"unterminated\n").unwrap();
+        let err = SshConfigService::read_config(cfg_path.to_str()).unwrap_err();
+        assert!(err.to_string().contains("第 2 行"), "{}", err);
+
+        // 文件不存在时为空配置
+        let missing = dir.path().join("missing");
+        assert!(SshConfigService::read_config_ast(missing.to_str()).unwrap().sections.is_empty());
+    }
+
+    #[test]
+    fn test_edit_ssh_config_preserves_formatting() {
+        use crate::types::{SshConfigEdit, SshDirectiveUpdate};
+        use std::fs;
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let cfg_path = dir.path().join("config");
+        let original = "# 我的配置\r\nHost a\r\n\tHostName a.example.com   # 注释\r\n\r\nHost b\r\n\tUser bob\r\n";
+        fs::write(&cfg_path, original).unwrap();
+
+        let edits = [
+            SshConfigEdit::SetDirective {
+                host: Some("b".to_string()),
+                directive: SshDirectiveUpdate { keyword: "Port".to_string(), values: vec!["2222".to_string()] },
+            },
+            SshConfigEdit::ReorderHosts { order: vec!["b".to_string(), "a".to_string()] },
+        ];
+        let updated = SshConfigService::edit_config(cfg_path.to_str(), &edits, Some(5)).expect("edit config");
+        assert_eq!(
+            updated,
+            "Host b\r\n\tUser bob\r\n\tPort 2222\r\n\r\n# 我的配置\r\nHost a\r\n\tHostName a.example.com   # 注释\r\n\r\n"
+        );
+        assert_eq!(fs::read_to_string(&cfg_path).unwrap(), updated);
+        let backups = || {
+            fs::read_dir(dir.path())
+                .unwrap()
+                .filter(|e| e.as_ref().unwrap().file_name().to_string_lossy().starts_with("config.bak."))
+                .count()
+        };
+        assert_eq!(backups(), 1);
+
+        // 没有变化时不写入，修改失败时文件保持不变
+        SshConfigService::edit_config(cfg_path.to_str(), &[], Some(5)).unwrap();
+        let bad = [
+            SshConfigEdit::RemoveHost { host: "a".to_string() },
+            SshConfigEdit::RemoveHost { host: "missing".to_string() },
+        ];
+        assert!(SshConfigService::edit_config(cfg_path.to_str(), &bad, Some(5)).is_err());
+        assert_eq!(fs::read_to_string(&cfg_path).unwrap(), updated);
+        assert_eq!(backups(), 1);
+    }
+
     #[test]
     fn test_save_ssh_config_with_backups_and_retention() {
         use std::fs;
@@ -118,4 +230,526 @@ mod integration_tests {
             v
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_storage_records_kdf_params() {
+        use crate::storage::StorageService;
+        use crate::types::STORAGE_VERSION;
+        use crate::types::{KdfParams, VaultHeader};
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let mut storage = StorageService::new_for_test(dir.path().join("storage.enc"));
+
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("vault_password").unwrap();
+        let encrypted = crypto_service.encrypt(b"{\"keys\":[]}").unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto_service.key_slots())
+            .unwrap();
+
+        let raw: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(storage.storage_path()).unwrap()).unwrap();
+        assert_eq!(raw["version"], STORAGE_VERSION);
+        assert_eq!(raw["key_slots"][0]["kind"], "password");
+        assert_eq!(raw["key_slots"][0]["kdf"]["type"], "argon2id");
+        assert!(raw.get("master_key_hash").is_none());
+        assert!(storage.is_initialized());
+
+        let (_, header) = storage.load_encrypted_data().unwrap();
+        match header {
+            VaultHeader::Envelope(slots) => {
+                assert_eq!(slots.len(), 1);
+                assert_eq!(slots[0].kdf, KdfParams::default());
+            }
+            VaultHeader::Legacy(_) => panic!("expected key slots"),
+        }
+    }
+
+    #[test]
+    fn test_auth_guard_survives_vault_rewrite() {
+        use crate::storage::StorageService;
+        use crate::types::{FailedAttempt, LockoutAction, LockoutPolicy};
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let mut storage = StorageService::new_for_test(dir.path().join("storage.enc"));
+
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("vault_password").unwrap();
+        let encrypted = crypto_service.encrypt(b"{\"keys\":[]}").unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto_service.key_slots())
+            .unwrap();
+        assert_eq!(storage.load_auth_guard().unwrap(), Default::default());
+
+        let mut guard = storage.load_auth_guard().unwrap();
+        guard.consecutive_failures = 4;
+        guard.retry_after = Some(chrono::Utc::now());
+        guard.failed_attempts.push(FailedAttempt {
+            at: chrono::Utc::now(),
+            source: "authenticate".to_string(),
+        });
+        guard.lockout_policy = Some(LockoutPolicy {
+            max_failures: 10,
+            action: LockoutAction::Lock,
+        });
+        storage.save_auth_guard(&guard).unwrap();
+
+        // 重写存储数据不会丢失失败记录与策略，解锁头部也保持不变
+        let encrypted = crypto_service.encrypt(b"{\"keys\":[1]}").unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto_service.key_slots())
+            .unwrap();
+        assert_eq!(storage.load_auth_guard().unwrap(), guard);
+        let (encrypted_data, header) = storage.load_encrypted_data().unwrap();
+        let mut reloaded = CryptoService::new();
+        reloaded.set_header(header);
+        assert!(reloaded.unlock("vault_password"));
+        assert_eq!(reloaded.decrypt(&encrypted_data).unwrap(), b"{\"keys\":[1]}");
+    }
+
+    #[test]
+    fn test_storage_recovers_from_prev_after_corruption() {
+        use crate::error::AppError;
+        use crate::storage::StorageService;
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("storage.enc");
+        let mut storage = StorageService::new_for_test(path.clone());
+
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("vault_password").unwrap();
+        let first = crypto_service.encrypt(b"{\"keys\":[1]}").unwrap();
+        storage
+            .save_encrypted_data(&first, crypto_service.key_slots())
+            .unwrap();
+        assert!(!storage.prev_path().exists());
+
+        // 第二次写入时保留上一版本
+        let second = crypto_service.encrypt(b"{\"keys\":[2]}").unwrap();
+        storage
+            .save_encrypted_data(&second, crypto_service.key_slots())
+            .unwrap();
+        assert!(storage.prev_path().exists());
+        assert!(!dir.path().join("storage.enc.tmp").exists());
+        assert!(!storage.can_recover_from_prev());
+        assert!(storage.recover_from_prev().is_err());
+
+        // 主文件被截断：校验失败，但仍判定为已初始化并可从 .prev 恢复
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, &content[..content.len() / 2]).unwrap();
+        assert!(matches!(
+            storage.load_encrypted_data(),
+            Err(AppError::StorageCorrupted)
+        ));
+        assert!(storage.is_initialized());
+        assert!(storage.can_recover_from_prev());
+
+        // 损坏的主文件不会覆盖 .prev
+        let third = crypto_service.encrypt(b"{\"keys\":[3]}").unwrap();
+        let prev_before = std::fs::read(storage.prev_path()).unwrap();
+        storage
+            .save_encrypted_data(&third, crypto_service.key_slots())
+            .unwrap();
+        assert_eq!(std::fs::read(storage.prev_path()).unwrap(), prev_before);
+
+        std::fs::write(&path, &content[..content.len() / 2]).unwrap();
+        storage.recover_from_prev().unwrap();
+        let (encrypted_data, header) = storage.load_encrypted_data().unwrap();
+        let mut reloaded = CryptoService::new();
+        reloaded.set_header(header);
+        assert!(reloaded.unlock("vault_password"));
+        assert_eq!(reloaded.decrypt(&encrypted_data).unwrap(), b"{\"keys\":[1]}");
+
+        // 重置时一并删除上一版本
+        storage.reset_storage().unwrap();
+        assert!(!storage.prev_path().exists());
+        assert!(!storage.is_initialized());
+    }
+
+    #[test]
+    fn test_vault_backups_rotate_and_restore() {
+        use crate::storage::StorageService;
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let mut storage = StorageService::new_for_test(dir.path().join("storage.enc"));
+        storage.set_backup_policy(true, 3);
+
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("vault_password").unwrap();
+        for i in 0..5 {
+            let encrypted = crypto_service
+                .encrypt(format!("{{\"keys\":[{}]}}", i).as_bytes())
+                .unwrap();
+            storage
+                .save_encrypted_data(&encrypted, crypto_service.key_slots())
+                .unwrap();
+        }
+
+        // 只保留最近 3 个快照，新的在前
+        let backups = storage.list_backups().unwrap();
+        assert_eq!(backups.len(), 3);
+        assert!(backups[0].name > backups[1].name);
+        let decrypt_backup = |storage: &StorageService, name: &str| {
+            let (encrypted_data, header) = storage.load_backup(name).unwrap();
+            let mut reloaded = CryptoService::new();
+            reloaded.set_header(header);
+            assert!(reloaded.unlock("vault_password"));
+            reloaded.decrypt(&encrypted_data).unwrap()
+        };
+        assert_eq!(decrypt_backup(&storage, &backups[0].name), b"{\"keys\":[4]}");
+        assert_eq!(decrypt_backup(&storage, &backups[2].name), b"{\"keys\":[2]}");
+        assert!(storage.load_backup("../storage.enc").is_err());
+
+        // 恢复后当前内容也保留为快照
+        storage.restore_backup(&backups[2].name).unwrap();
+        let (encrypted_data, header) = storage.load_encrypted_data().unwrap();
+        let mut reloaded = CryptoService::new();
+        reloaded.set_header(header);
+        assert!(reloaded.unlock("vault_password"));
+        assert_eq!(reloaded.decrypt(&encrypted_data).unwrap(), b"{\"keys\":[2]}");
+        let latest = &storage.list_backups().unwrap()[0];
+        assert_eq!(decrypt_backup(&storage, &latest.name), b"{\"keys\":[4]}");
+
+        // 关闭自动备份后不再产生快照；重置时删除全部快照
+        storage.set_backup_policy(false, 3);
+        let encrypted = crypto_service.encrypt(b"{\"keys\":[]}").unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto_service.key_slots())
+            .unwrap();
+        assert_eq!(storage.list_backups().unwrap()[0].name, latest.name);
+        storage.reset_storage().unwrap();
+        assert!(storage.list_backups().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_vault_save_succeeds_when_snapshot_fails() {
+        use crate::storage::StorageService;
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let mut storage = StorageService::new_for_test(dir.path().join("storage.enc"));
+        storage.set_backup_policy(true, 3);
+        // 快照目录位置被普通文件占用，无法创建快照
+        std::fs::write(storage.backup_dir(), b"").unwrap();
+
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("vault_password").unwrap();
+        let encrypted = crypto_service.encrypt(b"{\"keys\":[]}").unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto_service.key_slots())
+            .unwrap();
+
+        let (encrypted_data, _) = storage.load_encrypted_data().unwrap();
+        assert_eq!(crypto_service.decrypt(&encrypted_data).unwrap(), b"{\"keys\":[]}");
+
+        // 快照失败的原因保留供界面提示，之后快照成功时清除
+        assert!(storage.last_backup_error().is_some());
+        std::fs::remove_file(storage.backup_dir()).unwrap();
+        storage
+            .save_encrypted_data(&encrypted, crypto_service.key_slots())
+            .unwrap();
+        assert_eq!(storage.last_backup_error(), None);
+        assert_eq!(storage.list_backups().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_legacy_storage_without_kdf_uses_pbkdf2() {
+        use crate::storage::StorageService;
+        use crate::types::{KdfParams, VaultHeader};
+        use sha2::{Digest, Sha256};
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("storage.enc");
+
+        // 构造 1.0 版本存储文件：头部没有 kdf 字段，密钥由 PBKDF2 派生
+        let password = "legacy_password";
+        let salt = CryptoService::generate_salt();
+        let legacy = KdfParams::legacy();
+        let encrypted =
+            CryptoService::encrypt_with_password(password, &salt, &legacy, b"{\"keys\":[]}").unwrap();
+        let key = CryptoService::derive_key_with_params(password, &salt, &legacy).unwrap();
+        let mut hasher = Sha256::new();
+        hasher.update(key);
+        hasher.update(salt);
+        let checksum = format!("{:x}", Sha256::digest(&encrypted.ciphertext));
+        let legacy_json = serde_json::json!({
+            "version": "1.0",
+            "salt": salt.to_vec(),
+            "master_key_hash": format!("{:x}", hasher.finalize()),
+            "iv": encrypted.nonce,
+            "encrypted_data": encrypted.ciphertext,
+            "checksum": checksum,
+        });
+        std::fs::write(&path, legacy_json.to_string()).unwrap();
+
+        let storage = StorageService::new_for_test(path);
+        assert!(storage.is_initialized());
+        let (encrypted_data, header) = storage.load_encrypted_data().unwrap();
+        match &header {
+            VaultHeader::Legacy(legacy_header) => assert_eq!(legacy_header.kdf, legacy),
+            VaultHeader::Envelope(_) => panic!("expected legacy header"),
+        }
+
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_header(header);
+        assert!(crypto_service.is_legacy());
+        assert!(crypto_service.needs_upgrade());
+        assert!(crypto_service.verify_password(password));
+        assert!(!crypto_service.unlock("wrong_password"));
+
+        assert!(crypto_service.unlock(password));
+        let plaintext = crypto_service.decrypt(&encrypted_data).unwrap();
+        assert_eq!(plaintext, b"{\"keys\":[]}");
+    }
+
+    #[test]
+    fn test_storage_written_by_newer_version_is_refused() {
+        use crate::error::AppError;
+        use crate::storage::StorageService;
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("storage.enc");
+        let mut storage = StorageService::new_for_test(path.clone());
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("vault_password").unwrap();
+        let encrypted = crypto_service.encrypt(b"{\"keys\":[]}").unwrap();
+        storage.save_encrypted_data(&encrypted, crypto_service.key_slots()).unwrap();
+        assert!(storage.load_encrypted_data().is_ok());
+
+        let mut header: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        header["version"] = serde_json::Value::String("3.0".to_string());
+        std::fs::write(&path, header.to_string()).unwrap();
+
+        assert!(matches!(
+            storage.load_encrypted_data(),
+            Err(AppError::UnsupportedVersion(version, _)) if version == "3.0"
+        ));
+        // 不是损坏，不应提供从 .prev 恢复
+        assert!(!storage.can_recover_from_prev());
+    }
+
+    #[test]
+    fn test_header_is_bound_to_ciphertext() {
+        use crate::storage::StorageService;
+        use sha2::{Digest, Sha256};
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("storage.enc");
+        let mut storage = StorageService::new_for_test(path.clone());
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("vault_password").unwrap();
+        let recovery_key = CryptoService::generate_recovery_key();
+        let key_slots = crypto_service.rewrap_recovery_key_slot(&recovery_key).unwrap();
+        let encrypted = crypto_service
+            .encrypt_for_slots(b"{\"keys\":[]}", &key_slots)
+            .unwrap();
+        storage.save_encrypted_data(&encrypted, &key_slots).unwrap();
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        let decrypt_after = |tamper: &dyn Fn(&mut serde_json::Value)| {
+            let mut header: serde_json::Value = serde_json::from_str(&original).unwrap();
+            tamper(&mut header);
+            std::fs::write(&path, header.to_string()).unwrap();
+            let (encrypted_data, header) = storage.load_encrypted_data().unwrap();
+            let mut reloaded = CryptoService::new();
+            reloaded.set_header(header);
+            assert!(reloaded.unlock("vault_password"));
+            reloaded.decrypt(&encrypted_data)
+        };
+
+        assert!(decrypt_after(&|_| {}).is_ok());
+        // 解锁失败记录不参与认证
+        assert!(decrypt_after(&|h| h["auth_guard"]["consecutive_failures"] = 3.into()).is_ok());
+        // 删除恢复密钥槽、回退版本号都会导致解密失败
+        assert!(decrypt_after(&|h| {
+            h["key_slots"].as_array_mut().unwrap().pop();
+        })
+        .is_err());
+        assert!(decrypt_after(&|h| h["version"] = "2.0".into()).is_err());
+
+        // 兼容路径：2.0 文件的头部未参与认证，仍可解密
+        let unbound = crypto_service
+            .encrypt_for_slots(b"{\"keys\":[2]}", &[])
+            .unwrap();
+        let legacy = serde_json::json!({
+            "version": "2.0",
+            "key_slots": key_slots,
+            "iv": unbound.nonce,
+            "encrypted_data": unbound.ciphertext,
+            "checksum": format!("{:x}", Sha256::digest(&unbound.ciphertext)),
+        });
+        std::fs::write(&path, legacy.to_string()).unwrap();
+        let (encrypted_data, header) = storage.load_encrypted_data().unwrap();
+        assert!(encrypted_data.associated_data.is_none());
+        let mut reloaded = CryptoService::new();
+        reloaded.set_header(header);
+        assert!(reloaded.unlock("vault_password"));
+        assert_eq!(reloaded.decrypt(&encrypted_data).unwrap(), b"{\"keys\":[2]}");
+
+        // 未绑定头部的数据不能以新格式写入
+        assert!(storage.save_encrypted_data(&unbound, &key_slots).is_err());
+    }
+
+    #[test]
+    fn test_move_vault_files_to_new_location() {
+        use crate::profiles::VaultProfiles;
+        use crate::storage::StorageService;
+        use tempfile::tempdir;
+
+        let app_dir = tempdir().expect("create temp dir");
+        let target = tempdir().expect("create temp dir");
+        let mut profiles = VaultProfiles::with_app_dir(app_dir.path().to_path_buf()).unwrap();
+        let path = profiles.create("work").unwrap();
+
+        let mut storage = StorageService::with_path(path.clone()).unwrap();
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("vault_password").unwrap();
+        for i in 0..2 {
+            let encrypted = crypto_service
+                .encrypt(format!("{{\"keys\":[{}]}}", i).as_bytes())
+                .unwrap();
+            storage.save_encrypted_data(&encrypted, crypto_service.key_slots()).unwrap();
+        }
+
+        let new_path = storage.copy_to(&target.path().join("work")).unwrap();
+        assert!(storage.copy_to(&target.path().join("work")).is_err());
+        let copy = StorageService::with_path(new_path.clone()).unwrap();
+        let (encrypted_data, _) = copy.load_encrypted_data().unwrap();
+        assert_eq!(crypto_service.decrypt(&encrypted_data).unwrap(), b"{\"keys\":[1]}");
+        assert!(copy.prev_path().exists());
+        assert_eq!(copy.list_backups().unwrap().len(), 2);
+
+        profiles.relocate("work", target.path().join("work")).unwrap();
+        storage.remove_files().unwrap();
+        std::fs::remove_dir(path.parent().unwrap()).unwrap();
+        assert_eq!(profiles.storage_path("work").unwrap(), new_path);
+
+        // 移动记录持久保存，重命名只更新记录
+        let mut reopened = VaultProfiles::with_app_dir(app_dir.path().to_path_buf()).unwrap();
+        let vaults = reopened.list().unwrap();
+        assert_eq!(vaults.len(), 2);
+        assert_eq!(vaults[1].storage_path, new_path);
+        assert!(vaults[1].is_initialized);
+        assert_eq!(
+            reopened.rename("work", "client").unwrap(),
+            target.path().join("work/storage.enc")
+        );
+
+        // 删除移动过的存储只删除自己的文件
+        std::fs::write(target.path().join("work/notes.txt"), "keep").unwrap();
+        reopened.delete("client").unwrap();
+        assert!(!new_path.exists());
+        assert!(target.path().join("work/notes.txt").exists());
+        assert!(!reopened.exists("client"));
+    }
+
+    #[test]
+    fn test_stale_instance_cannot_overwrite_newer_vault() {
+        use crate::error::AppError;
+        use crate::storage::StorageService;
+        use crate::types::Vault;
+        use crate::vault::VaultRepository;
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("storage.enc");
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("vault_password").unwrap();
+
+        // 两个实例打开同一存储文件
+        let mut first = StorageService::new_for_test(path.clone());
+        let mut second = StorageService::new_for_test(path.clone());
+        VaultRepository::new(&crypto_service, &mut first)
+            .save(&mut Vault::default())
+            .unwrap();
+        let (stale, _) = first.load_encrypted_data().unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        let mut vault = VaultRepository::new(&crypto_service, &mut second).load().unwrap();
+        vault.settings.backup_retention = 3;
+        VaultRepository::new(&crypto_service, &mut second)
+            .save(&mut vault)
+            .unwrap();
+        // 模拟时间精度较粗的文件系统：修改时间不变时仍按内容发现修改
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        // 第一个实例基于旧数据保存时被拒绝，重新读取后可以保存
+        let plaintext = crypto_service.decrypt(&stale).unwrap();
+        let encrypted = crypto_service.encrypt(&plaintext).unwrap();
+        assert!(matches!(
+            first.save_encrypted_data(&encrypted, crypto_service.key_slots()),
+            Err(AppError::StorageChanged)
+        ));
+        let mut repo = VaultRepository::new(&crypto_service, &mut first);
+        assert_eq!(repo.load().unwrap().settings.backup_retention, 3);
+        repo.update_settings(|settings| settings.auto_backup = false).unwrap();
+
+        // 只更新解锁失败记录不算修改
+        let (current, _) = first.load_encrypted_data().unwrap();
+        let guard = second.load_auth_guard().unwrap();
+        second.save_auth_guard(&guard).unwrap();
+        let plaintext = crypto_service.decrypt(&current).unwrap();
+        let encrypted = crypto_service.encrypt(&plaintext).unwrap();
+        first
+            .save_encrypted_data(&encrypted, crypto_service.key_slots())
+            .unwrap();
+
+        // 其他实例修改了密钥槽（如修改主密码）后需要重新解锁
+        let mut rekeyed = CryptoService::new();
+        rekeyed.set_master_key("new_password").unwrap();
+        second.load_encrypted_data().unwrap();
+        VaultRepository::new(&rekeyed, &mut second)
+            .save(&mut Vault::default())
+            .unwrap();
+        assert!(matches!(
+            VaultRepository::new(&crypto_service, &mut first).update_settings(|_| {}),
+            Err(AppError::StorageChanged)
+        ));
+    }
+
+    #[test]
+    fn test_storage_lock_excludes_other_instances() {
+        use crate::storage::StorageService;
+        use std::sync::mpsc;
+        use std::time::Duration;
+        use tempfile::tempdir;
+
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join("storage.enc");
+        let mut first = StorageService::new_for_test(path.clone());
+
+        // 同一实例内可重入
+        let outer = first.lock().unwrap();
+        let inner = first.lock().unwrap();
+        drop(inner);
+
+        // 切换到新路径后使用独立的锁，原路径的锁在释放前仍然有效
+        first.set_storage_path(dir.path().join("moved").join("storage.enc"));
+        drop(first.lock().unwrap());
+
+        let (sender, receiver) = mpsc::channel();
+        let waiter = std::thread::spawn(move || {
+            let second = StorageService::new_for_test(path);
+            let _lock = second.lock().unwrap();
+            sender.send(()).unwrap();
+        });
+
+        // 外层锁释放前其他实例一直等待
+        assert!(receiver.recv_timeout(Duration::from_millis(300)).is_err());
+        drop(outer);
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        waiter.join().unwrap();
+    }
+}
@@ -42,13 +42,324 @@ pub struct SshKeyPair {
     pub fingerprint: String,
     pub created_at: DateTime<Utc>,
     pub last_used: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub passphrase_protected: bool, // 私钥是否受密码保护（OpenSSH 加密格式）
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityImportFailure {
+    pub file_name: String,
+    pub error: String,
+}
+
+// 从 ~/.ssh 导入私钥文件的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdentityImportResult {
+    pub imported: Vec<SshKeyPair>,
+    pub skipped: Vec<String>,             // 指纹已存在而跳过的文件
+    pub passphrase_required: Vec<String>, // 受密码保护但未提供密码的文件
+    pub failed: Vec<IdentityImportFailure>,
+}
+
+// 主密码密钥派生算法及参数（记录在存储文件头部与加密导出文件中）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum KdfParams {
+    #[serde(rename = "pbkdf2-hmac-sha256")]
+    Pbkdf2HmacSha256 { iterations: u32 },
+    #[serde(rename = "argon2id")]
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl KdfParams {
+    // 1.0 版本存储文件使用的 PBKDF2 参数
+    pub fn legacy() -> Self {
+        KdfParams::Pbkdf2HmacSha256 {
+            iterations: 100_000,
+        }
+    }
+}
+
+impl Default for KdfParams {
+    // RFC 9106 推荐的第二组参数：64 MiB 内存、3 轮、4 条并行通道
+    fn default() -> Self {
+        KdfParams::Argon2id {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+// 密钥槽的解锁方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySlotKind {
+    Password,
+    RecoveryKey,
+}
+
+// 密钥槽：由解锁凭据派生的密钥加密密钥（KEK）包装数据加密密钥（DEK）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeySlot {
+    pub kind: KeySlotKind,
+    pub salt: Vec<u8>,
+    pub kdf: KdfParams,
+    pub nonce: Vec<u8>,
+    pub wrapped_key: Vec<u8>,
+    // 该槽的 KEK 由主密码与密钥文件共同派生，解锁时需要密钥文件
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub requires_keyfile: bool,
+}
+
+// 1.x 版本存储文件头部：主密码派生的密钥直接加密数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegacyHeader {
+    pub salt: [u8; 32],
+    pub master_key_hash: String,
+    pub kdf: KdfParams,
+}
+
+// 存储文件头部中的解锁信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VaultHeader {
+    Legacy(LegacyHeader),
+    Envelope(Vec<KeySlot>),
+}
+
+// 连续解锁失败达到阈值后的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockoutAction {
+    Lock, // 禁用密码解锁，只能使用恢复密钥
+    Wipe, // 清除存储文件
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockoutPolicy {
+    pub max_failures: u32,
+    pub action: LockoutAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedAttempt {
+    pub at: DateTime<Utc>,
+    pub source: String, // 发起尝试的命令
+}
+
+// 解锁失败计数、退避状态与失败记录（写在存储文件头部，未解锁时也可更新）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthGuardState {
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    #[serde(default)]
+    pub retry_after: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub locked_out: bool,
+    #[serde(default)]
+    pub failed_attempts: Vec<FailedAttempt>,
+    #[serde(default)]
+    pub lockout_policy: Option<LockoutPolicy>,
+}
+
+// 加密数据中的完整内容：密钥列表、配置与元数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Vault {
+    // 结构版本，保存时总是写入当前版本（见 migrations.rs）
+    #[serde(default)]
+    pub schema_version: u32,
+    #[serde(default)]
+    pub keys: Vec<SshKeyPair>,
+    #[serde(rename = "config", default)]
+    pub settings: VaultSettings,
+    #[serde(default)]
+    pub metadata: VaultMetadata,
+}
+
+// 应用配置（存储中的字段名为 config），缺省字段取初始化时的默认值
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VaultSettings {
+    pub theme: String,
+    pub auto_backup: bool,
+    pub backup_retention: usize,
+    pub default_key_type: SshKeyType,
+    pub default_key_size: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_lock_minutes: Option<u32>,
+}
+
+impl Default for VaultSettings {
+    fn default() -> Self {
+        Self {
+            theme: "light".to_string(),
+            auto_backup: true,
+            backup_retention: 10,
+            default_key_type: SshKeyType::Ed25519,
+            default_key_size: 256,
+            auto_lock_minutes: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+// 存储文件的加密快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultBackupInfo {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+// 快照列表；last_error 为最近一次保存后创建快照失败的原因
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultBackupList {
+    pub backups: Vec<VaultBackupInfo>,
+    pub last_error: Option<String>,
+}
+
+// 命名存储（工作、个人等），各自有独立的文件与主密码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultProfileInfo {
+    pub name: String,
+    pub is_active: bool,
+    pub is_initialized: bool,
+    pub is_unlocked: bool,
+    pub storage_path: std::path::PathBuf,
+}
+
+// 数据目录的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataDirSource {
+    CommandLine, // --data-dir
+    Environment, // SSHMANAGER_DATA_DIR
+    Portable,    // 可执行文件旁的 data 目录
+    Default,     // <config_dir>/sshmanager
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataDirLocation {
+    pub path: std::path::PathBuf,
+    pub source: DataDirSource,
+}
+
+// 内置 ssh-agent 的运行状态；存储锁定后代理自动停止，key_ids 不含已过期或次数用完的密钥
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentStatus {
+    pub running: bool,
+    pub socket_path: Option<std::path::PathBuf>,
+    pub key_ids: Vec<String>,
+}
+
+// 代理中密钥的使用限制，与 ssh-add 的 -c、-t 类似
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentKeyConstraints {
+    // 每次签名前需在应用中确认
+    #[serde(default)]
+    pub confirm: bool,
+    // 加入代理后经过的秒数，到期后移除
+    #[serde(default)]
+    pub lifetime_secs: Option<u64>,
+    // 签名次数上限，用完后移除
+    #[serde(default)]
+    pub max_uses: Option<u32>,
+}
+
+// 启动代理时选择的密钥；受密码保护的私钥需要提供密码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentKeyOptions {
+    pub key_id: String,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    #[serde(default)]
+    pub constraints: AgentKeyConstraints,
+}
+
+// 连接代理的进程，取决于操作系统能提供哪些信息
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentPeerInfo {
+    pub pid: Option<u32>,
+    pub uid: Option<u32>,
+    pub process_name: Option<String>,
+    pub executable: Option<std::path::PathBuf>,
+}
+
+// 等待用户确认的签名请求，通过事件发送给前端
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentConfirmRequest {
+    pub request_id: u64,
+    pub key_id: String,
+    pub key_name: String,
+    pub fingerprint: String,
+    pub peer: Option<AgentPeerInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentSignOutcome {
+    Signed,
+    Denied, // 用户拒绝或确认超时
+    Failed,
+}
+
+// 系统 ssh-agent（SSH_AUTH_SOCK）中的密钥；与存储中的密钥指纹相同时给出对应的密钥
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemAgentKey {
+    pub fingerprint: String,
+    pub algorithm: String,
+    pub comment: String,
+    pub key_id: Option<String>,
+    pub key_name: Option<String>,
+}
+
+// 代理签名记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSignRecord {
+    pub key_id: String,
+    pub key_name: String,
+    pub fingerprint: String,
+    pub signed_at: DateTime<Utc>,
+    pub peer: Option<AgentPeerInfo>,
+    pub outcome: AgentSignOutcome,
+}
+
+// 存储格式版本：1.0 固定使用 PBKDF2；1.1 起在头部记录 KDF 算法与参数；
+// 2.0 起数据由随机数据密钥加密，数据密钥由各密钥槽包装；
+// 2.1 起头部（版本与密钥槽）作为 AES-GCM 关联数据参与认证
+pub const STORAGE_VERSION: &str = "2.1";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EncryptedStorage {
     pub version: String,
+    // salt、master_key_hash、kdf 仅出现在 1.x 版本中
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub salt: Vec<u8>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub master_key_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kdf: Option<KdfParams>, // 1.x 中缺省时为 1.0 版本的 PBKDF2
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub key_slots: Vec<KeySlot>,
+    #[serde(default)]
+    pub auth_guard: AuthGuardState,
     pub iv: Vec<u8>,
     pub encrypted_data: Vec<u8>,
     pub checksum: String,
@@ -56,6 +367,7 @@ pub struct EncryptedStorage {
     pub data: serde_json::Map<String, serde_json::Value>,
 }
 
+// Host 块的常用字段；与 ssh 一致，重复的选项取第一个值，IdentityFile 全部保留在 identity_files 中
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SshHostConfig {
     pub host_pattern: String,
@@ -63,11 +375,99 @@ pub struct SshHostConfig {
     pub user: Option<String>,
     pub port: Option<u16>,
     pub identity_file: Option<String>,
+    #[serde(default)]
+    pub identity_files: Vec<String>,
+    // 键为小写的关键字，值为第一次出现的值
     pub other_options: std::collections::HashMap<String, String>,
+    // 可重复的选项（LocalForward、SendEnv 等）的全部值，键为小写的关键字
+    #[serde(default)]
+    pub repeated_options: std::collections::HashMap<String, Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SshConfig {
     pub hosts: Vec<SshHostConfig>,
     pub global_settings: std::collections::HashMap<String, String>,
+    #[serde(default)]
+    pub global_repeated_options: std::collections::HashMap<String, Vec<String>>,
+}
+
+// 设置一个选项：values 中每个值写成一行（如多个 IdentityFile），为空时删除该选项
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SshDirectiveUpdate {
+    pub keyword: String,
+    pub values: Vec<String>,
+}
+
+// 对 SSH 配置的结构化修改；host 为 Host 行的模式（与 SshHostConfig.host_pattern 相同）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SshConfigEdit {
+    AddHost {
+        patterns: Vec<String>,
+        #[serde(default)]
+        directives: Vec<SshDirectiveUpdate>,
+        index: Option<usize>, // 在第几个 Host 之前插入，缺省时追加到末尾
+    },
+    UpdateHost {
+        host: String,
+        patterns: Option<Vec<String>>,
+        #[serde(default)]
+        directives: Vec<SshDirectiveUpdate>,
+    },
+    RemoveHost {
+        host: String,
+    },
+    // 按给出的顺序排列全部 Host 块，Match 块位置不变
+    ReorderHosts {
+        order: Vec<String>,
+    },
+    // host 缺省时设置全局选项
+    SetDirective {
+        host: Option<String>,
+        #[serde(flatten)]
+        directive: SshDirectiveUpdate,
+    },
+}
+
+// OpenSSH 配置（ssh_config(5)）语法树，行号从 1 开始
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SshConfigAst {
+    pub path: Option<std::path::PathBuf>,
+    pub global: Vec<SshConfigDirective>, // 第一个 Host/Match 之前的选项
+    pub sections: Vec<SshConfigSection>,
+}
+
+// 一条配置：关键字保留原始大小写，参数已去除引号与转义
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SshConfigDirective {
+    pub keyword: String,
+    pub args: Vec<String>,
+    pub line: usize,
+    // Include 展开后的文件，按文件名排序；只在从文件解析时展开
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub includes: Vec<SshConfigAst>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SshConfigSection {
+    Host {
+        patterns: Vec<String>, // 含 ! 前缀的否定模式
+        line: usize,
+        directives: Vec<SshConfigDirective>,
+    },
+    Match {
+        criteria: Vec<SshMatchCriterion>,
+        line: usize,
+        directives: Vec<SshConfigDirective>,
+    },
+}
+
+// Match 条件，keyword 为小写（all、canonical、final 没有参数）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SshMatchCriterion {
+    pub keyword: String,
+    pub negated: bool,
+    pub arg: Option<String>,
 }
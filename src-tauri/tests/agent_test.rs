@@ -0,0 +1,500 @@
+#[cfg(unix)]
+mod tests {
+    use rsa::signature::Verifier;
+    use ssh_key::PublicKey;
+    use ssh_key_manager_lib::services::agent::{
+        ConfirmNotifier, SSH_AGENTC_REQUEST_IDENTITIES, SSH_AGENTC_SIGN_REQUEST, SSH_AGENT_FAILURE,
+        SSH_AGENT_IDENTITIES_ANSWER, SSH_AGENT_RSA_SHA2_256, SSH_AGENT_RSA_SHA2_512,
+        SSH_AGENT_SIGN_RESPONSE,
+    };
+    use ssh_key_manager_lib::services::{
+        AgentIdentity, AgentService, CryptoService, SshKeyService, SystemAgentClient,
+    };
+    use ssh_key_manager_lib::types::{
+        AgentConfirmRequest, AgentKeyConstraints, AgentSignOutcome, KeyGenerationParams,
+        SshKeyPair, SshKeyType,
+    };
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    // 最小的 ssh-agent 协议客户端
+    struct AgentClient {
+        stream: UnixStream,
+    }
+
+    impl AgentClient {
+        fn connect(path: &Path) -> Self {
+            Self {
+                stream: UnixStream::connect(path).expect("connect to agent"),
+            }
+        }
+
+        fn request(&mut self, message: &[u8]) -> Vec<u8> {
+            self.stream
+                .write_all(&(message.len() as u32).to_be_bytes())
+                .unwrap();
+            self.stream.write_all(message).unwrap();
+
+            let mut len = [0u8; 4];
+            self.stream.read_exact(&mut len).unwrap();
+            let mut response = vec![0u8; u32::from_be_bytes(len) as usize];
+            self.stream.read_exact(&mut response).unwrap();
+            response
+        }
+
+        // 返回 (公钥, 注释) 列表
+        fn identities(&mut self) -> Vec<(Vec<u8>, String)> {
+            let response = self.request(&[SSH_AGENTC_REQUEST_IDENTITIES]);
+            assert_eq!(response[0], SSH_AGENT_IDENTITIES_ANSWER);
+            let mut reader = &response[1..];
+            let count = read_u32(&mut reader);
+            (0..count)
+                .map(|_| {
+                    let blob = read_string(&mut reader).to_vec();
+                    let comment = String::from_utf8(read_string(&mut reader).to_vec()).unwrap();
+                    (blob, comment)
+                })
+                .collect()
+        }
+
+        // 成功时返回 (算法名, 签名数据)
+        fn sign(&mut self, key_blob: &[u8], data: &[u8], flags: u32) -> Option<(String, Vec<u8>)> {
+            let mut message = vec![SSH_AGENTC_SIGN_REQUEST];
+            put_string(&mut message, key_blob);
+            put_string(&mut message, data);
+            message.extend_from_slice(&flags.to_be_bytes());
+
+            let response = self.request(&message);
+            if response == [SSH_AGENT_FAILURE] {
+                return None;
+            }
+            assert_eq!(response[0], SSH_AGENT_SIGN_RESPONSE);
+            let mut reader = &response[1..];
+            let mut signature = read_string(&mut reader);
+            let algorithm = String::from_utf8(read_string(&mut signature).to_vec()).unwrap();
+            Some((algorithm, read_string(&mut signature).to_vec()))
+        }
+    }
+
+    fn read_u32(reader: &mut &[u8]) -> u32 {
+        let (head, rest) = reader.split_at(4);
+        *reader = rest;
+        u32::from_be_bytes(head.try_into().unwrap())
+    }
+
+    fn read_string<'a>(reader: &mut &'a [u8]) -> &'a [u8] {
+        let len = read_u32(reader) as usize;
+        let (head, rest) = reader.split_at(len);
+        *reader = rest;
+        head
+    }
+
+    fn put_string(buf: &mut Vec<u8>, value: &[u8]) {
+        buf.extend_from_slice(&(value.len() as u32).to_be_bytes());
+        buf.extend_from_slice(value);
+    }
+
+    fn generate(key_type: SshKeyType, key_size: u32, comment: &str) -> SshKeyPair {
+        SshKeyService::generate_key_pair(KeyGenerationParams {
+            name: format!("{:?}", key_type),
+            key_type,
+            key_size,
+            comment: comment.to_string(),
+            passphrase: None,
+        })
+        .unwrap()
+    }
+
+    // 不需要确认的测试中不会调用
+    fn no_confirm() -> ConfirmNotifier {
+        Arc::new(|_: &AgentConfirmRequest| false)
+    }
+
+    fn key_blob(key: &SshKeyPair) -> Vec<u8> {
+        PublicKey::from_openssh(&key.public_key)
+            .unwrap()
+            .to_bytes()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_agent_lists_and_signs_with_vault_keys() {
+        let dir = tempdir().expect("create temp dir");
+        let socket_path = dir.path().join("agent.sock");
+        let ed25519 = generate(SshKeyType::Ed25519, 256, "ed@example.com");
+        let rsa_key = generate(SshKeyType::Rsa, 2048, "");
+        let unused = generate(SshKeyType::Ed25519, 256, "unused");
+
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("vault_password").unwrap();
+        let mut agent = AgentService::new();
+        let identities = vec![
+            AgentIdentity::from_key_pair(&ed25519, None).unwrap(),
+            AgentIdentity::from_key_pair(&rsa_key, None).unwrap(),
+        ];
+        agent
+            .start(
+                socket_path.clone(),
+                identities,
+                crypto.unlock_token().unwrap(),
+                no_confirm(),
+            )
+            .unwrap();
+        let status = agent.status();
+        assert!(status.running);
+        assert_eq!(status.key_ids, [ed25519.id.clone(), rsa_key.id.clone()]);
+
+        let mut client = AgentClient::connect(&socket_path);
+        let listed = client.identities();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(
+            listed[0],
+            (key_blob(&ed25519), "ed@example.com".to_string())
+        );
+        // 没有注释时使用密钥名称
+        assert_eq!(listed[1], (key_blob(&rsa_key), "Rsa".to_string()));
+
+        let data = b"session-id and userauth request";
+        let (algorithm, signature) = client.sign(&listed[0].0, data, 0).unwrap();
+        assert_eq!(algorithm, "ssh-ed25519");
+        let public = PublicKey::from_bytes(&listed[0].0).unwrap();
+        let verifying_key =
+            ed25519_dalek::VerifyingKey::from_bytes(&public.key_data().ed25519().unwrap().0)
+                .unwrap();
+        verifying_key
+            .verify_strict(
+                data,
+                &ed25519_dalek::Signature::from_slice(&signature).unwrap(),
+            )
+            .unwrap();
+
+        // RSA 按请求的标志选择 SHA-2 算法，未指定时拒绝 SHA-1
+        let public = PublicKey::from_bytes(&listed[1].0).unwrap();
+        let rsa_public = public.key_data().rsa().unwrap();
+        let rsa_public = rsa::RsaPublicKey::new(
+            rsa::BigUint::from_bytes_be(rsa_public.n().as_positive_bytes().unwrap()),
+            rsa::BigUint::from_bytes_be(rsa_public.e().as_positive_bytes().unwrap()),
+        )
+        .unwrap();
+        let (algorithm, signature) = client
+            .sign(&listed[1].0, data, SSH_AGENT_RSA_SHA2_512)
+            .unwrap();
+        assert_eq!(algorithm, "rsa-sha2-512");
+        rsa::pkcs1v15::VerifyingKey::<sha2::Sha512>::new(rsa_public.clone())
+            .verify(
+                data,
+                &rsa::pkcs1v15::Signature::try_from(signature.as_slice()).unwrap(),
+            )
+            .unwrap();
+        let (algorithm, signature) = client
+            .sign(&listed[1].0, data, SSH_AGENT_RSA_SHA2_256)
+            .unwrap();
+        assert_eq!(algorithm, "rsa-sha2-256");
+        rsa::pkcs1v15::VerifyingKey::<sha2::Sha256>::new(rsa_public)
+            .verify(
+                data,
+                &rsa::pkcs1v15::Signature::try_from(signature.as_slice()).unwrap(),
+            )
+            .unwrap();
+        assert!(client.sign(&listed[1].0, data, 0).is_none());
+
+        // 未加入代理的密钥与不支持的请求（如添加密钥）返回失败
+        assert!(client.sign(&key_blob(&unused), data, 0).is_none());
+        assert_eq!(client.request(&[17]), [SSH_AGENT_FAILURE]);
+
+        agent.stop();
+        assert!(!agent.status().running);
+        assert!(!socket_path.exists());
+    }
+
+    #[test]
+    fn test_agent_socket_dir_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir().expect("create temp dir");
+        let key = generate(SshKeyType::Ed25519, 256, "");
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("vault_password").unwrap();
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        // 新建的目录为 0700；已存在的目录去掉组与其他用户的权限
+        let created = dir.path().join("created");
+        let shared = dir.path().join("shared");
+        std::fs::create_dir(&shared).unwrap();
+        std::fs::set_permissions(&shared, std::fs::Permissions::from_mode(0o755)).unwrap();
+        for socket_dir in [created, shared] {
+            let socket_path = socket_dir.join("agent.sock");
+            let mut agent = AgentService::new();
+            agent
+                .start(
+                    socket_path.clone(),
+                    vec![AgentIdentity::from_key_pair(&key, None).unwrap()],
+                    crypto.unlock_token().unwrap(),
+                    no_confirm(),
+                )
+                .unwrap();
+            assert_eq!(mode(&socket_dir), 0o700);
+            assert_eq!(mode(&socket_path), 0o600);
+            assert_eq!(AgentClient::connect(&socket_path).identities().len(), 1);
+            agent.stop();
+        }
+    }
+
+    #[test]
+    fn test_agent_stops_when_vault_locks() {
+        let dir = tempdir().expect("create temp dir");
+        let socket_path = dir.path().join("agent.sock");
+        let key = generate(SshKeyType::Ed25519, 256, "");
+
+        let mut crypto = CryptoService::new();
+        assert!(crypto.unlock_token().is_none());
+        crypto.set_master_key("vault_password").unwrap();
+        let mut agent = AgentService::new();
+        agent
+            .start(
+                socket_path.clone(),
+                vec![AgentIdentity::from_key_pair(&key, None).unwrap()],
+                crypto.unlock_token().unwrap(),
+                no_confirm(),
+            )
+            .unwrap();
+        let mut client = AgentClient::connect(&socket_path);
+        assert_eq!(client.identities().len(), 1);
+
+        // 锁定后已建立的连接不再提供密钥，socket 随后被删除
+        crypto.clear_master_key();
+        assert_eq!(
+            client.request(&[SSH_AGENTC_REQUEST_IDENTITIES]),
+            [SSH_AGENT_FAILURE]
+        );
+        assert!(!agent.status().running);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while socket_path.exists() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(!socket_path.exists());
+        assert!(UnixStream::connect(&socket_path).is_err());
+    }
+
+    #[test]
+    fn test_agent_key_constraints_and_sign_log() {
+        let dir = tempdir().expect("create temp dir");
+        let socket_path = dir.path().join("agent.sock");
+        let confirmed = generate(SshKeyType::Ed25519, 256, "confirmed");
+        let limited = generate(SshKeyType::Ed25519, 256, "limited");
+        let expiring = generate(SshKeyType::Ed25519, 256, "expiring");
+
+        let mut crypto = CryptoService::new();
+        crypto.set_master_key("vault_password").unwrap();
+        let identities = vec![
+            AgentIdentity::from_key_pair(&confirmed, None)
+                .unwrap()
+                .with_constraints(AgentKeyConstraints {
+                    confirm: true,
+                    ..Default::default()
+                }),
+            AgentIdentity::from_key_pair(&limited, None)
+                .unwrap()
+                .with_constraints(AgentKeyConstraints {
+                    max_uses: Some(1),
+                    ..Default::default()
+                }),
+            AgentIdentity::from_key_pair(&expiring, None)
+                .unwrap()
+                .with_constraints(AgentKeyConstraints {
+                    lifetime_secs: Some(1),
+                    ..Default::default()
+                }),
+        ];
+
+        // 确认请求转交给测试线程答复
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let notifier: ConfirmNotifier = Arc::new(move |request: &AgentConfirmRequest| {
+            sender.lock().unwrap().send(request.clone()).is_ok()
+        });
+        let agent = Arc::new(Mutex::new(AgentService::new()));
+        agent
+            .lock()
+            .unwrap()
+            .start(
+                socket_path.clone(),
+                identities,
+                crypto.unlock_token().unwrap(),
+                notifier,
+            )
+            .unwrap();
+        let responder = {
+            let agent = Arc::clone(&agent);
+            std::thread::spawn(move || {
+                let mut requests = Vec::new();
+                for approved in [true, false] {
+                    let request: AgentConfirmRequest = receiver.recv().unwrap();
+                    assert!(agent
+                        .lock()
+                        .unwrap()
+                        .respond_confirmation(request.request_id, approved));
+                    requests.push(request);
+                }
+                requests
+            })
+        };
+
+        let mut client = AgentClient::connect(&socket_path);
+        let data = b"data to sign";
+        assert!(client.sign(&key_blob(&confirmed), data, 0).is_some());
+        assert!(client.sign(&key_blob(&confirmed), data, 0).is_none());
+        let requests = responder.join().unwrap();
+        assert_eq!(requests[0].key_id, confirmed.id);
+        assert_eq!(requests[0].fingerprint, confirmed.fingerprint);
+        assert_ne!(requests[0].request_id, requests[1].request_id);
+        // 答复过的请求不能再次答复
+        assert!(!agent
+            .lock()
+            .unwrap()
+            .respond_confirmation(requests[0].request_id, true));
+
+        // 次数用完后不再列出
+        assert!(client.sign(&key_blob(&limited), data, 0).is_some());
+        assert!(client.sign(&key_blob(&limited), data, 0).is_none());
+        assert_eq!(client.identities().len(), 2);
+
+        // 超过有效期后不再提供
+        std::thread::sleep(Duration::from_millis(1100));
+        assert!(client.sign(&key_blob(&expiring), data, 0).is_none());
+        let listed = client.identities();
+        assert_eq!(listed, [(key_blob(&confirmed), "confirmed".to_string())]);
+        assert_eq!(
+            agent.lock().unwrap().status().key_ids,
+            [confirmed.id.as_str()]
+        );
+
+        let log = agent.lock().unwrap().sign_log();
+        let outcomes: Vec<_> = log
+            .iter()
+            .map(|record| (record.key_id.clone(), record.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                (confirmed.id.clone(), AgentSignOutcome::Signed),
+                (confirmed.id.clone(), AgentSignOutcome::Denied),
+                (limited.id.clone(), AgentSignOutcome::Signed),
+            ]
+        );
+        assert_eq!(log[0].fingerprint, confirmed.fingerprint);
+        #[cfg(target_os = "linux")]
+        {
+            let peer = log[0].peer.as_ref().expect("peer info");
+            assert_eq!(peer.pid, Some(std::process::id()));
+            assert!(peer.executable.is_some());
+        }
+
+        agent.lock().unwrap().stop();
+    }
+
+    // 启动一个独立的 OpenSSH ssh-agent，系统中没有时返回 None
+    fn spawn_ssh_agent(socket_path: &Path) -> Option<std::process::Child> {
+        let child = std::process::Command::new("ssh-agent")
+            .arg("-D")
+            .arg("-a")
+            .arg(socket_path)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .ok()?;
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !socket_path.exists() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        Some(child)
+    }
+
+    #[test]
+    fn test_system_agent_add_list_remove() {
+        let dir = tempdir().expect("create temp dir");
+        let socket_path = dir.path().join("ssh-agent.sock");
+        let Some(mut ssh_agent) = spawn_ssh_agent(&socket_path) else {
+            eprintln!("ssh-agent not found, skipping");
+            return;
+        };
+
+        let ed25519 = generate(SshKeyType::Ed25519, 256, "ed@example.com");
+        let rsa_key = generate(SshKeyType::Rsa, 2048, "");
+        let mut client = SystemAgentClient::connect(&socket_path).unwrap();
+        assert!(client.list_identities().unwrap().is_empty());
+
+        client
+            .add_identity(&AgentIdentity::from_key_pair(&ed25519, None).unwrap())
+            .unwrap();
+        client
+            .add_identity(
+                &AgentIdentity::from_key_pair(&rsa_key, None)
+                    .unwrap()
+                    .with_constraints(AgentKeyConstraints {
+                        lifetime_secs: Some(600),
+                        ..Default::default()
+                    }),
+            )
+            .unwrap();
+        // 标准协议不支持次数限制
+        assert!(client
+            .add_identity(
+                &AgentIdentity::from_key_pair(&ed25519, None)
+                    .unwrap()
+                    .with_constraints(AgentKeyConstraints {
+                        max_uses: Some(1),
+                        ..Default::default()
+                    }),
+            )
+            .is_err());
+
+        let held = client.list_identities().unwrap();
+        let vault_keys = [ed25519.clone(), rsa_key.clone()];
+        let described: Vec<_> = held
+            .iter()
+            .map(|identity| identity.describe(&vault_keys))
+            .collect();
+        assert_eq!(described.len(), 2);
+        assert_eq!(described[0].fingerprint, ed25519.fingerprint);
+        assert_eq!(described[0].algorithm, "ssh-ed25519");
+        assert_eq!(described[0].comment, "ed@example.com");
+        assert_eq!(described[0].key_id.as_deref(), Some(ed25519.id.as_str()));
+        assert_eq!(described[1].fingerprint, rsa_key.fingerprint);
+        assert_eq!(described[1].algorithm, "ssh-rsa");
+        assert_eq!(described[1].key_name.as_deref(), Some("Rsa"));
+        // 不在存储中的密钥没有对应
+        assert!(held[0].describe(&[]).key_id.is_none());
+
+        // 系统代理能用加入的 RSA 私钥签名
+        let data = b"signed by the system agent";
+        let (algorithm, signature) = AgentClient::connect(&socket_path)
+            .sign(&key_blob(&rsa_key), data, SSH_AGENT_RSA_SHA2_256)
+            .unwrap();
+        assert_eq!(algorithm, "rsa-sha2-256");
+        let public = PublicKey::from_openssh(&rsa_key.public_key).unwrap();
+        let rsa_public = public.key_data().rsa().unwrap();
+        let rsa_public = rsa::RsaPublicKey::new(
+            rsa::BigUint::from_bytes_be(rsa_public.n().as_positive_bytes().unwrap()),
+            rsa::BigUint::from_bytes_be(rsa_public.e().as_positive_bytes().unwrap()),
+        )
+        .unwrap();
+        rsa::pkcs1v15::VerifyingKey::<sha2::Sha256>::new(rsa_public)
+            .verify(
+                data,
+                &rsa::pkcs1v15::Signature::try_from(signature.as_slice()).unwrap(),
+            )
+            .unwrap();
+
+        client.remove_identity(&held[0].key_blob).unwrap();
+        assert_eq!(client.list_identities().unwrap(), held[1..]);
+        assert!(client.remove_identity(&held[0].key_blob).is_err());
+
+        let _ = ssh_agent.kill();
+        let _ = ssh_agent.wait();
+    }
+}
@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod tests {
+    use sha2::{Digest, Sha256};
+    use ssh_key_manager_lib::error::AppError;
     use ssh_key_manager_lib::services::CryptoService;
+    use ssh_key_manager_lib::types::{KdfParams, KeySlotKind, LegacyHeader, VaultHeader};
 
     #[test]
     fn test_crypto_service_new() {
@@ -13,8 +16,8 @@ mod tests {
         let password = "test_password";
         let salt = [0u8; 32];
 
-        let key1 = CryptoService::derive_key(password, &salt);
-        let key2 = CryptoService::derive_key(password, &salt);
+        let key1 = CryptoService::derive_key(password, &salt).unwrap();
+        let key2 = CryptoService::derive_key(password, &salt).unwrap();
 
         assert_eq!(key1, key2);
     }
@@ -25,12 +28,72 @@ mod tests {
         let salt1 = [1u8; 32];
         let salt2 = [2u8; 32];
 
-        let key1 = CryptoService::derive_key(password, &salt1);
-        let key2 = CryptoService::derive_key(password, &salt2);
+        let key1 = CryptoService::derive_key(password, &salt1).unwrap();
+        let key2 = CryptoService::derive_key(password, &salt2).unwrap();
 
         assert_ne!(key1, key2);
     }
 
+    #[test]
+    fn test_derive_key_with_params() {
+        let password = "test_password";
+        let salt = [3u8; 32];
+
+        // 默认参数为 Argon2id，与 1.0 版本的 PBKDF2 派生结果不同
+        let default_key = CryptoService::derive_key(password, &salt).unwrap();
+        let argon2_key =
+            CryptoService::derive_key_with_params(password, &salt, &KdfParams::default()).unwrap();
+        let legacy_key =
+            CryptoService::derive_key_with_params(password, &salt, &KdfParams::legacy()).unwrap();
+        assert_eq!(default_key, argon2_key);
+        assert_ne!(argon2_key, legacy_key);
+
+        let weaker = KdfParams::Argon2id {
+            memory_kib: 8 * 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let weaker_key = CryptoService::derive_key_with_params(password, &salt, &weaker).unwrap();
+        assert_ne!(argon2_key, weaker_key);
+    }
+
+    #[test]
+    fn test_derive_key_rejects_unreasonable_params() {
+        let salt = [0u8; 32];
+        let huge_memory = KdfParams::Argon2id {
+            memory_kib: 16 * 1024 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        };
+        let zero_iterations = KdfParams::Pbkdf2HmacSha256 { iterations: 0 };
+
+        assert!(CryptoService::derive_key_with_params("pw", &salt, &huge_memory).is_err());
+        assert!(CryptoService::derive_key_with_params("pw", &salt, &zero_iterations).is_err());
+    }
+
+    #[test]
+    fn test_encrypt_with_password_round_trip() {
+        let salt = CryptoService::generate_salt();
+        let kdf = KdfParams::legacy();
+
+        let encrypted =
+            CryptoService::encrypt_with_password("export_password", &salt, &kdf, b"payload")
+                .unwrap();
+        let decrypted =
+            CryptoService::decrypt_with_password("export_password", &salt, &kdf, &encrypted)
+                .unwrap();
+        assert_eq!(decrypted, b"payload");
+
+        // 参数不一致时无法解密
+        let wrong_kdf = CryptoService::decrypt_with_password(
+            "export_password",
+            &salt,
+            &KdfParams::default(),
+            &encrypted,
+        );
+        assert!(wrong_kdf.is_err());
+    }
+
     #[test]
     fn test_set_master_key() {
         let mut crypto_service = CryptoService::new();
@@ -39,8 +102,180 @@ mod tests {
         let result = crypto_service.set_master_key(password);
         assert!(result.is_ok());
         assert!(crypto_service.is_authenticated());
-        assert!(crypto_service.get_salt().is_some());
-        assert!(crypto_service.get_master_key_hash().is_some());
+        assert_eq!(crypto_service.key_slots().len(), 1);
+        assert_eq!(crypto_service.key_slots()[0].kind, KeySlotKind::Password);
+        assert_eq!(crypto_service.key_slots()[0].kdf, KdfParams::default());
+        assert!(!crypto_service.is_legacy());
+        assert!(!crypto_service.needs_upgrade());
+    }
+
+    #[test]
+    fn test_verify_password_with_legacy_kdf() {
+        let password = "legacy_password";
+        let salt = [7u8; 32];
+        let legacy_key =
+            CryptoService::derive_key_with_params(password, &salt, &KdfParams::legacy()).unwrap();
+
+        // 1.0 存储中的主密码哈希为 SHA-256(派生密钥 || 盐值)
+        let mut hasher = Sha256::new();
+        hasher.update(legacy_key);
+        hasher.update(salt);
+        let stored_hash = format!("{:x}", hasher.finalize());
+
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_header(VaultHeader::Legacy(LegacyHeader {
+            salt,
+            master_key_hash: stored_hash,
+            kdf: KdfParams::legacy(),
+        }));
+
+        assert!(crypto_service.needs_upgrade());
+        assert!(crypto_service.verify_password(password));
+        assert!(!crypto_service.verify_password("wrong_password"));
+
+        // 1.x 存储的数据直接由派生密钥加密
+        let encrypted = CryptoService::encrypt_with_key(&legacy_key, b"legacy data").unwrap();
+        assert!(crypto_service.unlock(password));
+        assert_eq!(crypto_service.decrypt(&encrypted).unwrap(), b"legacy data");
+    }
+
+    #[test]
+    fn test_unlock_with_password_slot() {
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("slot_password").unwrap();
+        let encrypted = crypto_service.encrypt(b"vault data").unwrap();
+        let slots = crypto_service.key_slots().to_vec();
+
+        // 数据密钥是随机生成的，不等于密码派生的 KEK
+        let kek =
+            CryptoService::derive_key_with_params("slot_password", &slots[0].salt, &slots[0].kdf)
+                .unwrap();
+        assert!(CryptoService::decrypt_with_key(&kek, &encrypted).is_err());
+
+        let mut reopened = CryptoService::new();
+        reopened.set_header(VaultHeader::Envelope(slots));
+        assert!(!reopened.unlock("wrong_password"));
+        assert!(!reopened.is_authenticated());
+        assert!(reopened.unlock("slot_password"));
+        assert_eq!(reopened.decrypt(&encrypted).unwrap(), b"vault data");
+    }
+
+    #[test]
+    fn test_recovery_key_format() {
+        let recovery_key = CryptoService::generate_recovery_key();
+        let groups: Vec<&str> = recovery_key.split('-').collect();
+        assert_eq!(groups.len(), 8);
+        assert!(groups.iter().all(|g| g.len() == 4));
+        assert_ne!(*recovery_key, *CryptoService::generate_recovery_key());
+
+        // 忽略大小写与分隔符，并把 O/I/L 视为 0/1
+        let normalized = CryptoService::normalize_recovery_key(&recovery_key).unwrap();
+        assert_eq!(*normalized, recovery_key.replace('-', ""));
+        assert_eq!(
+            CryptoService::normalize_recovery_key("oil0 abcd-efgh-jkmn-pqrs-tvwx-yz01-2345")
+                .unwrap(),
+            CryptoService::normalize_recovery_key("0110ABCDEFGHJKMNPQRSTVWXYZ012345").unwrap()
+        );
+        assert!(CryptoService::normalize_recovery_key("ABCD-EFGH").is_none());
+        assert!(
+            CryptoService::normalize_recovery_key("UUUU-0000-0000-0000-0000-0000-0000-0000")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_unlock_with_recovery_key_slot() {
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("master_password").unwrap();
+        let encrypted = crypto_service.encrypt(b"vault data").unwrap();
+        assert!(!crypto_service.has_recovery_key());
+
+        let recovery_key = CryptoService::generate_recovery_key();
+        let slots = crypto_service
+            .rewrap_recovery_key_slot(&recovery_key)
+            .unwrap();
+        assert_eq!(slots.len(), 2);
+
+        let mut reopened = CryptoService::new();
+        reopened.set_header(VaultHeader::Envelope(slots));
+        assert!(reopened.has_recovery_key());
+        // 恢复密钥不能当作主密码使用，反之亦然
+        assert!(!reopened.unlock(&recovery_key));
+        assert!(!reopened.unlock_with_recovery_key("master_password"));
+        assert!(reopened.unlock_with_recovery_key(&recovery_key));
+        assert_eq!(reopened.decrypt(&encrypted).unwrap(), b"vault data");
+
+        // 重新生成后旧恢复密钥失效，主密码不受影响
+        let new_recovery_key = CryptoService::generate_recovery_key();
+        let slots = reopened
+            .rewrap_recovery_key_slot(&new_recovery_key)
+            .unwrap();
+        let mut regenerated = CryptoService::new();
+        regenerated.set_header(VaultHeader::Envelope(slots));
+        assert!(!regenerated.unlock_with_recovery_key(&recovery_key));
+        assert!(regenerated.verify_password("master_password"));
+        assert!(regenerated.unlock_with_recovery_key(&new_recovery_key));
+    }
+
+    #[test]
+    fn test_keyfile_required_for_password_slot() {
+        let keyfile = CryptoService::hash_keyfile(b"keyfile contents");
+        let other_keyfile = CryptoService::hash_keyfile(b"another keyfile");
+
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("master_password").unwrap();
+        let encrypted = crypto_service.encrypt(b"vault data").unwrap();
+        let slots = crypto_service
+            .rewrap_password_slots_with_keyfile("master_password", Some(&keyfile))
+            .unwrap();
+        assert!(slots[0].requires_keyfile);
+
+        let mut reopened = CryptoService::new();
+        reopened.set_header(VaultHeader::Envelope(slots.clone()));
+        assert!(reopened.requires_keyfile());
+
+        // 缺少密钥文件时返回明确的错误
+        assert!(matches!(
+            reopened.try_unlock("master_password"),
+            Err(AppError::KeyfileRequired)
+        ));
+
+        // 密钥文件错误与主密码错误无法区分，都只是解锁失败
+        reopened.set_keyfile(Some(other_keyfile));
+        assert!(!reopened.try_unlock("master_password").unwrap());
+        reopened.set_keyfile(Some(keyfile));
+        assert!(!reopened.try_unlock("wrong_password").unwrap());
+        assert!(reopened.try_unlock("master_password").unwrap());
+        assert_eq!(reopened.decrypt(&encrypted).unwrap(), b"vault data");
+
+        // 修改主密码时保留密钥文件要求；停用后仅凭主密码即可解锁
+        let rewrapped = reopened.rewrap_password_slots("new_password").unwrap();
+        assert!(rewrapped[0].requires_keyfile);
+        let disabled = reopened
+            .rewrap_password_slots_with_keyfile("master_password", None)
+            .unwrap();
+        let mut plain = CryptoService::new();
+        plain.set_header(VaultHeader::Envelope(disabled));
+        assert!(!plain.requires_keyfile());
+        assert!(plain.unlock("master_password"));
+    }
+
+    #[test]
+    fn test_rewrap_password_slots_keeps_data_key() {
+        let mut crypto_service = CryptoService::new();
+        crypto_service.set_master_key("old_password").unwrap();
+        let encrypted = crypto_service.encrypt(b"vault data").unwrap();
+
+        let slots = crypto_service
+            .rewrap_password_slots("new_password")
+            .unwrap();
+        assert_eq!(slots.len(), 1);
+
+        let mut reopened = CryptoService::new();
+        reopened.set_header(VaultHeader::Envelope(slots));
+        assert!(!reopened.unlock("old_password"));
+        assert!(reopened.unlock("new_password"));
+        assert_eq!(reopened.decrypt(&encrypted).unwrap(), b"vault data");
     }
 
     #[test]
@@ -1,7 +1,9 @@
 #[cfg(test)]
 mod tests {
+    use ssh_key_manager_lib::error::AppError;
     use ssh_key_manager_lib::services::SshKeyService;
     use ssh_key_manager_lib::types::{KeyGenerationParams, SshKeyType};
+    use ssh_key::{Cipher, PrivateKey};
 
     #[test]
     fn test_ssh_key_service_new() {
@@ -110,8 +112,182 @@ mod tests {
         let key_pair = result.unwrap();
         assert_eq!(key_pair.name, "Test Protected Key");
         assert_eq!(key_pair.comment, "protected@example.com");
-        // Note: In a real implementation, the private key would be encrypted
-        assert!(!key_pair.private_key.is_empty());
+        assert!(key_pair.passphrase_protected);
+        assert!(key_pair.private_key.contains("BEGIN OPENSSH PRIVATE KEY"));
+
+        // 私钥应为 bcrypt-pbkdf + aes256-ctr 加密，且只能用正确的密码解密
+        let private_key = PrivateKey::from_openssh(&key_pair.private_key).unwrap();
+        assert!(private_key.is_encrypted());
+        assert_eq!(private_key.cipher(), Cipher::Aes256Ctr);
+        assert!(private_key.kdf().is_bcrypt());
+        assert!(private_key.decrypt("wrong_passphrase").is_err());
+
+        let decrypted = private_key.decrypt("secure_passphrase").unwrap();
+        assert_eq!(
+            decrypted.public_key().to_openssh().unwrap(),
+            key_pair.public_key
+        );
+    }
+
+    #[test]
+    fn test_generate_key_with_empty_passphrase_is_unencrypted() {
+        let params = KeyGenerationParams {
+            name: "Empty Passphrase Key".to_string(),
+            key_type: SshKeyType::Ed25519,
+            key_size: 256,
+            comment: "empty@example.com".to_string(),
+            passphrase: Some(String::new()),
+        };
+
+        let key_pair = SshKeyService::generate_key_pair(params).unwrap();
+        assert!(!key_pair.passphrase_protected);
+        assert!(!SshKeyService::is_private_key_encrypted(&key_pair.private_key));
+    }
+
+    #[test]
+    fn test_change_passphrase_add_change_remove() {
+        let params = KeyGenerationParams {
+            name: "Rotating Key".to_string(),
+            key_type: SshKeyType::Ed25519,
+            key_size: 256,
+            comment: "rotate@example.com".to_string(),
+            passphrase: None,
+        };
+        let key_pair = SshKeyService::generate_key_pair(params).unwrap();
+
+        // 添加密码
+        let (added, protected) =
+            SshKeyService::change_passphrase(&key_pair.private_key, None, Some("first")).unwrap();
+        assert!(protected);
+        assert!(PrivateKey::from_openssh(&added).unwrap().decrypt("first").is_ok());
+
+        // 修改密码
+        let (changed, protected) =
+            SshKeyService::change_passphrase(&added, Some("first"), Some("second")).unwrap();
+        assert!(protected);
+        let changed_key = PrivateKey::from_openssh(&changed).unwrap();
+        assert!(changed_key.decrypt("first").is_err());
+        assert!(changed_key.decrypt("second").is_ok());
+
+        // 移除密码
+        let (removed, protected) =
+            SshKeyService::change_passphrase(&changed, Some("second"), None).unwrap();
+        assert!(!protected);
+        let removed_key = PrivateKey::from_openssh(&removed).unwrap();
+        assert!(!removed_key.is_encrypted());
+        assert_eq!(
+            removed_key.public_key().to_openssh().unwrap(),
+            key_pair.public_key
+        );
+    }
+
+    #[test]
+    fn test_change_passphrase_rejects_wrong_old_passphrase() {
+        let params = KeyGenerationParams {
+            name: "Protected Key".to_string(),
+            key_type: SshKeyType::Ed25519,
+            key_size: 256,
+            comment: "protected@example.com".to_string(),
+            passphrase: Some("correct".to_string()),
+        };
+        let key_pair = SshKeyService::generate_key_pair(params).unwrap();
+
+        let wrong = SshKeyService::change_passphrase(&key_pair.private_key, Some("wrong"), None);
+        assert!(matches!(wrong, Err(AppError::InvalidPassphrase)));
+
+        let missing = SshKeyService::change_passphrase(&key_pair.private_key, None, Some("new"));
+        assert!(matches!(missing, Err(AppError::InvalidPassphrase)));
+    }
+
+    #[test]
+    fn test_import_private_key_derives_metadata() {
+        let params = KeyGenerationParams {
+            name: "Source Key".to_string(),
+            key_type: SshKeyType::Ecdsa,
+            key_size: 384,
+            comment: "source@example.com".to_string(),
+            passphrase: None,
+        };
+        let source = SshKeyService::generate_key_pair(params).unwrap();
+        let public_file = format!("{} source@example.com\n", source.public_key);
+
+        let imported = SshKeyService::import_private_key(
+            "id_ecdsa",
+            &source.private_key,
+            Some(&public_file),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(imported.name, "id_ecdsa");
+        assert_eq!(imported.key_type, SshKeyType::Ecdsa);
+        assert_eq!(imported.key_size, 384);
+        assert_eq!(imported.comment, "source@example.com");
+        assert_eq!(imported.fingerprint, source.fingerprint);
+        assert!(imported.public_key.ends_with(" source@example.com"));
+        assert!(!imported.passphrase_protected);
+    }
+
+    #[test]
+    fn test_import_encrypted_private_key_requires_passphrase() {
+        let params = KeyGenerationParams {
+            name: "Encrypted Source".to_string(),
+            key_type: SshKeyType::Ed25519,
+            key_size: 256,
+            comment: String::new(),
+            passphrase: Some("import-me".to_string()),
+        };
+        let source = SshKeyService::generate_key_pair(params).unwrap();
+
+        let missing =
+            SshKeyService::import_private_key("id_ed25519", &source.private_key, None, None);
+        assert!(matches!(missing, Err(AppError::PassphraseRequired)));
+
+        let wrong = SshKeyService::import_private_key(
+            "id_ed25519",
+            &source.private_key,
+            None,
+            Some("nope"),
+        );
+        assert!(matches!(wrong, Err(AppError::InvalidPassphrase)));
+
+        let imported = SshKeyService::import_private_key(
+            "id_ed25519",
+            &source.private_key,
+            None,
+            Some("import-me"),
+        )
+        .unwrap();
+        assert_eq!(imported.key_type, SshKeyType::Ed25519);
+        assert_eq!(imported.fingerprint, source.fingerprint);
+        assert!(imported.passphrase_protected);
+        assert!(SshKeyService::is_private_key_encrypted(
+            &imported.private_key
+        ));
+    }
+
+    #[test]
+    fn test_import_private_key_rejects_mismatched_public_key() {
+        let make = |name: &str| {
+            SshKeyService::generate_key_pair(KeyGenerationParams {
+                name: name.to_string(),
+                key_type: SshKeyType::Ed25519,
+                key_size: 256,
+                comment: String::new(),
+                passphrase: None,
+            })
+            .unwrap()
+        };
+        let first = make("first");
+        let second = make("second");
+
+        let result = SshKeyService::import_private_key(
+            "id_ed25519",
+            &first.private_key,
+            Some(&second.public_key),
+            None,
+        );
+        assert!(result.is_err());
     }
 
     #[test]
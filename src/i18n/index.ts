@@ -290,7 +290,14 @@ const zh = {
       method: {
         title: '导入方式',
         file: '从文件导入',
-        text: '从文本导入'
+        text: '从文本导入',
+        privateKey: '导入私钥文件（OpenSSH / PEM / PPK）'
+      },
+      privateKey: {
+        label: '选择私钥文件',
+        name: '密钥名称',
+        passphrase: '私钥密码',
+        passphrasePlaceholder: '私钥未加密时留空'
       },
       file: {
         label: '选择密钥文件',
@@ -316,11 +323,16 @@ const zh = {
         json: 'JSON 格式 (.json)',
         openssh: 'OpenSSH 格式',
         pem: 'PEM 格式',
+        ppk: 'PuTTY PPK 格式',
         description: 'JSON格式：适合应用备份和恢复 | OpenSSH/PEM格式：适合系统使用'
       },
       security: {
         includePrivate: '包含私钥 (不推荐，仅在安全环境中使用)',
-        encryptWithMaster: '使用主密码加密导出'
+        encryptWithMaster: '使用主密码加密导出',
+        keyPassphrase: '私钥密码',
+        keyPassphrasePlaceholder: '私钥受密码保护时填写',
+        exportPassphrase: '导出文件密码',
+        exportPassphrasePlaceholder: '留空则不加密导出的私钥文件'
       },
       preview: {
         title: '导出预览',
@@ -353,6 +365,11 @@ const zh = {
     title: '设置',
     appSettings: '应用设置',
     developing: '功能开发中',
+    autoLock: {
+      title: '自动锁定',
+      desc: '空闲超过所选时间后自动锁定并返回解锁页面',
+      minutes: '{n} 分钟'
+    },
     autoExport: {
       title: '密钥自动导出',
       desc: '当密钥新增、删除或导入时，自动导出所有密钥到目录',
@@ -363,6 +380,28 @@ const zh = {
       title: '语言设置',
       select: '选择语言'
     },
+    recoveryKey: {
+      title: '恢复密钥',
+      desc: '忘记主密码时，可以使用恢复密钥重新设置主密码',
+      enabled: '已设置',
+      disabled: '未设置',
+      generate: '生成恢复密钥',
+      regenerate: '重新生成',
+      revoke: '撤销',
+      regenerateConfirm: '重新生成后，旧的恢复密钥将立即失效。确定继续吗？',
+      revokeConfirm: '撤销后将无法再使用恢复密钥重新设置主密码。确定撤销吗？',
+      generateFailed: '生成恢复密钥失败',
+      revokeFailed: '撤销恢复密钥失败',
+      dialog: {
+        title: '请保存恢复密钥',
+        message: '恢复密钥只显示这一次，请抄写或保存到安全的地方。忘记主密码时可以用它重新设置主密码。',
+        saved: '我已保存'
+      },
+      messages: {
+        copySuccess: '恢复密钥已复制到剪贴板',
+        copyError: '复制恢复密钥失败'
+      }
+    },
     reset: {
       title: '重置应用',
       description: '清除所有数据，包括主密码、所有密钥和当前语言设置。此操作无法撤销。',
@@ -403,6 +442,13 @@ const zh = {
       initializationFailed: '初始化失败',
       wrongPassword: '密码错误',
       operationFailed: '操作失败，请稍后重试'
+    },
+    storageRecovery: {
+      title: '存储文件已损坏',
+      message: '存储文件未通过完整性校验，是否从上一次保存的版本恢复？最近一次修改可能会丢失。',
+      confirm: '恢复',
+      success: '已从上一版本恢复，请重新解锁',
+      failed: '恢复失败'
     }
   }
 }
@@ -696,7 +742,14 @@ const en = {
       method: {
         title: 'Import Method',
         file: 'Import from File',
-        text: 'Import from Text'
+        text: 'Import from Text',
+        privateKey: 'Import Private Key File (OpenSSH / PEM / PPK)'
+      },
+      privateKey: {
+        label: 'Select Private Key File',
+        name: 'Key Name',
+        passphrase: 'Private Key Passphrase',
+        passphrasePlaceholder: 'Leave empty if the private key is not encrypted'
       },
       file: {
         label: 'Select Key File',
@@ -722,11 +775,16 @@ const en = {
         json: 'JSON Format (.json)',
         openssh: 'OpenSSH Format',
         pem: 'PEM Format',
+        ppk: 'PuTTY PPK Format',
         description: 'JSON Format: Suitable for app backup and restore | OpenSSH/PEM Format: Suitable for system use'
       },
       security: {
         includePrivate: 'Include Private Keys (Not recommended, use only in secure environments)',
-        encryptWithMaster: 'Encrypt export with master password'
+        encryptWithMaster: 'Encrypt export with master password',
+        keyPassphrase: 'Private Key Passphrase',
+        keyPassphrasePlaceholder: 'Required if the private keys are passphrase-protected',
+        exportPassphrase: 'Export File Passphrase',
+        exportPassphrasePlaceholder: 'Leave empty to export unencrypted private key files'
       },
       preview: {
         title: 'Export Preview',
@@ -759,6 +817,11 @@ const en = {
     title: 'Settings',
     appSettings: 'Application Settings',
     developing: 'Feature under development',
+    autoLock: {
+      title: 'Auto-lock',
+      desc: 'Lock the vault and return to the unlock screen after this much idle time',
+      minutes: '{n} minutes'
+    },
     autoExport: {
       title: 'Auto-export keys',
       desc: 'Automatically export all keys when keys are added, deleted, or imported',
@@ -769,6 +832,28 @@ const en = {
       title: 'Language Settings',
       select: 'Select Language'
     },
+    recoveryKey: {
+      title: 'Recovery Key',
+      desc: 'Use the recovery key to set a new master password if you forget the current one',
+      enabled: 'Configured',
+      disabled: 'Not configured',
+      generate: 'Generate Recovery Key',
+      regenerate: 'Regenerate',
+      revoke: 'Revoke',
+      regenerateConfirm: 'The current recovery key will stop working as soon as a new one is generated. Continue?',
+      revokeConfirm: 'After revoking, the recovery key can no longer be used to set a new master password. Revoke it?',
+      generateFailed: 'Failed to generate the recovery key',
+      revokeFailed: 'Failed to revoke the recovery key',
+      dialog: {
+        title: 'Save Your Recovery Key',
+        message: 'The recovery key is shown only once. Write it down or store it somewhere safe. You can use it to set a new master password if you forget the current one.',
+        saved: 'I Have Saved It'
+      },
+      messages: {
+        copySuccess: 'Recovery key copied to clipboard',
+        copyError: 'Failed to copy the recovery key'
+      }
+    },
     reset: {
       title: 'Reset Application',
       description: 'Clear all data, including the master password, all keys, and current language settings. This action cannot be undone.',
@@ -809,6 +894,13 @@ const en = {
       initializationFailed: 'Initialization failed',
       wrongPassword: 'Wrong password',
       operationFailed: 'Operation failed, please try again later'
+    },
+    storageRecovery: {
+      title: 'Storage File Corrupted',
+      message: 'The storage file failed its integrity check. Restore the previously saved version? The most recent change may be lost.',
+      confirm: 'Restore',
+      success: 'Restored from the previous version, please unlock again',
+      failed: 'Restore failed'
     }
   }
 }
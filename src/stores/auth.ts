@@ -1,6 +1,7 @@
 import { defineStore } from 'pinia'
 import { ref, computed } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
+import type { AuthGuardState, FailedAttempt, LockoutPolicy, SshKeyPair, DataDirLocation, VaultBackupList, VaultProfileInfo } from '@/types'
 
 export const useAuthStore = defineStore('auth', () => {
   const isAuthenticated = ref(false)
@@ -41,24 +42,266 @@ export const useAuthStore = defineStore('auth', () => {
     }
   }
   
-  // 用户登录
-  const login = async (masterKey: string): Promise<boolean> => {
+  // 存储文件损坏时检查能否从上一版本恢复
+  const canRecoverStorage = async (): Promise<boolean> => {
+    try {
+      return await invoke<boolean>('can_recover_storage')
+    } catch (error) {
+      console.error('检查存储恢复失败:', error)
+      return false
+    }
+  }
+  
+  // 用上一版本覆盖已损坏的存储文件
+  const recoverStorage = async (): Promise<boolean> => {
+    try {
+      return await invoke<boolean>('recover_storage')
+    } catch (error) {
+      console.error('恢复存储失败:', error)
+      return false
+    }
+  }
+  
+  // 用户登录（退避、锁定等错误信息直接抛给调用方展示）
+  const login = async (masterKey: string, keyfilePath?: string): Promise<boolean> => {
     try {
       isLoading.value = true
-      const result = await invoke<boolean>('authenticate', { masterKey })
+      const result = await invoke<boolean>('authenticate', { masterKey, keyfilePath })
       isAuthenticated.value = result
       return result
+    } finally {
+      isLoading.value = false
+    }
+  }
+  
+  // 修改主密码
+  const changeMasterPassword = async (oldMasterKey: string, newMasterKey: string, keyfilePath?: string): Promise<boolean> => {
+    try {
+      isLoading.value = true
+      return await invoke<boolean>('change_master_password', { oldMasterKey, newMasterKey, keyfilePath })
     } catch (error) {
-      console.error('登录失败:', error)
+      console.error('修改主密码失败:', error)
       return false
     } finally {
       isLoading.value = false
     }
   }
   
-  // 用户登出
-  const logout = () => {
+  // 生成（或重新生成）恢复密钥，返回需要用户抄写保存的明文
+  const generateRecoveryKey = async (): Promise<string | null> => {
+    try {
+      return await invoke<string>('generate_recovery_key')
+    } catch (error) {
+      console.error('生成恢复密钥失败:', error)
+      return null
+    }
+  }
+  
+  // 撤销恢复密钥
+  const revokeRecoveryKey = async (): Promise<boolean> => {
+    try {
+      return await invoke<boolean>('revoke_recovery_key')
+    } catch (error) {
+      console.error('撤销恢复密钥失败:', error)
+      return false
+    }
+  }
+  
+  // 检查是否已设置恢复密钥
+  const hasRecoveryKey = async (): Promise<boolean> => {
+    try {
+      return await invoke<boolean>('has_recovery_key')
+    } catch (error) {
+      console.error('检查恢复密钥失败:', error)
+      return false
+    }
+  }
+  
+  // 使用恢复密钥解锁并设置新的主密码
+  const recoverWithRecoveryKey = async (recoveryKey: string, newMasterKey: string): Promise<boolean> => {
+    try {
+      isLoading.value = true
+      const result = await invoke<boolean>('recover_with_recovery_key', { recoveryKey, newMasterKey })
+      if (result) {
+        isAuthenticated.value = true
+      }
+      return result
+    } catch (error) {
+      console.error('使用恢复密钥恢复失败:', error)
+      return false
+    } finally {
+      isLoading.value = false
+    }
+  }
+  
+  // 检查解锁是否需要密钥文件
+  const isKeyfileRequired = async (): Promise<boolean> => {
+    try {
+      return await invoke<boolean>('is_keyfile_required')
+    } catch (error) {
+      console.error('检查密钥文件设置失败:', error)
+      return false
+    }
+  }
+  
+  // 启用密钥文件（错误信息直接抛给调用方展示）
+  const enableKeyfile = async (masterKey: string, keyfilePath: string): Promise<boolean> => {
+    return await invoke<boolean>('enable_keyfile', { masterKey, keyfilePath })
+  }
+  
+  // 更换密钥文件
+  const rotateKeyfile = async (masterKey: string, newKeyfilePath: string): Promise<boolean> => {
+    return await invoke<boolean>('rotate_keyfile', { masterKey, newKeyfilePath })
+  }
+  
+  // 停用密钥文件
+  const disableKeyfile = async (masterKey: string): Promise<boolean> => {
+    return await invoke<boolean>('disable_keyfile', { masterKey })
+  }
+  
+  // 获取解锁失败记录与锁定策略
+  const getAuthGuardState = async (): Promise<AuthGuardState | null> => {
+    try {
+      return await invoke<AuthGuardState>('get_auth_guard_state')
+    } catch (error) {
+      console.error('获取解锁失败记录失败:', error)
+      return null
+    }
+  }
+  
+  // 设置连续失败后的锁定策略（null 表示只做退避）
+  const setLockoutPolicy = async (policy: LockoutPolicy | null): Promise<boolean> => {
+    return await invoke<boolean>('set_lockout_policy', { policy })
+  }
+  
+  // 清空解锁失败记录
+  const clearFailedAttempts = async (): Promise<boolean> => {
+    try {
+      await invoke<FailedAttempt[]>('clear_failed_attempts')
+      return true
+    } catch (error) {
+      console.error('清空解锁失败记录失败:', error)
+      return false
+    }
+  }
+  
+  // 列出存储文件的加密快照
+  const listVaultBackups = async (): Promise<VaultBackupList> => {
+    try {
+      return await invoke<VaultBackupList>('list_vault_backups')
+    } catch (error) {
+      console.error('获取快照列表失败:', error)
+      return { backups: [], last_error: null }
+    }
+  }
+  
+  // 预览快照中的密钥列表（需要快照保存时的主密码，错误信息直接抛给调用方展示）
+  const previewVaultBackup = async (backupName: string, masterKey: string, keyfilePath?: string): Promise<SshKeyPair[]> => {
+    return await invoke<SshKeyPair[]>('preview_vault_backup', { backupName, masterKey, keyfilePath })
+  }
+  
+  // 从快照恢复
+  const restoreVaultBackup = async (backupName: string, masterKey: string, keyfilePath?: string): Promise<boolean> => {
+    return await invoke<boolean>('restore_vault_backup', { backupName, masterKey, keyfilePath })
+  }
+  
+  // 列出所有命名存储
+  const listVaults = async (): Promise<VaultProfileInfo[]> => {
+    try {
+      return await invoke<VaultProfileInfo[]>('list_vaults')
+    } catch (error) {
+      console.error('获取存储列表失败:', error)
+      return []
+    }
+  }
+  
+  // 创建命名存储（使用独立的主密码）
+  const createVault = async (name: string, masterKey: string): Promise<boolean> => {
+    return await invoke<boolean>('create_vault', { name, masterKey })
+  }
+  
+  // 切换当前存储并返回其是否已初始化；切换后需要重新解锁
+  const switchVault = async (name: string): Promise<boolean> => {
+    isAuthenticated.value = false
+    await invoke<boolean>('switch_vault', { name })
+    return await checkInitialization()
+  }
+  
+  // 切换到指定存储并解锁
+  const openVault = async (name: string, masterKey: string, keyfilePath?: string): Promise<boolean> => {
+    isAuthenticated.value = false
+    const result = await invoke<boolean>('open_vault', { name, masterKey, keyfilePath })
+    isInitialized.value = true
+    isAuthenticated.value = result
+    return result
+  }
+  
+  const renameVault = async (name: string, newName: string): Promise<boolean> => {
+    return await invoke<boolean>('rename_vault', { name, newName })
+  }
+  
+  // 删除命名存储（需要该存储的主密码）
+  const deleteVault = async (name: string, masterKey: string, keyfilePath?: string): Promise<boolean> => {
+    return await invoke<boolean>('delete_vault', { name, masterKey, keyfilePath })
+  }
+  
+  // 当前数据目录及其来源
+  const getDataDir = async (): Promise<DataDirLocation | null> => {
+    try {
+      return await invoke<DataDirLocation>('get_data_dir')
+    } catch (error) {
+      console.error('获取数据目录失败:', error)
+      return null
+    }
+  }
+  
+  // 把当前存储移动到新目录，返回新的存储文件路径
+  const moveVault = async (targetDir: string): Promise<string> => {
+    return await invoke<string>('move_vault', { targetDir })
+  }
+  
+  // 获取自动锁定时间（分钟）
+  const getAutoLockMinutes = async (): Promise<number> => {
+    try {
+      return await invoke<number>('get_auto_lock_minutes')
+    } catch (error) {
+      console.error('获取自动锁定时间失败:', error)
+      return 10
+    }
+  }
+  
+  // 设置自动锁定时间（分钟）
+  const setAutoLockMinutes = async (minutes: number): Promise<boolean> => {
+    try {
+      return await invoke<boolean>('set_auto_lock_minutes', { minutes })
+    } catch (error) {
+      console.error('设置自动锁定时间失败:', error)
+      return false
+    }
+  }
+  
+  // 记录用户活动，重置后端的空闲计时
+  const touchSession = async () => {
+    try {
+      await invoke('touch_session')
+    } catch (error) {
+      console.error('更新会话活动失败:', error)
+    }
+  }
+  
+  // 后端已锁定（手动或空闲超时）
+  const markLocked = () => {
+    isAuthenticated.value = false
+  }
+  
+  // 用户登出：锁定并清除后端内存中的密钥
+  const logout = async () => {
     isAuthenticated.value = false
+    try {
+      await invoke('lock_vault')
+    } catch (error) {
+      console.error('锁定失败:', error)
+    }
   }
   
   // 重置认证状态
@@ -78,6 +321,35 @@ export const useAuthStore = defineStore('auth', () => {
     checkInitialization,
     initializeApp,
     login,
+    canRecoverStorage,
+    recoverStorage,
+    changeMasterPassword,
+    generateRecoveryKey,
+    revokeRecoveryKey,
+    hasRecoveryKey,
+    recoverWithRecoveryKey,
+    isKeyfileRequired,
+    enableKeyfile,
+    rotateKeyfile,
+    disableKeyfile,
+    getAuthGuardState,
+    setLockoutPolicy,
+    clearFailedAttempts,
+    listVaultBackups,
+    previewVaultBackup,
+    restoreVaultBackup,
+    listVaults,
+    createVault,
+    switchVault,
+    openVault,
+    renameVault,
+    deleteVault,
+    getDataDir,
+    moveVault,
+    getAutoLockMinutes,
+    setAutoLockMinutes,
+    touchSession,
+    markLocked,
     logout,
     reset,
   }
@@ -1,7 +1,14 @@
 import { defineStore } from 'pinia'
 import { ref, computed } from 'vue'
 import { invoke } from '@tauri-apps/api/core'
-import type { SshKeyPair, KeyGenerationParams } from '@/types'
+import type {
+  SshKeyPair,
+  KeyGenerationParams,
+  AgentStatus,
+  AgentKeyOptions,
+  AgentSignRecord,
+  SystemAgentKey
+} from '@/types'
 import { useSettingsStore } from '@/stores/settings'
 import { join } from '@tauri-apps/api/path'
 import { useToast } from '@/composables/useToast'
@@ -161,6 +168,23 @@ export const useKeyStore = defineStore('key', () => {
     }
   }
 
+  // 导入单个私钥（OpenSSH、PEM 或 PPK），加密私钥需提供密码
+  const importPrivateKey = async (
+    name: string,
+    privateKey: string,
+    passphrase?: string
+  ): Promise<SshKeyPair> => {
+    try {
+      const key = await invoke<SshKeyPair>('import_private_key', { name, privateKey, passphrase })
+      keys.value.push(key)
+      await maybeAutoExport()
+      return key
+    } catch (error) {
+      console.error('导入私钥失败:', error)
+      throw error
+    }
+  }
+
   // 导出所有密钥
   const exportAllKeys = async (): Promise<string> => {
     try {
@@ -171,6 +195,43 @@ export const useKeyStore = defineStore('key', () => {
     }
   }
 
+  // 启动内置 ssh-agent；受密码保护的私钥需提供密码，需要确认的签名通过 agent-confirm-request 事件通知
+  const startAgent = async (keys: AgentKeyOptions[]): Promise<AgentStatus> => {
+    return await invoke<AgentStatus>('start_agent', { keys })
+  }
+
+  const stopAgent = async (): Promise<boolean> => {
+    return await invoke<boolean>('stop_agent')
+  }
+
+  const getAgentStatus = async (): Promise<AgentStatus> => {
+    return await invoke<AgentStatus>('get_agent_status')
+  }
+
+  const respondAgentConfirmation = async (
+    requestId: number,
+    approved: boolean
+  ): Promise<boolean> => {
+    return await invoke<boolean>('respond_agent_confirmation', { requestId, approved })
+  }
+
+  const getAgentSignLog = async (): Promise<AgentSignRecord[]> => {
+    return await invoke<AgentSignRecord[]>('get_agent_sign_log')
+  }
+
+  // 将密钥加入系统 ssh-agent（不支持 max_uses），返回代理中现有的密钥
+  const addKeysToSystemAgent = async (keys: AgentKeyOptions[]): Promise<SystemAgentKey[]> => {
+    return await invoke<SystemAgentKey[]>('add_keys_to_system_agent', { keys })
+  }
+
+  const listSystemAgentKeys = async (): Promise<SystemAgentKey[]> => {
+    return await invoke<SystemAgentKey[]>('list_system_agent_keys')
+  }
+
+  const removeSystemAgentKeys = async (fingerprints: string[]): Promise<number> => {
+    return await invoke<number>('remove_system_agent_keys', { fingerprints })
+  }
+
   // 设置选中的密钥
   const setSelectedKey = (id: string | null) => {
     selectedKeyId.value = id
@@ -218,7 +279,16 @@ export const useKeyStore = defineStore('key', () => {
     updateKeyInfo,
     exportKey,
     importKeys,
+    importPrivateKey,
     exportAllKeys,
+    startAgent,
+    stopAgent,
+    getAgentStatus,
+    respondAgentConfirmation,
+    getAgentSignLog,
+    addKeysToSystemAgent,
+    listSystemAgentKeys,
+    removeSystemAgentKeys,
     setSelectedKey,
     getKeyById,
     hasKey,
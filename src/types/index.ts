@@ -10,6 +10,10 @@ export interface SshKeyPair {
   fingerprint: string;
   created_at: string;
   last_used?: string;
+  passphrase_protected?: boolean; // 私钥是否受密码保护
+  tags?: string[];
+  notes?: string;
+  expires_at?: string | null;
 }
 
 export type SshKeyType = 'Rsa' | 'Ed25519' | 'Ecdsa';
@@ -22,6 +26,14 @@ export interface KeyGenerationParams {
   passphrase?: string; // 密钥密码（可选）
 }
 
+// 从 ~/.ssh 导入私钥文件的结果
+export interface IdentityImportResult {
+  imported: SshKeyPair[];
+  skipped: string[]; // 指纹已存在
+  passphrase_required: string[]; // 需要提供密码
+  failed: { file_name: string; error: string }[];
+}
+
 // 应用配置类型
 export interface AppConfig {
   theme: 'light' | 'dark';
@@ -39,12 +51,55 @@ export interface SshHostConfig {
   user?: string;
   port?: number;
   identity_file?: string;
+  identity_files?: string[];
+  // read_ssh_config 返回的键为小写的关键字
   other_options: Record<string, string>;
+  // 可重复选项（LocalForward、SendEnv 等）的全部值，键为小写的关键字
+  repeated_options?: Record<string, string[]>;
 }
 
 export interface SshConfig {
   hosts: SshHostConfig[];
   global_settings: Record<string, string>;
+  global_repeated_options?: Record<string, string[]>;
+}
+
+// edit_ssh_config 的结构化修改；host 为 Host 行的模式（同 SshHostConfig.host_pattern），
+// values 为空时删除该选项
+export interface SshDirectiveUpdate {
+  keyword: string;
+  values: string[];
+}
+
+export type SshConfigEdit =
+  | { op: 'add_host'; patterns: string[]; directives?: SshDirectiveUpdate[]; index?: number | null }
+  | { op: 'update_host'; host: string; patterns?: string[] | null; directives?: SshDirectiveUpdate[] }
+  | { op: 'remove_host'; host: string }
+  | { op: 'reorder_hosts'; order: string[] }
+  | ({ op: 'set_directive'; host?: string | null } & SshDirectiveUpdate);
+
+// OpenSSH 配置语法树（parse_ssh_config），行号从 1 开始
+export interface SshConfigDirective {
+  keyword: string;
+  args: string[];
+  line: number;
+  includes?: SshConfigAst[];
+}
+
+export interface SshMatchCriterion {
+  keyword: string;
+  negated: boolean;
+  arg: string | null;
+}
+
+export type SshConfigSection =
+  | { kind: 'host'; patterns: string[]; line: number; directives: SshConfigDirective[] }
+  | { kind: 'match'; criteria: SshMatchCriterion[]; line: number; directives: SshConfigDirective[] };
+
+export interface SshConfigAst {
+  path: string | null;
+  global: SshConfigDirective[];
+  sections: SshConfigSection[];
 }
 
 // 认证相关类型
@@ -53,6 +108,109 @@ export interface AuthState {
   is_initialized: boolean;
 }
 
+export type LockoutAction = 'lock' | 'wipe';
+
+export interface LockoutPolicy {
+  max_failures: number;
+  action: LockoutAction;
+}
+
+export interface FailedAttempt {
+  at: string;
+  source: string;
+}
+
+export interface AuthGuardState {
+  consecutive_failures: number;
+  retry_after: string | null;
+  locked_out: boolean;
+  failed_attempts: FailedAttempt[];
+  lockout_policy: LockoutPolicy | null;
+}
+
+// 存储文件的加密快照
+export interface VaultBackupInfo {
+  name: string;
+  created_at: string;
+  size: number;
+}
+
+// 快照列表，last_error 为最近一次保存时快照失败的原因
+export interface VaultBackupList {
+  backups: VaultBackupInfo[];
+  last_error: string | null;
+}
+
+// 命名存储
+export interface VaultProfileInfo {
+  name: string;
+  is_active: boolean;
+  is_initialized: boolean;
+  is_unlocked: boolean;
+  storage_path: string;
+}
+
+// 数据目录：命令行 --data-dir、环境变量 SSHMANAGER_DATA_DIR、便携模式或默认位置
+export interface DataDirLocation {
+  path: string;
+  source: 'command_line' | 'environment' | 'portable' | 'default';
+}
+
+// 内置 ssh-agent 状态；SSH_AUTH_SOCK 指向 socket_path 即可使用，存储锁定后自动停止
+export interface AgentStatus {
+  running: boolean;
+  socket_path: string | null;
+  key_ids: string[];
+}
+
+// 代理中单个密钥的限制
+export interface AgentKeyConstraints {
+  confirm?: boolean;
+  lifetime_secs?: number | null;
+  max_uses?: number | null;
+}
+
+export interface AgentKeyOptions {
+  key_id: string;
+  passphrase?: string | null;
+  constraints?: AgentKeyConstraints;
+}
+
+// 连接方进程信息，取决于操作系统是否提供
+export interface AgentPeerInfo {
+  pid: number | null;
+  uid: number | null;
+  process_name: string | null;
+  executable: string | null;
+}
+
+// agent-confirm-request 事件内容
+export interface AgentConfirmRequest {
+  request_id: number;
+  key_id: string;
+  key_name: string;
+  fingerprint: string;
+  peer: AgentPeerInfo | null;
+}
+
+// 系统 ssh-agent（SSH_AUTH_SOCK）中的密钥，key_id 为存储中指纹相同的密钥
+export interface SystemAgentKey {
+  fingerprint: string;
+  algorithm: string;
+  comment: string;
+  key_id: string | null;
+  key_name: string | null;
+}
+
+export interface AgentSignRecord {
+  key_id: string;
+  key_name: string;
+  fingerprint: string;
+  signed_at: string;
+  peer: AgentPeerInfo | null;
+  outcome: 'signed' | 'denied' | 'failed';
+}
+
 // 导入导出类型
 export interface ExportData {
   version: string;
@@ -81,6 +81,21 @@ export function buildFullOptionsFrom(
     return entries
 }
 
+// 工具：选项名不区分大小写，常见选项统一为官方文档的写法
+export function canonicalOptionName(key: string): string {
+    const lower = key.toLowerCase()
+    return COMMON_SSH_HOST_OPTIONS.find((k) => k.toLowerCase() === lower) ?? key
+}
+
+// 工具：把后端返回的小写键转换为常见选项的写法
+export function canonicalizeOptions<T>(options: Record<string, T> | undefined): Record<string, T> {
+    const out: Record<string, T> = {}
+    for (const [k, v] of Object.entries(options ?? {})) {
+        out[canonicalOptionName(k)] = v
+    }
+    return out
+}
+
 // 工具：将键值数组还原为对象，并移除空键或空值
 export function compactOptions(
     arr: Array<{ key: string; value: string }>